@@ -0,0 +1,42 @@
+#!/usr/bin/env python3
+# Build definitions.json for the bot from WordNet, for the words of dictionary.json.
+# Needs nltk with the WordNet corpus:
+#   pip install nltk && python3 -m nltk.downloader wordnet
+# Usage: scripts/definitions.py [dictionary.json] [definitions.json]
+import json
+import sys
+
+from nltk.corpus import wordnet as wn
+
+POS_NAMES = {"n": "noun", "v": "verb", "a": "adjective", "s": "adjective", "r": "adverb"}
+MAX_SENSES = 3
+
+
+def senses(word):
+    out = []
+    for synset in wn.synsets(word)[:MAX_SENSES]:
+        out.append({
+            "pos": POS_NAMES.get(synset.pos(), synset.pos()),
+            "definition": synset.definition(),
+            "examples": synset.examples()[:1],
+        })
+    return out
+
+
+def main():
+    src = sys.argv[1] if len(sys.argv) > 1 else "dictionary.json"
+    dst = sys.argv[2] if len(sys.argv) > 2 else "definitions.json"
+    with open(src) as f:
+        words = json.load(f)
+    entries = {}
+    for word in words:
+        found = senses(word)
+        if found:
+            entries[word] = found
+    with open(dst, "w") as f:
+        json.dump(entries, f)
+    print(f"{len(entries)} of {len(words)} words defined, written to {dst}")
+
+
+if __name__ == "__main__":
+    main()
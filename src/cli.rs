@@ -0,0 +1,64 @@
+// Command line tools, run instead of the bot when the first argument names one.
+//
+// `export` turns a JSON export of everything (from /wd_export) into any other export,
+// with the same filters as the command, so spreadsheets can be made without Discord.
+use crate::commands::util::queries;
+use crate::data::export::{Dataset, Filter, Format, Snapshot};
+use poise::serenity_prelude::GuildId;
+use std::fs;
+
+const USAGE: &str = "\
+Usage: project-wordclash export <everything.json> <scores|players|games|everything> [csv|json]
+                                [--since YYYY-MM-DD] [--until YYYY-MM-DD] [--guild ID]
+Writes the export to standard output.";
+
+// Run the tool named by `args[0]`, if there's one. Returns None to start the bot instead.
+pub fn run(args: &[String]) -> Option<Result<(), String>> {
+    match args.first().map(String::as_str) {
+        Some("export") => Some(export(&args[1..])),
+        Some("help" | "--help" | "-h") => {
+            println!("{}", USAGE);
+            Some(Ok(()))
+        }
+        _ => None,
+    }
+}
+
+fn export(args: &[String]) -> Result<(), String> {
+    let (path, dataset) = match args {
+        [path, dataset, ..] => (path, parse_dataset(dataset)?),
+        _ => return Err(USAGE.to_string()),
+    };
+    let mut format = Format::Csv;
+    let mut filter = Filter::default();
+    let mut rest = args[2..].iter();
+    while let Some(arg) = rest.next() {
+        let mut value = || rest.next().ok_or_else(|| format!("{} needs a value", arg));
+        match arg.as_str() {
+            "csv" => format = Format::Csv,
+            "json" => format = Format::Json,
+            "--since" => filter.since = Some(queries::parse_date(value()?, false).map_err(|e| e.to_string())?),
+            "--until" => filter.until = Some(queries::parse_date(value()?, true).map_err(|e| e.to_string())?),
+            "--guild" => {
+                let id = value()?;
+                filter.guild = Some(GuildId(id.parse().map_err(|_| format!("Not a guild ID: {}", id))?));
+            }
+            _ => return Err(format!("Unknown argument: {}\n{}", arg, USAGE)),
+        }
+    }
+    let text = fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    let snapshot = Snapshot::parse(Dataset::Everything, &text).map_err(|e| format!("{}: {}", path, e))?;
+    let out = snapshot.filtered(&filter).render(dataset, format).map_err(|e| e.to_string())?;
+    print!("{}", out);
+    Ok(())
+}
+
+fn parse_dataset(s: &str) -> Result<Dataset, String> {
+    match s {
+        "scores" => Ok(Dataset::Scores),
+        "players" => Ok(Dataset::Players),
+        "games" => Ok(Dataset::Games),
+        "everything" => Ok(Dataset::Everything),
+        _ => Err(format!("Unknown dataset: {}\n{}", s, USAGE)),
+    }
+}
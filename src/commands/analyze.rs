@@ -0,0 +1,76 @@
+use crate::constants;
+use crate::game::{analysis, GameId};
+use crate::{Context, Error};
+use crate::i18n::{self, tr};
+use tokio::sync::Semaphore;
+use super::util::*;
+
+// Analyses take a while on the blocking threads, so only a few run at once, the rest wait.
+static ANALYSES: Semaphore = Semaphore::const_new(2);
+
+/// Analyse a finished Worduel
+///
+/// For each guess, shows how many words were still possible before and after it,
+/// how much it was expected to reveal, and what a solver would have played.
+/// The game number is shown at the bottom of the game over message.
+#[poise::command(slash_command, category = "Worduel", rename = "wd_analyze")]
+pub async fn analyze(
+    ctx: Context<'_>,
+    #[description = "Game number"] game: GameId,
+) -> Result<(), Error> {
+    let lang = lang(ctx).await;
+    let archived = ctx.data().archive().get(game).await
+        .ok_or(CmdError::NoArchivedGame(game))?;
+
+    ctx.defer().await?;
+    // Can take a moment for long words, keep it off the async threads.
+    let data = ctx.data().clone();
+    let (words, guesses) = (archived.words.clone(), archived.guesses.clone());
+    let permit = ANALYSES.acquire().await?;
+    let sides = tokio::task::spawn_blocking(move || {
+        [0, 1].map(|i| analysis::analyze_side(&data.dict, &words[i], &guesses[i]))
+    }).await?;
+    drop(permit);
+
+    let mut fields = Vec::new();
+    for (i, side) in sides.iter().enumerate() {
+        let mut rows = Vec::new();
+        for a in side {
+            let mut row = tr!(lang, "analyze.guess",
+                guess = a.guess.to_uppercase(),
+                before = a.before,
+                after = a.after,
+                bits = format!("{:.1}", a.actual_bits()),
+                expected = format!("{:.1}", a.expected_bits));
+            if let Some(best) = &a.best_guess {
+                row.push_str(&tr!(lang, "analyze.best",
+                    guess = best.to_uppercase(), bits = format!("{:.1}", a.best_bits)));
+            }
+            rows.push(row);
+        }
+        if rows.is_empty() {
+            rows.push(tr!(lang, "analyze.no_guesses"));
+        }
+        // Long games don't fit in one field, the side goes on in more
+        let name = tr!(lang, "analyze.side", side = i + 1, word = archived.words[i].to_uppercase());
+        for value in queries::split_lines(&rows, constants::EMBED_FIELD_LIMIT) {
+            fields.push((name.clone(), value));
+        }
+    }
+
+    let players = tr!(lang, "analyze.players",
+        a = i18n::user(archived.players[0]), b = i18n::user(archived.players[1]));
+    ctx.send(|m| {
+        m.embed(|e| {
+            e.title(tr!(lang, "analyze.title", game = game))
+                .description(players)
+                .color(ctx.data().config.embed_color);
+            for (name, value) in fields {
+                e.field(name, value, false);
+            }
+            e
+        })
+    })
+    .await?;
+    Ok(())
+}
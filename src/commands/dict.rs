@@ -1,17 +1,33 @@
-use crate::dict::wordmatch;
+use crate::dict::{self, wordmatch};
 use crate::{Context, Error};
+use crate::i18n::{tr, Lang};
+use super::util::*;
+use poise::serenity::utils::MessageBuilder;
+
+// Leaves room for the rest of the message.
+const DESCRIPTION_LIMIT: usize = 1800;
+// How many words the explorer lists at most.
+const EXPLORER_LIMIT: usize = 60;
 
 /// Look up a word in the dictionary
+///
+/// Also shows the word's definitions, if known.
 #[poise::command(prefix_command, slash_command, hide_in_help, category = "Dictionary")]
 pub async fn lookup(
     ctx: Context<'_>,
     #[description = "Word to look up in dictionary"] word: String,
 ) -> Result<(), Error> {
+    let lang = lang(ctx).await;
+    let mut content = MessageBuilder::new();
     if ctx.data().dict.contains(&word) {
-        ctx.say("Found in the dictionary").await?;
+        content.push_line(tr!(lang, "dict.found"));
     } else {
-        ctx.say("Not found in the dictionary").await?;
+        content.push_line(tr!(lang, "dict.not_found"));
+    }
+    if ctx.data().defs.lookup(&word).is_some() {
+        content.push(ctx.data().defs.describe(&word, DESCRIPTION_LIMIT));
     }
+    ctx.say(content.build()).await?;
 
     Ok(())
 }
@@ -25,18 +41,18 @@ pub async fn testmatch(
     #[description = "Base word"] base: String,
     #[description = "Word to test"] word: String,
 ) -> Result<(), Error> {
+    let lang = lang(ctx).await;
     if base.len() != word.len() {
-        ctx.say("Word lengths mismatched!").await?;
+        ctx.say(tr!(lang, "dict.length_mismatch")).await?;
         return Ok(());
     }
 
     let wmatch = wordmatch::match_word(&base, &word);
-    use poise::serenity::utils::MessageBuilder;
     use wordmatch::MatchLetter;
     ctx.send(|m| {
         m.content(
             MessageBuilder::new()
-                .push_line("Match status:")
+                .push_line(tr!(lang, "dict.match_status"))
                 .push_mono(
                     wmatch
                         .iter()
@@ -56,3 +72,141 @@ pub async fn testmatch(
 
     Ok(())
 }
+
+/// Explore the dictionary
+///
+/// Unavailable while you're in a game, no cheating.
+#[poise::command(
+    slash_command,
+    category = "Dictionary",
+    rename = "dict",
+    subcommands("pattern", "anagram", "filter", "counts")
+)]
+pub async fn explore(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+// List what an explorer query found among dictionary words of length `len`,
+// with the guild's word lists applied like ensure_word does:
+// denied words are dropped, allowed and pack words that `fits` are added.
+async fn explorer_reply<'a, I: Iterator<Item = &'a str>, F: Fn(&str) -> bool>(
+    ctx: Context<'_>,
+    lang: Lang,
+    title: String,
+    len: usize,
+    words: I,
+    fits: F,
+) -> Result<(), Error> {
+    let dict = &ctx.data().dict;
+    let words = ctx.data().guilds().with_read(ctx.guild_id(), |g| {
+        let overlay = match g {
+            Some(g) => &g.words,
+            None => return words.map(String::from).collect::<Vec<_>>(),
+        };
+        let mut out: Vec<String> = words
+            .filter(|w| !overlay.deny.contains(*w))
+            .map(String::from)
+            .collect();
+        out.extend(overlay.extras_with_len(dict, len).into_iter().filter(|w| fits(w)).map(String::from));
+        out.sort_unstable();
+        out
+    }).await;
+
+    let mut content = MessageBuilder::new();
+    content.push_bold_line_safe(title);
+    if words.is_empty() {
+        content.push(tr!(lang, "dict.none_found"));
+    } else {
+        content.push_line(tr!(lang, "dict.found_count", n = words.len()));
+        content.push_mono_safe(words.iter().take(EXPLORER_LIMIT).cloned().collect::<Vec<_>>().join(" "));
+        if words.len() > EXPLORER_LIMIT {
+            content.push("\n").push(tr!(lang, "dict.more", n = words.len() - EXPLORER_LIMIT));
+        }
+    }
+    ctx.send(|m| m.content(queries::truncate_message(content.build())).ephemeral(true)).await?;
+    Ok(())
+}
+
+async fn ensure_not_in_game(ctx: Context<'_>) -> CmdResult<()> {
+    if ctx.data().in_game(ctx.author().id).await {
+        return Err(CmdError::ExplorerInGame);
+    }
+    Ok(())
+}
+
+/// Find words matching a pattern
+///
+/// Use ? (or _) for any letter, e.g. s?a?e
+#[poise::command(slash_command)]
+pub async fn pattern(
+    ctx: Context<'_>,
+    #[description = "Pattern, ? stands for any letter"] pattern: String,
+) -> Result<(), Error> {
+    ensure_not_in_game(ctx).await?;
+    let lang = lang(ctx).await;
+    let pattern = pattern.to_lowercase().replace('_', &dict::WILDCARD.to_string());
+    explorer_reply(
+        ctx,
+        lang,
+        tr!(lang, "dict.matching", pattern = pattern),
+        pattern.len(),
+        ctx.data().dict.matching(&pattern),
+        |w| dict::fits_pattern(w, &pattern),
+    ).await
+}
+
+/// Find anagrams of a word
+#[poise::command(slash_command)]
+pub async fn anagram(
+    ctx: Context<'_>,
+    #[description = "Letters to rearrange"] letters: String,
+) -> Result<(), Error> {
+    ensure_not_in_game(ctx).await?;
+    let lang = lang(ctx).await;
+    let letters = letters.to_lowercase();
+    let counts = dict::letter_counts(&letters);
+    explorer_reply(
+        ctx,
+        lang,
+        tr!(lang, "dict.anagrams", letters = letters),
+        letters.len(),
+        ctx.data().dict.anagrams(&letters),
+        |w| dict::letter_counts(w) == counts,
+    ).await
+}
+
+/// Find words of a length containing or excluding letters
+#[poise::command(slash_command)]
+pub async fn filter(
+    ctx: Context<'_>,
+    #[description = "Word length"] length: usize,
+    #[description = "Letters the word must contain"] contains: Option<String>,
+    #[description = "Letters the word must not contain"] excludes: Option<String>,
+) -> Result<(), Error> {
+    ensure_not_in_game(ctx).await?;
+    let contains = contains.unwrap_or_default().to_lowercase();
+    let excludes = excludes.unwrap_or_default().to_lowercase();
+    let (include, exclude) = (dict::letter_counts(&contains), dict::letter_counts(&excludes));
+    let lang = lang(ctx).await;
+    explorer_reply(
+        ctx,
+        lang,
+        tr!(lang, "dict.filter", length = length, contains = contains, excludes = excludes),
+        length,
+        ctx.data().dict.filter(length, &contains, &excludes),
+        |w| dict::fits_letters(w, &include, &exclude),
+    ).await
+}
+
+/// Count dictionary words of each length
+#[poise::command(slash_command)]
+pub async fn counts(ctx: Context<'_>) -> Result<(), Error> {
+    ensure_not_in_game(ctx).await?;
+    let mut content = MessageBuilder::new();
+    content.push_bold_line(tr!(lang(ctx).await, "dict.per_length"));
+    for (len, count) in ctx.data().dict.counts() {
+        content.push_line(format!("{}: {}", len, count));
+    }
+    ctx.send(|m| m.content(content.build()).ephemeral(true)).await?;
+    Ok(())
+}
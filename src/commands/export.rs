@@ -0,0 +1,73 @@
+pub use crate::data::export::{Dataset, Format};
+use crate::data::export::{Filter, Snapshot};
+use crate::{Context, Error};
+use crate::i18n::tr;
+use poise::serenity_prelude as serenity;
+use std::borrow::Cow;
+use super::util::*;
+use perms::Tier;
+
+// Largest file /wd_import reads, Discord's upload limit.
+const IMPORT_LIMIT: u64 = 8 << 20;
+
+/// Download scores, player stats or finished games as a file
+///
+/// Server admins get this server's games and their players,
+/// bot owners can ask for every server's.
+/// Dates are given as YYYY-MM-DD, the last day counts.
+#[poise::command(slash_command, category = "Admin", rename = "wd_export", guild_only, check = "perms::admin", ephemeral)]
+pub async fn export(
+    ctx: Context<'_>,
+    #[description = "What to export"] dataset: Dataset,
+    #[description = "File format (default: CSV)"] format: Option<Format>,
+    #[description = "Only games that ended on this day or later"] since: Option<String>,
+    #[description = "Only games that ended on this day or earlier"] until: Option<String>,
+    #[description = "Every server's games (bot owners only)"] everywhere: Option<bool>,
+) -> Result<(), Error> {
+    let lang = lang(ctx).await;
+    let format = format.unwrap_or(Format::Csv);
+    let guild = match everywhere {
+        Some(true) if perms::tier(ctx).await < Tier::Owner => return Err(CmdError::Forbidden(Tier::Owner).into()),
+        Some(true) => None,
+        _ => ctx.guild_id(),
+    };
+    let filter = Filter {
+        since: since.as_deref().map(|s| queries::parse_date(s, false)).transpose()?,
+        until: until.as_deref().map(|s| queries::parse_date(s, true)).transpose()?,
+        guild,
+    };
+    let snapshot = ctx.data().snapshot(&filter).await;
+    let content = snapshot.render(dataset, format)?;
+    tracing::info!(?dataset, ?format, ?filter, user = %ctx.author().id, "data exported");
+    ctx.send(|m| {
+        m.content(tr!(lang, "export.done", games = snapshot.games.len(), scores = snapshot.scores.len()))
+            .attachment(serenity::AttachmentType::Bytes {
+                data: Cow::Owned(content.into_bytes()),
+                filename: dataset.filename(format),
+            })
+    }).await?;
+    Ok(())
+}
+
+/// Restore scores and finished games from an exported file
+///
+/// Scores of the players in the file are replaced, games are added back.
+/// Games can only be restored from JSON.
+#[poise::command(slash_command, category = "Admin", rename = "wd_import", check = "perms::owner", ephemeral)]
+pub async fn import(
+    ctx: Context<'_>,
+    #[description = "What the file holds"] dataset: Dataset,
+    #[description = "File from /wd_export"] file: serenity::Attachment,
+) -> Result<(), Error> {
+    let lang = lang(ctx).await;
+    if file.size > IMPORT_LIMIT {
+        return Err(CmdError::BadImport(format!("{} is over {} MB", file.filename, IMPORT_LIMIT >> 20)).into());
+    }
+    let bytes = file.download().await?;
+    let text = String::from_utf8(bytes).map_err(|e| CmdError::BadImport(e.to_string()))?;
+    let snapshot = Snapshot::parse(dataset, &text)?;
+    let (scores, games) = ctx.data().restore(snapshot).await;
+    tracing::info!(?dataset, scores, games, user = %ctx.author().id, "data imported");
+    ctx.say(tr!(lang, "import.done", games = games, scores = scores)).await?;
+    Ok(())
+}
@@ -0,0 +1,89 @@
+use crate::game::*;
+use crate::data::CtxData;
+use crate::{Context, Error};
+use crate::i18n::{self, tr, Lang};
+use poise::serenity_prelude as serenity;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use super::queue::variant_name;
+
+/// List your pending Worduel invites
+///
+/// Both the ones you've received and the ones you've sent,
+/// with the time left before they expire.
+#[poise::command(slash_command, category = "Worduel", rename = "wd_invites", ephemeral)]
+pub async fn invites(ctx: Context<'_>) -> Result<(), Error> {
+    let lang = super::util::lang(ctx).await;
+    let (incoming, outgoing) = ctx.data().invites_of(ctx.author().id).await;
+    let now = SystemTime::now();
+
+    ctx.send(|m| {
+        m.embed(|e| {
+            e.title(tr!(lang, "invites.title"))
+                .field(tr!(lang, "invites.received"),
+                    render_invites(lang, &incoming, now, &tr!(lang, "invites.reject_hint")), false)
+                .field(tr!(lang, "invites.sent"),
+                    render_invites(lang, &outgoing, now, &tr!(lang, "invites.cancel_hint")), false)
+                .color(ctx.data().config.embed_color)
+        })
+    }).await?;
+    Ok(())
+}
+
+fn render_invites(
+    lang: Lang, invites: &[(serenity::UserId, GameVariant, SystemTime)], now: SystemTime, hint: &str
+) -> String {
+    if invites.is_empty() {
+        return tr!(lang, "invites.none");
+    }
+    let mut content = serenity::MessageBuilder::new();
+    for &(id, variant, expiry) in invites {
+        let who = if id == multiplayer::OPEN_SEAT {
+            tr!(lang, "invites.open")
+        } else {
+            i18n::user(id).to_string()
+        };
+        content.push_line(tr!(lang, "invites.entry",
+            who = who,
+            variant = variant_name(lang, variant),
+            left = render_remaining(lang, expiry.duration_since(now).unwrap_or_default())));
+    }
+    content.push_italic(hint);
+    content.build()
+}
+
+fn render_remaining(lang: Lang, left: Duration) -> String {
+    let secs = left.as_secs();
+    match secs / 60 {
+        0 => tr!(lang, "time.seconds", s = secs),
+        mins => tr!(lang, "time.minutes", m = mins, s = format!("{:02}", secs % 60)),
+    }
+}
+
+// Drop expired invites and games every cleanup interval (see config.rs),
+// and tell challengers about their expired invites.
+// Runs for as long as the bot does.
+pub async fn cleanup(ctxdata: Arc<CtxData>, http: Arc<serenity::Http>) {
+    let mut timer = tokio::time::interval(ctxdata.config.expiry.cleanup_interval);
+    loop {
+        timer.tick().await;
+        let expired = ctxdata.cleanup(SystemTime::now()).await;
+        tracing::debug!(expired = expired.len(), "cleanup done");
+
+        for (from, to, variant, invite) in expired {
+            let channel = match invite.channel {
+                Some(c) => c,
+                None => continue,
+            };
+            let lang = ctxdata.lang_for(from, None).await;
+            let content = tr!(lang, "invite.expired",
+                user = i18n::user(from),
+                variant = variant_name(lang, variant),
+                enemy = i18n::user(to),
+                game = invite.game);
+            if let Err(e) = channel.say(&http, content).await {
+                tracing::warn!(game = invite.game, user = %from, error = %e, "failed to notify a challenger of an expired invite");
+            }
+        }
+    }
+}
@@ -0,0 +1,40 @@
+use crate::{Context, Error};
+use crate::i18n::{tr, Lang};
+use super::util::{self, perms};
+
+/// Choose the language the bot answers you in
+///
+/// Leave it out to use the server's language again.
+#[poise::command(slash_command, category = "Worduel", rename = "wd_lang", ephemeral)]
+pub async fn lang(
+    ctx: Context<'_>,
+    #[description = "Language"] language: Option<Lang>,
+) -> Result<(), Error> {
+    ctx.data().set_lang(ctx.author().id, language).await;
+    let lang = util::lang(ctx).await;
+    ctx.say(match language {
+        Some(_) => tr!(lang, "lang.user", lang = lang.name()),
+        None => tr!(lang, "lang.user_reset", lang = lang.name()),
+    }).await?;
+    Ok(())
+}
+
+/// Set this server's language
+///
+/// Used for members who haven't chosen their own.
+#[poise::command(
+    slash_command,
+    category = "Admin",
+    rename = "wd_lang_server",
+    guild_only,
+    check = "perms::admin",
+    ephemeral
+)]
+pub async fn lang_server(
+    ctx: Context<'_>,
+    #[description = "Language"] language: Lang,
+) -> Result<(), Error> {
+    ctx.data().guilds().with_write(ctx.guild_id().unwrap(), |g| g.lang = language).await?;
+    ctx.say(tr!(util::lang(ctx).await, "lang.server", lang = language.name())).await?;
+    Ok(())
+}
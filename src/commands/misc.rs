@@ -1,5 +1,5 @@
 use crate::{Context, Error};
-use poise::serenity_prelude as serenity;
+use crate::i18n::tr;
 use rand::prelude::*;
 
 /// Roll dice
@@ -11,17 +11,19 @@ pub async fn roll_dice(
     #[description = "Die size"] die_size: Option<u32>,
     #[description = "Die count"] die_count: Option<u16>,
 ) -> Result<(), Error> {
+    let lang = super::util::lang(ctx).await;
     let die_size = die_size.unwrap_or(6);
     if die_size <= 1 {
         ctx.say(format!(
-            "**Error:** {} is not a valid size of dice.",
-            die_size
+            "{} {}",
+            tr!(lang, "err.prefix"),
+            tr!(lang, "err.bad_die_size", size = die_size),
         ))
         .await?;
         return Ok(());
     }
     let die_count = die_count.unwrap_or(1);
-    ctx.say(format!("Rolling {}d{}...", die_count, die_size))
+    ctx.say(tr!(lang, "roll.rolling", count = die_count, size = die_size))
         .await?;
 
     let roll: u64 = {
@@ -32,12 +34,12 @@ pub async fn roll_dice(
             .sum()
     };
 
-    let mut output = serenity::MessageBuilder::new();
-    output.push("Result: ").push_mono(roll);
+    let mut output = tr!(lang, "roll.result", result = roll);
     if die_count == 1 && die_size == 20 && roll == 20 {
-        output.push("\nDon't let it go to your head.");
+        output.push('\n');
+        output.push_str(&tr!(lang, "roll.nat20"));
     }
-    ctx.say(output.build()).await?;
+    ctx.say(output).await?;
 
     Ok(())
 }
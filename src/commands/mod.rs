@@ -1,6 +1,18 @@
+pub mod analyze;
 pub mod dict;
+pub mod export;
+pub mod invites;
+pub mod lang;
 pub mod misc;
+pub mod moderation;
+pub mod notify;
+pub mod privacy;
+pub mod queue;
 pub mod timedgame;
 pub mod turngame;
 pub mod scores;
-pub mod util;
\ No newline at end of file
+pub mod series;
+pub mod settings;
+pub mod util;
+pub mod watch;
+pub mod words;
\ No newline at end of file
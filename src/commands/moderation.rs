@@ -0,0 +1,167 @@
+use crate::{Context, Error};
+use crate::data::audit::{self, AuditEntry, ModAction};
+use crate::game::GameId;
+use crate::i18n::{self, tr, Lang};
+use poise::serenity_prelude as serenity;
+use std::time::{Duration, SystemTime};
+use super::settings::check_range;
+use super::util::*;
+use perms::Tier;
+
+const DEFAULT_BAN_HOURS: u64 = 24;
+const MAX_BAN_HOURS: u64 = 30 * 24;
+const LOG_ENTRIES: usize = 20;
+
+/// Deal with stuck games and players abusing the bot
+///
+/// Everything done here goes to the moderation log.
+#[poise::command(
+    slash_command,
+    category = "Admin",
+    rename = "wd_mod",
+    guild_only,
+    subcommands("end", "score", "score_reset", "ban", "unban", "log")
+)]
+pub async fn moderation(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// End a game going on here, whatever state it's in
+///
+/// Bot owners can end games of any server.
+#[poise::command(slash_command, guild_only, check = "perms::admin", ephemeral)]
+pub async fn end(
+    ctx: Context<'_>,
+    #[description = "Game number"] game: GameId,
+    #[description = "Count the points scored so far (default: no)"] scores: Option<bool>,
+) -> Result<(), Error> {
+    let lang = lang(ctx).await;
+    let scored = scores.unwrap_or(false);
+    let scope = match perms::tier(ctx).await {
+        Tier::Owner => None,
+        _ => ctx.guild_id(),
+    };
+    let (a, b) = ctx.data().end_game(game, scored, scope).await?;
+    record(ctx, ModAction::EndGame { game, scored }).await?;
+    let key = if scored {"mod.ended_scored"} else {"mod.ended"};
+    ctx.say(tr!(lang, key, game = game, a = i18n::user(a), b = i18n::user(b))).await?;
+    Ok(())
+}
+
+// Scores are shared by every server, so only bot owners may touch them.
+
+/// Add to or take from a player's score
+#[poise::command(slash_command, guild_only, check = "perms::owner", ephemeral)]
+pub async fn score(
+    ctx: Context<'_>,
+    #[description = "Player"] user: serenity::User,
+    #[description = "Points to add, negative to take away"] change: i64,
+) -> Result<(), Error> {
+    let score = ctx.data().scores().adjust(user.id, change).await;
+    record(ctx, ModAction::Score { user: user.id, change, score }).await?;
+    ctx.say(tr!(lang(ctx).await, "mod.score", user = i18n::user(user.id), score = score)).await?;
+    Ok(())
+}
+
+/// Take a player off the leaderboard
+#[poise::command(slash_command, guild_only, check = "perms::owner", ephemeral)]
+pub async fn score_reset(
+    ctx: Context<'_>,
+    #[description = "Player"] user: serenity::User,
+) -> Result<(), Error> {
+    ctx.data().scores().reset(user.id).await;
+    record(ctx, ModAction::ScoreReset { user: user.id }).await?;
+    ctx.say(tr!(lang(ctx).await, "mod.score_reset", user = i18n::user(user.id))).await?;
+    Ok(())
+}
+
+/// Stop a player from challenging anyone here for a while
+///
+/// Games already going on aren't affected.
+#[poise::command(slash_command, guild_only, check = "perms::admin", ephemeral)]
+pub async fn ban(
+    ctx: Context<'_>,
+    #[description = "Player"] user: serenity::User,
+    #[description = "For how many hours (default: 24)"] hours: Option<u64>,
+) -> Result<(), Error> {
+    let lang = lang(ctx).await;
+    let hours = hours.unwrap_or(DEFAULT_BAN_HOURS);
+    check_range("hours", hours, MAX_BAN_HOURS)?;
+    let now = SystemTime::now();
+    let until = now + Duration::from_secs(hours * 3600);
+    let (now, until_secs) = (audit::unix_secs(now), audit::unix_secs(until));
+    ctx.data().guilds().with_write(ctx.guild_id().unwrap(), |g| {
+        // Bans that ran out are forgotten on the way
+        g.bans.retain(|_, until| *until > now);
+        g.bans.insert(user.id, until_secs);
+    }).await?;
+    record(ctx, ModAction::Ban { user: user.id, until: until_secs }).await?;
+    ctx.say(tr!(lang, "mod.banned", user = i18n::user(user.id), until = queries::timestamp(until))).await?;
+    Ok(())
+}
+
+/// Let a banned player challenge others again
+#[poise::command(slash_command, guild_only, check = "perms::admin", ephemeral)]
+pub async fn unban(
+    ctx: Context<'_>,
+    #[description = "Player"] user: serenity::User,
+) -> Result<(), Error> {
+    let lang = lang(ctx).await;
+    let now = SystemTime::now();
+    let was_banned = ctx.data().guilds().with_write(ctx.guild_id().unwrap(), |g| {
+        let banned = g.banned_until(user.id, now).is_some();
+        g.bans.remove(&user.id);
+        banned
+    }).await?;
+    if !was_banned {
+        ctx.say(tr!(lang, "mod.not_banned", user = i18n::user(user.id))).await?;
+        return Ok(());
+    }
+    record(ctx, ModAction::Unban { user: user.id }).await?;
+    ctx.say(tr!(lang, "mod.unbanned", user = i18n::user(user.id))).await?;
+    Ok(())
+}
+
+/// Show what moderators did here lately
+#[poise::command(slash_command, guild_only, check = "perms::admin", ephemeral)]
+pub async fn log(ctx: Context<'_>) -> Result<(), Error> {
+    let lang = lang(ctx).await;
+    let lines = ctx.data().guilds().with_read(ctx.guild_id(), |g| {
+        g.map(|g| g.audit.latest(LOG_ENTRIES).map(|e| render_entry(lang, e)).collect::<Vec<_>>())
+    }).await.unwrap_or_default();
+    let description = match lines.is_empty() {
+        true => tr!(lang, "audit.empty"),
+        false => queries::truncate_message(lines.join("\n")),
+    };
+    ctx.send(|m| {
+        m.embed(|e| {
+            e.title(tr!(lang, "audit.title"))
+                .description(description)
+                .color(ctx.data().config.embed_color)
+        })
+    }).await?;
+    Ok(())
+}
+
+// Put a moderation action on the guild's record.
+async fn record(ctx: Context<'_>, action: ModAction) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().unwrap();
+    tracing::info!(guild = %guild_id, moderator = %ctx.author().id, ?action, "moderation");
+    ctx.data().guilds().with_write(guild_id, |g| g.audit.record(ctx.author().id, action)).await?;
+    Ok(())
+}
+
+fn render_entry(lang: Lang, entry: &AuditEntry) -> String {
+    let at = |secs| queries::timestamp(SystemTime::UNIX_EPOCH + Duration::from_secs(secs));
+    let what = match &entry.action {
+        ModAction::EndGame { game, scored: false } => tr!(lang, "audit.end_game", game = game),
+        ModAction::EndGame { game, scored: true } => tr!(lang, "audit.end_game_scored", game = game),
+        ModAction::Score { user, change, score } => {
+            tr!(lang, "audit.score", user = i18n::user(*user), change = format!("{:+}", change), score = score)
+        }
+        ModAction::ScoreReset { user } => tr!(lang, "audit.score_reset", user = i18n::user(*user)),
+        ModAction::Ban { user, until } => tr!(lang, "audit.ban", user = i18n::user(*user), until = at(*until)),
+        ModAction::Unban { user } => tr!(lang, "audit.unban", user = i18n::user(*user)),
+    };
+    format!("{} {}: {}", at(entry.at), i18n::user(entry.moderator), what)
+}
@@ -0,0 +1,121 @@
+use crate::data::CtxData;
+pub use crate::data::notify::NotifyEvent;
+use crate::{Context, Error};
+use crate::i18n::{tr, Lang};
+use poise::serenity_prelude as serenity;
+use serenity::{ChannelId, GuildId, Mention, UserId};
+use crate::game::{GameId, GameVariant};
+use super::queue::variant_name;
+
+/// Choose what the bot sends you direct messages about
+///
+/// Without options, shows your current choices.
+/// Without an event, applies to all of them.
+#[poise::command(slash_command, category = "Worduel", rename = "wd_notify", ephemeral)]
+pub async fn notify(
+    ctx: Context<'_>,
+    #[description = "Event to be told about"] event: Option<NotifyEvent>,
+    #[description = "Send DMs about it"] enabled: Option<bool>,
+) -> Result<(), Error> {
+    let own_id = ctx.author().id;
+    let lang = super::util::lang(ctx).await;
+    let prefs = match enabled {
+        Some(on) => ctx.data().set_notify(own_id, event, on).await,
+        None => match ctx.data().find_user(own_id).await {
+            Some(handle) => handle.lock().await.notify,
+            None => Default::default(),
+        },
+    };
+
+    ctx.send(|m| {
+        m.embed(|e| {
+            e.title(tr!(lang, "notify.title"))
+                .description(prefs.render(lang))
+                .color(ctx.data().config.embed_color)
+        })
+    }).await?;
+    Ok(())
+}
+
+// DM `user_id` about `event`, if they want to hear about it.
+// The note is written in their language, falling back to that of `guild`.
+// Failures only concern that user, so they're logged rather than returned.
+pub async fn dm(
+    http: &serenity::Http,
+    data: &CtxData,
+    user_id: UserId,
+    guild: Option<GuildId>,
+    event: NotifyEvent,
+    note: impl FnOnce(Lang) -> String,
+) {
+    if !data.wants_dm(user_id, event).await {
+        return;
+    }
+    let content = note(data.lang_for(user_id, guild).await);
+    let res = match user_id.create_dm_channel(http).await {
+        Ok(channel) => channel.say(http, content).await.map(|_| ()),
+        Err(e) => Err(e),
+    };
+    if let Err(e) = res {
+        tracing::warn!(user = %user_id, error = %e, "failed to send a DM");
+    }
+}
+
+// DM text for a new challenge, pointing to where it was made.
+pub fn challenge_note(lang: Lang, challenger: &str, variant: GameVariant, channel: ChannelId) -> String {
+    tr!(lang, "notify.challenged",
+        name = challenger,
+        variant = variant_name(lang, variant),
+        channel = Mention::from(channel),
+        command = accept_command(variant))
+}
+
+// DM text for an accepted challenge.
+pub fn accept_note(lang: Lang, name: &str, variant: GameVariant, channel: ChannelId) -> String {
+    tr!(lang, "notify.accepted", name = name, variant = variant_name(lang, variant), channel = Mention::from(channel))
+}
+
+pub fn accept_command(variant: GameVariant) -> &'static str {
+    match variant {
+        GameVariant::Timed => "/wd_accept",
+        GameVariant::TurnBased => "/wdturn_accept",
+    }
+}
+
+// DM text for a finished game, from the point of view of the one told.
+pub fn result_note(lang: Lang, game_id: GameId, opponent: &str, won: Option<bool>) -> String {
+    let key = match won {
+        Some(true) => "notify.won",
+        Some(false) => "notify.lost",
+        None => "notify.draw",
+    };
+    tr!(lang, key, name = opponent, game = game_id)
+}
+
+// Post to the guild's announcement channel, in the guild's language,
+// unless there's none or it's the channel the command was used in anyway.
+// Mentions don't ping, results shouldn't wake anyone up.
+pub async fn announce(ctx: Context<'_>, note: impl FnOnce(Lang) -> String) {
+    let guild = ctx.guild_id();
+    let channel = match ctx.data().guilds().with_read(guild, |g| g.and_then(|g| g.announcements)).await {
+        Some(c) if c != ctx.channel_id() => c,
+        _ => return,
+    };
+    let content = note(ctx.data().guild_lang(guild).await);
+    let res = channel.send_message(&ctx.discord().http, |m| {
+        m.content(content).allowed_mentions(|a| a.empty_parse())
+    }).await;
+    if let Err(e) = res {
+        tracing::warn!(%channel, error = %e, "failed to post an announcement");
+    }
+}
+
+// Announcement of a finished game between `a` and `b`, `won` telling whether `a` won.
+pub fn result_announcement(lang: Lang, game_id: GameId, a: UserId, b: UserId, won: Option<bool>) -> String {
+    let (a, b) = (Mention::from(a), Mention::from(b));
+    match won {
+        Some(true) => tr!(lang, "announce.won", winner = a, loser = b, game = game_id),
+        Some(false) => tr!(lang, "announce.won", winner = b, loser = a, game = game_id),
+        None => tr!(lang, "announce.draw", a = a, b = b, game = game_id),
+    }
+}
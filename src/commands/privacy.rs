@@ -0,0 +1,79 @@
+pub use crate::data::privacy::ChallengeFrom;
+use crate::data::privacy::PrivacyPrefs;
+use crate::{Context, Error};
+use crate::i18n::{self, tr, Lang};
+use poise::serenity_prelude as serenity;
+use super::util::*;
+
+/// Choose who may challenge you
+///
+/// Without options, shows your current choices.
+/// Friends may always challenge you, unless you don't want to be disturbed.
+/// Do not disturb doesn't apply to matchmaking: joining the queue asks for a game,
+/// so you can still be paired with anyone you haven't blocked.
+#[poise::command(slash_command, category = "Worduel", rename = "wd_privacy", ephemeral)]
+pub async fn privacy(
+    ctx: Context<'_>,
+    #[description = "Turn down all challenges, matchmaking aside"] do_not_disturb: Option<bool>,
+    #[description = "Who else may challenge you"] challenges_from: Option<ChallengeFrom>,
+    #[description = "Role for challenges from members of a role"] role: Option<serenity::Role>,
+) -> Result<(), Error> {
+    let lang = lang(ctx).await;
+    let prefs = ctx.data().set_privacy(ctx.author().id, |p| {
+        p.dnd = do_not_disturb.unwrap_or(p.dnd);
+        p.from = challenges_from.unwrap_or(p.from);
+        if let Some(role) = &role {
+            p.role = Some(role.id);
+        }
+    }).await;
+    show(ctx, lang, &prefs).await
+}
+
+/// Stop someone from challenging you
+///
+/// Their waiting invites are turned down, they aren't told why.
+#[poise::command(slash_command, category = "Worduel", rename = "wd_block", ephemeral)]
+pub async fn block(
+    ctx: Context<'_>,
+    #[description = "User"] user: serenity::User,
+    #[description = "Whether they're blocked (default: yes)"] blocked: Option<bool>,
+) -> Result<(), Error> {
+    let lang = lang(ctx).await;
+    let blocked = blocked.unwrap_or(true);
+    if user.id == ctx.author().id {
+        return Err(CmdError::ChallengedSelf.into());
+    }
+    ctx.data().block(ctx.author().id, user.id, blocked).await;
+    let key = if blocked {"privacy.blocked_user"} else {"privacy.unblocked_user"};
+    ctx.say(tr!(lang, key, user = i18n::user(user.id))).await?;
+    Ok(())
+}
+
+/// Let someone challenge you whoever else may
+#[poise::command(slash_command, category = "Worduel", rename = "wd_friend", ephemeral)]
+pub async fn friend(
+    ctx: Context<'_>,
+    #[description = "User"] user: serenity::User,
+    #[description = "Whether they're your friend (default: yes)"] friend: Option<bool>,
+) -> Result<(), Error> {
+    let lang = lang(ctx).await;
+    let friend = friend.unwrap_or(true);
+    if user.id == ctx.author().id {
+        return Err(CmdError::ChallengedSelf.into());
+    }
+    ctx.data().set_privacy(ctx.author().id, |p| p.befriend(user.id, friend)).await;
+    let key = if friend {"privacy.friended"} else {"privacy.unfriended"};
+    ctx.say(tr!(lang, key, user = i18n::user(user.id))).await?;
+    Ok(())
+}
+
+async fn show(ctx: Context<'_>, lang: Lang, prefs: &PrivacyPrefs) -> Result<(), Error> {
+    ctx.send(|m| {
+        m.embed(|e| {
+            e.title(tr!(lang, "privacy.title"))
+                .description(prefs.render(lang))
+                .color(ctx.data().config.embed_color)
+        })
+    }).await?;
+    Ok(())
+}
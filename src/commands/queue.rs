@@ -0,0 +1,93 @@
+use crate::game::*;
+use crate::data::CtxData;
+use crate::{Context, Error};
+use crate::i18n::{self, tr, Lang};
+use poise::serenity_prelude as serenity;
+use std::sync::Arc;
+use std::time::SystemTime;
+use super::util::*;
+
+/// Wait for an opponent of similar skill
+///
+/// Once matched, the game starts right away with random words on both sides.
+/// The longer you wait, the wider the range of opponents considered.
+#[poise::command(slash_command, category = "Worduel", rename = "wd_queue", ephemeral)]
+pub async fn queue(
+    ctx: Context<'_>,
+    #[description = "Word length (default: the server's)"] length: Option<usize>,
+    #[description = "Play turn-based instead of timed"] turn_based: Option<bool>,
+) -> Result<(), Error> {
+    let variant = match turn_based {
+        Some(true) => GameVariant::TurnBased,
+        _ => GameVariant::Timed,
+    };
+    let lang = lang(ctx).await;
+    let length = match length {
+        Some(l) => l,
+        None => ctx.data().default_length(ctx.guild_id()).await,
+    };
+    ctx.data().join_queue(ctx.author().id, variant, length, ctx.channel_id(), ctx.guild_id()).await?;
+    ctx.say(tr!(lang, "queue.joined", variant = variant_name(lang, variant), length = length)).await?;
+    Ok(())
+}
+
+/// Stop waiting for an opponent
+#[poise::command(slash_command, category = "Worduel", rename = "wd_queue_leave", ephemeral)]
+pub async fn queue_leave(ctx: Context<'_>) -> Result<(), Error> {
+    if !ctx.data().queue().leave(ctx.author().id).await {
+        return Err(CmdError::NotQueued.into());
+    }
+    ctx.say(tr!(lang(ctx).await, "queue.left")).await?;
+    Ok(())
+}
+
+pub fn variant_name(lang: Lang, variant: GameVariant) -> String {
+    match variant {
+        GameVariant::Timed => tr!(lang, "variant.timed"),
+        GameVariant::TurnBased => tr!(lang, "variant.turn"),
+    }
+}
+
+// Pair up queued players every matchmaking interval and tell them about it.
+// Runs for as long as the bot does.
+pub async fn matchmaking(ctxdata: Arc<CtxData>, http: Arc<serenity::Http>) {
+    let mut timer = tokio::time::interval(ctxdata.config.matchmaking.interval);
+    loop {
+        timer.tick().await;
+        let (pairs, expired) = ctxdata.queue().pair(SystemTime::now()).await;
+
+        for entry in expired {
+            let lang = ctxdata.guild_lang(entry.guild).await;
+            let content = tr!(lang, "queue.expired", user = i18n::user(entry.id));
+            if let Err(e) = entry.channel.say(&http, content).await {
+                tracing::warn!(user = %entry.id, error = %e, "failed to notify a queued player");
+            }
+        }
+
+        for (key, a, b) in pairs {
+            // Both players hear about it in the channel they queued in
+            let lang = ctxdata.guild_lang(a.guild).await;
+            let (ua, ub) = (i18n::user(a.id), i18n::user(b.id));
+            let content = match ctxdata.start_matched(key, &a, &b).await {
+                Ok(game_id) => tr!(lang, "queue.matched",
+                    a = ua, b = ub, game = game_id,
+                    variant = variant_name(lang, key.0), length = key.1,
+                    command = match key.0 {
+                        GameVariant::Timed => "/wd_send",
+                        GameVariant::TurnBased => "/wdturn_send",
+                    }),
+                Err(e) => tr!(lang, "queue.match_failed", a = ua, b = ub, error = e.localize(lang)),
+            };
+            let mut channels = vec![a.channel];
+            if b.channel != a.channel {
+                channels.push(b.channel);
+            }
+            for channel in channels {
+                if let Err(e) = channel.say(&http, &content).await {
+                    tracing::warn!(%channel, error = %e, "failed to notify matched players");
+                }
+            }
+        }
+    }
+}
+
@@ -1,30 +1,38 @@
 use crate::{Context, Error};
+use crate::data::guilds::LeaderboardMode;
+use crate::i18n::{self, tr};
 use super::util::*;
-use poise::serenity_prelude as serenity;
 
 /// List up to top n scores in the leaderboard
 /// n maximum 50, minimum 1, default 10.
+///
+/// Servers decide whether the whole channel sees it.
 #[poise::command(slash_command, category = "Worduel", rename = "wd_top", ephemeral)]
 pub async fn leaderboard(
     ctx: Context<'_>,
     #[description = "Result size"] count: Option<usize>,
 ) -> Result<(), Error> {
+    let lang = lang(ctx).await;
     let count = count.unwrap_or(10);
-    if count < 1 || count > 50 {
-        return Err(CmdError::Misc("You cannot list this many top players".to_string()).into());
+    if !(1..=50).contains(&count) {
+        return Err(CmdError::BadListSize.into());
+    }
+
+    let mode = ctx.data().guilds().with_read(ctx.guild_id(), |g| g.map(|g| g.leaderboard)).await;
+    let mode = mode.unwrap_or_default();
+    if mode == LeaderboardMode::Off {
+        return Err(CmdError::LeaderboardOff.into());
     }
 
     let scores = ctx.data().scores().list_top(count).await;
-    let mut result = serenity::MessageBuilder::new();
-    for i in 0..scores.len() {
-        let (user, score) = &scores[i];
-        result.push(i+1).push(". ");
-        result.user(user);
-        result.push(": ").push(score).push(" pts\n");
+    let mut result = String::new();
+    for (i, (user, score)) in scores.iter().enumerate() {
+        result.push_str(&tr!(lang, "scores.entry", rank = i+1, user = i18n::user(*user), score = score));
+        result.push('\n');
     }
-    if scores.len() == 0 {
-        result.push("No one has won a game yet");
+    if scores.is_empty() {
+        result = tr!(lang, "scores.empty");
     }
-    ctx.say(result.build()).await?;
+    ctx.send(|m| m.content(result).ephemeral(mode == LeaderboardMode::Private)).await?;
     Ok(())
 }
\ No newline at end of file
@@ -0,0 +1,141 @@
+use crate::game::*;
+use crate::{CtxData, Error};
+use crate::Context;
+use crate::i18n::{self, tr, Lang};
+use poise::serenity_prelude as serenity;
+use std::sync::Arc;
+use super::notify::{self, NotifyEvent};
+use super::queue::variant_name;
+
+// Custom ID of rematch buttons, followed by the game ID.
+const REMATCH_PREFIX: &str = "wd_rematch:";
+
+/// Challenge an user to a best-of-N Worduel series
+///
+/// The first game starts like a normal challenge.
+/// Afterwards you take turns challenging each other,
+/// other games between the two of you join the series.
+#[poise::command(slash_command, category = "Worduel", rename = "wd_series", ephemeral)]
+pub async fn series(
+    ctx: Context<'_>,
+    #[description = "Challenged user"] user: serenity::User,
+    #[description = "Number of games (3, 5 or 7)"] best_of: usize,
+    #[description = "Challenge word for the first game (default: a random one)"] word: Option<String>,
+    #[description = "Play turn-based instead of timed"] turn_based: Option<bool>,
+) -> Result<(), Error> {
+    let variant = match turn_based {
+        Some(true) => GameVariant::TurnBased,
+        _ => GameVariant::Timed,
+    };
+    let lang = super::util::lang(ctx).await;
+    let word = ctx.data().player_word(ctx.guild_id(), word.as_deref()).await?;
+    let roles = ctx.author_member().await.map(|m| m.roles).unwrap_or_default();
+    let options = ChallengeOptions { guild: ctx.guild_id(), channel: Some(ctx.channel_id()), roles, ..Default::default() };
+    let game_id = ctx.data().start_series(
+        ctx.author().id, user.id, best_of, word.clone(), variant, options,
+    ).await?;
+
+    let (word_length, max_guesses) = {
+        let game_handle = ctx.data().game(game_id).await.unwrap();
+        let gamedata = game_handle.lock().await;
+        (gamedata.get_word_length(), gamedata.get_max_guesses())
+    };
+
+    ctx.say(tr!(lang, "series.created", word = word)).await?;
+
+    ctx.channel_id().send_message(&ctx.discord().http, |m| {
+        m.content(tr!(lang, "series.public", best_of = best_of, user = i18n::user(user.id)))
+        .embed(|e| {
+            e.title(tr!(lang, "series.title"))
+                .description(tr!(lang, "series.terms",
+                    length = word_length,
+                    guesses = max_guesses,
+                    command = notify::accept_command(variant)))
+                .color(ctx.data().config.embed_color)
+        })
+    })
+    .await?;
+    let (name, channel) = (&ctx.author().name, ctx.channel_id());
+    notify::dm(&ctx.discord().http, ctx.data(), user.id, ctx.guild_id(), NotifyEvent::Challenged, |lang| {
+        tr!(lang, "notify.challenged_series",
+            name = name,
+            variant = variant_name(lang, variant),
+            channel = serenity::Mention::from(channel),
+            command = notify::accept_command(variant),
+            best_of = best_of)
+    }).await;
+    Ok(())
+}
+
+// Series standing after a game, for the game-over embed.
+pub fn series_field(series: &Series, lang: Lang) -> String {
+    let outcome = match (series.is_over(), series.winner()) {
+        (true, Some(w)) => tr!(lang, "series.winner", user = i18n::user(w)),
+        (true, None) => tr!(lang, "series.draw"),
+        (false, _) => {
+            let picker = series.next_picker();
+            let [a, b] = series.get_players();
+            tr!(lang, "series.next", picker = i18n::user(picker), other = i18n::user(if picker == a { b } else { a }))
+        }
+    };
+    format!("{}\n{}", series.render(lang), outcome)
+}
+
+pub fn rematch_button(
+    c: &mut serenity::CreateComponents, game_id: GameId, lang: Lang
+) -> &mut serenity::CreateComponents {
+    c.create_action_row(|r| {
+        r.create_button(|b| {
+            b.custom_id(format!("{}{}", REMATCH_PREFIX, game_id))
+                .label(tr!(lang, "rematch.button"))
+                .style(serenity::ButtonStyle::Primary)
+        })
+    })
+}
+
+// Handles presses of rematch buttons, which outlive the commands that posted them.
+pub fn listener<'a>(
+    ctx: &'a serenity::Context,
+    event: &'a poise::Event<'a>,
+    _framework: poise::FrameworkContext<'a, Arc<CtxData>, Error>,
+    data: &'a Arc<CtxData>,
+) -> poise::BoxFuture<'a, Result<(), Error>> {
+    Box::pin(async move {
+        let press = match event {
+            poise::Event::InteractionCreate { interaction: serenity::Interaction::MessageComponent(press) } => press,
+            _ => return Ok(()),
+        };
+        let game_id: GameId = match press.data.custom_id.strip_prefix(REMATCH_PREFIX).and_then(|id| id.parse().ok()) {
+            Some(id) => id,
+            None => return Ok(()),
+        };
+
+        let lang = data.lang_for(press.user.id, press.guild_id).await;
+        let mut rematch = None;
+        let roles = press.member.as_ref().map(|m| m.roles.clone()).unwrap_or_default();
+        let (content, ephemeral) = match data.rematch(press.user.id, game_id, Some(press.channel_id), roles).await {
+            Ok((new_id, enemy_id)) => {
+                rematch = Some((new_id, enemy_id));
+                (tr!(lang, "rematch.public",
+                    enemy = i18n::user(enemy_id), user = i18n::user(press.user.id), game = new_id),
+                false)
+            }
+            Err(e) => (e.localize(lang), true),
+        };
+        press.create_interaction_response(&ctx.http, |r| {
+            r.kind(serenity::InteractionResponseType::ChannelMessageWithSource)
+                .interaction_response_data(|d| d.content(content).ephemeral(ephemeral))
+        })
+        .await?;
+        if let Some((new_id, enemy_id)) = rematch {
+            notify::dm(&ctx.http, data, enemy_id, press.guild_id, NotifyEvent::Challenged, |lang| {
+                tr!(lang, "notify.rematch",
+                    name = press.user.name,
+                    game = game_id,
+                    channel = serenity::Mention::from(press.channel_id),
+                    new_game = new_id)
+            }).await;
+        }
+        Ok(())
+    })
+}
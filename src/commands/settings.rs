@@ -0,0 +1,177 @@
+use crate::{Context, Error};
+use crate::data::guilds::{GuildData, LeaderboardMode};
+use crate::i18n::{tr, Lang};
+use poise::serenity_prelude as serenity;
+use serenity::Mentionable;
+use super::util::*;
+
+// Bounds of the guild settings that are numbers.
+const MAX_GUESSES: u64 = 12;
+const MAX_INVITE_MINUTES: u64 = 24 * 60;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, poise::ChoiceParameter)]
+pub enum Setting {
+    #[name = "Game channels"]
+    GameChannels,
+    #[name = "Word length"]
+    WordLength,
+    #[name = "Guesses"]
+    Guesses,
+    #[name = "Invite expiry"]
+    InviteExpiry,
+    #[name = "Random words"]
+    RandomWords,
+    #[name = "Leaderboard"]
+    Leaderboard,
+    #[name = "Announcements"]
+    Announcements,
+}
+
+/// Change how Worduel works on this server
+///
+/// Without options, shows the current settings.
+/// Once a channel is allowed, games can only be started in allowed channels.
+#[poise::command(
+    slash_command,
+    category = "Admin",
+    rename = "wd_config",
+    guild_only,
+    check = "perms::admin",
+    ephemeral
+)]
+#[allow(clippy::too_many_arguments)]
+pub async fn config(
+    ctx: Context<'_>,
+    #[description = "Allow starting games in this channel"] allow_channel: Option<serenity::GuildChannel>,
+    #[description = "Stop allowing games in this channel"] disallow_channel: Option<serenity::GuildChannel>,
+    #[description = "Length of random words when no word is given"] word_length: Option<usize>,
+    #[description = "Guesses per game, instead of one more than the word length"] guesses: Option<u64>,
+    #[description = "Minutes before invites expire"] invite_expiry: Option<u64>,
+    #[description = "Let players ask for random words"] random_words: Option<bool>,
+    #[description = "Who sees the leaderboard"] leaderboard: Option<LeaderboardMode>,
+    #[description = "Channel to post game results in"] announcements: Option<serenity::GuildChannel>,
+    #[description = "Setting to put back to its default"] reset: Option<Setting>,
+) -> Result<(), Error> {
+    let lang = lang(ctx).await;
+    let guild_id = ctx.guild_id().unwrap();
+    if let Some(length) = word_length {
+        queries::test_length(&ctx.data().config.words, length)?;
+    }
+    if let Some(n) = guesses {
+        check_range("guesses", n, MAX_GUESSES)?;
+    }
+    if let Some(n) = invite_expiry {
+        check_range("invite_expiry", n, MAX_INVITE_MINUTES)?;
+    }
+
+    let guild = ctx.data().guilds().with_write(guild_id, |g| {
+        match reset {
+            Some(Setting::GameChannels) => g.game_channels.clear(),
+            Some(Setting::WordLength) => g.word_length = None,
+            Some(Setting::Guesses) => g.guesses = None,
+            Some(Setting::InviteExpiry) => g.invite_expiry = None,
+            Some(Setting::RandomWords) => g.random_words = true,
+            Some(Setting::Leaderboard) => g.leaderboard = LeaderboardMode::default(),
+            Some(Setting::Announcements) => g.announcements = None,
+            None => {}
+        }
+        if let Some(c) = allow_channel {
+            g.game_channels.insert(c.id);
+        }
+        if let Some(c) = disallow_channel {
+            g.game_channels.remove(&c.id);
+        }
+        g.word_length = word_length.or(g.word_length);
+        g.guesses = guesses.map(|n| n as usize).or(g.guesses);
+        g.invite_expiry = invite_expiry.map(|m| m * 60).or(g.invite_expiry);
+        g.random_words = random_words.unwrap_or(g.random_words);
+        g.leaderboard = leaderboard.unwrap_or(g.leaderboard);
+        g.announcements = announcements.map(|c| c.id).or(g.announcements);
+        g.clone()
+    }).await?;
+
+    let default_length = ctx.data().config.words.default_length;
+    ctx.send(|m| {
+        m.embed(|e| {
+            e.title(tr!(lang, "settings.title"))
+                .description(render_settings(lang, &guild, default_length))
+                .color(ctx.data().config.embed_color)
+        })
+    }).await?;
+    Ok(())
+}
+
+/// Let members with a role manage Worduel on this server
+///
+/// Members who can manage the server always can.
+#[poise::command(
+    slash_command,
+    category = "Admin",
+    rename = "wd_admin_role",
+    guild_only,
+    check = "perms::admin",
+    ephemeral
+)]
+pub async fn admin_role(
+    ctx: Context<'_>,
+    #[description = "Role"] role: serenity::Role,
+    #[description = "Whether it's an admin role (default: yes)"] admin: Option<bool>,
+) -> Result<(), Error> {
+    let lang = lang(ctx).await;
+    let admin = admin.unwrap_or(true);
+    ctx.data().guilds().with_write(ctx.guild_id().unwrap(), |g| match admin {
+        true => g.admin_roles.insert(role.id),
+        false => g.admin_roles.remove(&role.id),
+    }).await?;
+    tracing::info!(guild = %role.guild_id, role = %role.id, admin, user = %ctx.author().id, "admin role changed");
+    ctx.say(match admin {
+        true => tr!(lang, "settings.admin_role_added", role = role.name),
+        false => tr!(lang, "settings.admin_role_removed", role = role.name),
+    }).await?;
+    Ok(())
+}
+
+pub fn check_range(name: &'static str, value: u64, max: u64) -> CmdResult<()> {
+    if !(1..=max).contains(&value) {
+        return Err(CmdError::BadSetting { name, min: 1, max });
+    }
+    Ok(())
+}
+
+fn render_settings(lang: Lang, g: &GuildData, default_length: usize) -> String {
+    let channels = match g.game_channels.is_empty() {
+        true => tr!(lang, "settings.any_channel"),
+        false => g.game_channels.iter().map(|c| c.mention().to_string()).collect::<Vec<_>>().join(", "),
+    };
+    let guesses = match g.guesses {
+        Some(n) => n.to_string(),
+        None => tr!(lang, "settings.guesses_default"),
+    };
+    let expiry = match g.invite_expiry {
+        Some(secs) => tr!(lang, "settings.minutes", m = secs / 60),
+        None => tr!(lang, "settings.expiry_default"),
+    };
+    let leaderboard = match g.leaderboard {
+        LeaderboardMode::Private => tr!(lang, "settings.leaderboard_private"),
+        LeaderboardMode::Public => tr!(lang, "settings.leaderboard_public"),
+        LeaderboardMode::Off => tr!(lang, "settings.leaderboard_off"),
+    };
+    let announcements = match g.announcements {
+        Some(c) => c.mention().to_string(),
+        None => tr!(lang, "settings.none"),
+    };
+    let admin_roles = match g.admin_roles.is_empty() {
+        true => tr!(lang, "settings.none"),
+        false => g.admin_roles.iter().map(|r| r.mention().to_string()).collect::<Vec<_>>().join(", "),
+    };
+    [
+        tr!(lang, "settings.game_channels", value = channels),
+        tr!(lang, "settings.word_length", value = g.word_length.unwrap_or(default_length)),
+        tr!(lang, "settings.guesses", value = guesses),
+        tr!(lang, "settings.invite_expiry", value = expiry),
+        tr!(lang, "settings.random_words", value = tr!(lang, if g.random_words { "settings.on" } else { "settings.off" })),
+        tr!(lang, "settings.leaderboard", value = leaderboard),
+        tr!(lang, "settings.announcements", value = announcements),
+        tr!(lang, "settings.admin_roles", value = admin_roles),
+    ].join("\n")
+}
@@ -1,53 +1,147 @@
 use crate::constants;
 use crate::game::*;
 use crate::{Context, Error};
+use crate::i18n::{self, tr, Lang};
 use poise::serenity_prelude as serenity;
 use super::util::*;
+use super::watch::Broadcast;
+use super::series::{rematch_button, series_field};
+use super::notify::{self, NotifyEvent};
 
 /// Challenge an user to a timed Worduel
 ///
 /// Supplied word must be within reasonable length bounds
 /// and appear in the dictionary.
-/// Alternatively, if it's an integer, a random word of that length will be chosen.
+/// Alternatively, if it's an integer, a random word of that length will be chosen,
+/// and if it's left out, a random word of the server's default length.
 #[poise::command(slash_command, category = "Worduel", rename = "wd_challenge", ephemeral)]
 pub async fn challenge(
     ctx: Context<'_>,
     #[description = "Challenged user"] user: serenity::User,
-    #[description = "Challenge word"] word: String,
+    #[description = "Challenge word (default: a random one)"] word: Option<String>,
+    #[description = "Player getting the handicap (default: the challenged)"] handicap_for: Option<serenity::User>,
+    #[description = "Handicap: extra guesses"] extra_guesses: Option<usize>,
+    #[description = "Handicap: seconds taken off their time"] time_bonus: Option<u64>,
+    #[description = "Handicap: reveal the first letter of their word"] first_letter: Option<bool>,
 ) -> Result<(), Error> {
-    let word = queries::ensure_word(&ctx.data().dict, &word)?;
+    let lang = lang(ctx).await;
+    let word = ctx.data().player_word(ctx.guild_id(), word.as_deref()).await?;
+    let handicap = multiplayer::Handicap {
+        extra_guesses: extra_guesses.unwrap_or(0),
+        time_bonus: time_bonus.unwrap_or(0),
+        first_letter: first_letter.unwrap_or(false),
+    };
+    let limits = &ctx.data().config.handicaps;
+    let handicaps = queries::handicaps(limits, ctx.author().id, user.id, handicap_for.map(|u| u.id), handicap)?;
 
-    let game_id = ctx.data().challenge_player(
-        ctx.author().id, user.id, word.clone(), GameVariant::Timed
+    let roles = ctx.author_member().await.map(|m| m.roles).unwrap_or_default();
+    let options = ChallengeOptions {
+        guild: ctx.guild_id(),
+        handicaps,
+        channel: Some(ctx.channel_id()),
+        roles,
+        matched: false,
+    };
+    let game_id = ctx.data().challenge_with(
+        ctx.author().id, user.id, word.clone(), GameVariant::Timed, options
     ).await?;
 
-    let mplock = ctx.data().mpgames.read().await;
-    let gamedata = mplock.get(&game_id).unwrap(); // why wouldn't it exist?
+    // Copy out what the embed needs, so the game isn't locked during HTTP requests
+    let (word_length, max_guesses, handicaps) = {
+        let game_handle = ctx.data().game(game_id).await.unwrap(); // why wouldn't it exist?
+        let gamedata = game_handle.lock().await;
+        (gamedata.get_word_length(), gamedata.get_max_guesses(), gamedata.render_handicaps(lang))
+    };
 
-    ctx.say(format!("Created game with word: {}", word)).await?;
+    ctx.say(tr!(lang, "challenge.created", word = word)).await?;
 
     ctx.channel_id().send_message(&ctx.discord().http, |m| {
-        m.content(
-            serenity::MessageBuilder::new()
-                .push("You have been challenged to a Worduel, ")
-                .user(user)
-                .push("!")
-                .build(),
-        )
+        m.content(tr!(lang, "challenge.public_timed", user = i18n::user(user.id)))
         .embed(|e| {
-            e.title("Worduel challenge")
-                .description(format!(
-                    "Word length: {}\nMax guesses: {}",
-                    gamedata.get_word_length(),
-                    gamedata.get_max_guesses(),
-                ))
-                .color((255, 204, 11))
+            e.title(tr!(lang, "challenge.title_timed"))
+                .description(tr!(lang, "embed.terms", length = word_length, guesses = max_guesses))
+                .color(ctx.data().config.embed_color);
+            if let Some(h) = &handicaps {
+                e.field(tr!(lang, "embed.handicaps"), h, false);
+            }
+            e
+        })
+    })
+    .await?;
+    let (name, channel) = (&ctx.author().name, ctx.channel_id());
+    notify::dm(&ctx.discord().http, ctx.data(), user.id, ctx.guild_id(), NotifyEvent::Challenged, |lang| {
+        notify::challenge_note(lang, name, GameVariant::Timed, channel)
+    }).await;
+    Ok(())
+}
+
+/// Post an open Worduel challenge in this channel
+///
+/// The first eligible member to accept it becomes your opponent,
+/// using the usual accept command with your name.
+/// Same word rules as for challenges.
+#[poise::command(slash_command, category = "Worduel", rename = "wd_open", ephemeral)]
+pub async fn open(
+    ctx: Context<'_>,
+    #[description = "Challenge word (default: a random one)"] word: Option<String>,
+    #[description = "Only members with this role may accept"] role: Option<serenity::Role>,
+    #[description = "Lowest score allowed to accept"] min_score: Option<u64>,
+    #[description = "Highest score allowed to accept"] max_score: Option<u64>,
+) -> Result<(), Error> {
+    let lang = lang(ctx).await;
+    let word = ctx.data().player_word(ctx.guild_id(), word.as_deref()).await?;
+    let eligible = Eligibility {
+        role: role.as_ref().map(|r| r.id),
+        rating: (min_score, max_score),
+    };
+
+    let game_id = ctx.data().open_challenge(
+        ctx.author().id, word.clone(), GameVariant::Timed, ctx.guild_id(), ctx.channel_id(), eligible
+    ).await?;
+
+    let (word_length, max_guesses) = {
+        let game_handle = ctx.data().game(game_id).await.unwrap();
+        let gamedata = game_handle.lock().await;
+        (gamedata.get_word_length(), gamedata.get_max_guesses())
+    };
+
+    ctx.say(tr!(lang, "open.created", word = word)).await?;
+
+    let terms = open_terms(lang, word_length, max_guesses, role.as_ref(), (min_score, max_score));
+    ctx.channel_id().send_message(&ctx.discord().http, |m| {
+        m.content(tr!(lang, "open.public",
+            user = i18n::user(ctx.author().id), command = "/wd_accept", name = ctx.author().name))
+        .embed(|e| {
+            e.title(tr!(lang, "open.title_timed"))
+                .description(terms)
+                .color(ctx.data().config.embed_color)
         })
     })
     .await?;
     Ok(())
 }
 
+// Embed description of an open challenge, listing who may take it.
+pub fn open_terms(
+    lang: Lang, length: usize, guesses: usize, role: Option<&serenity::Role>, score: (Option<u64>, Option<u64>)
+) -> String {
+    let mut terms = tr!(lang, "embed.terms", length = length, guesses = guesses);
+    if let Some(r) = role {
+        terms.push('\n');
+        terms.push_str(&tr!(lang, "embed.only_for", role = r.name));
+    }
+    match score {
+        (None, None) => {},
+        (min, max) => {
+            terms.push('\n');
+            terms.push_str(&tr!(lang, "embed.score_range",
+                min = min.unwrap_or(0),
+                max = max.map(|m| m.to_string()).unwrap_or_else(|| tr!(lang, "embed.any"))));
+        }
+    }
+    terms
+}
+
 /// Accept a Worduel invitation
 ///
 /// The word you specify will be what the inviter has to guess.
@@ -58,24 +152,39 @@ pub async fn accept(
     #[description = "Chosen challenger"] user: serenity::User,
     #[description = "Response word"] word: String,
 ) -> Result<(), Error> {
-    let word = queries::ensure_word(&ctx.data().dict, &word)?;
+    let lang = lang(ctx).await;
+    // An open challenge becomes an invite for whoever claims it first
+    let roles = ctx.author_member().await.map(|m| m.roles).unwrap_or_default();
+    ctx.data().claim_open(ctx.author().id, user.id, GameVariant::Timed, ctx.channel_id(), &roles).await?;
+    let (game_id, word) = ctx.data().accept_invite(ctx.author().id, user.id, &word, GameVariant::Timed).await?;
+    let handicaps = match ctx.data().game(game_id).await {
+        Some(handle) => handle.lock().await.render_handicaps(lang),
+        None => None,
+    };
 
-    ctx.data().accept_invite(ctx.author().id, user.id, word.clone(), GameVariant::Timed).await?;
-
-    ctx.say(format!("Responded to game with word: {}", word)).await?;
+    ctx.say(match handicaps {
+        Some(h) => tr!(lang, "accept.done_handicaps", word = word, handicaps = h),
+        None => tr!(lang, "accept.done", word = word),
+    }).await?;
 
     ctx.channel_id().send_message(&ctx.discord().http, |m| {
-        m.content(
-            serenity::MessageBuilder::new()
-                .push("Your challenge has been accepted by ")
-                .push(&ctx.author().name)
-                .push(", ")
-                .user(user.id)
-                .push("!")
-                .build()
-        )
+        m.content(tr!(lang, "accept.public", name = ctx.author().name, user = i18n::user(user.id)))
     })
     .await?;
+    let (name, channel) = (&ctx.author().name, ctx.channel_id());
+    notify::dm(&ctx.discord().http, ctx.data(), user.id, ctx.guild_id(), NotifyEvent::Accepted, |lang| {
+        notify::accept_note(lang, name, GameVariant::Timed, channel)
+    }).await;
+    Ok(())
+}
+
+/// Withdraw your open challenge
+///
+/// Works for both timed and turn-based ones, as long as nobody claimed it yet.
+#[poise::command(slash_command, category = "Worduel", rename = "wd_open_cancel", ephemeral)]
+pub async fn open_cancel(ctx: Context<'_>) -> Result<(), Error> {
+    let game_id = ctx.data().cancel_open(ctx.author().id).await?;
+    ctx.say(tr!(lang(ctx).await, "open.withdrawn", game = game_id)).await?;
     Ok(())
 }
 
@@ -85,21 +194,36 @@ pub async fn reject(
     ctx: Context<'_>,
     #[description = "Challenger being rejected"] user: serenity::User,
 ) -> Result<(), Error> {
+    let lang = lang(ctx).await;
     let game = ctx.data().reject_invite(ctx.author().id, user.id, GameVariant::Timed).await?;
 
     ctx.say(match game {
-        None => "Rejected invite, game void".to_string(),
-        Some(g) => format!("Rejected invite, word was: {}", g.get_baseword(1))
+        None => tr!(lang, "reject.done_void"),
+        Some(g) => tr!(lang, "reject.done", word = g.lock().await.get_baseword(1)),
     }).await?;
 
     ctx.channel_id().send_message(&ctx.discord().http, |m| {
-        m.content(
-            serenity::MessageBuilder::new()
-                .push("Your challenge to ")
-                .user(ctx.author().id)
-                .push(" has been rejected.")
-                .build()
-        )
+        m.content(tr!(lang, "reject.public", user = i18n::user(user.id), rejecter = i18n::user(ctx.author().id)))
+    })
+    .await?;
+    Ok(())
+}
+
+/// Withdraw a Worduel invite you sent
+///
+/// Only works until it's accepted.
+#[poise::command(slash_command, category = "Worduel", rename = "wd_cancel", ephemeral)]
+pub async fn cancel(
+    ctx: Context<'_>,
+    #[description = "Challenged user"] user: serenity::User,
+) -> Result<(), Error> {
+    let lang = lang(ctx).await;
+    ctx.data().cancel_invite(ctx.author().id, user.id, GameVariant::Timed).await?;
+    ctx.say(tr!(lang, "invite.withdrawn")).await?;
+
+    ctx.channel_id().send_message(&ctx.discord().http, |m| {
+        m.content(tr!(lang, "invite.withdrawn_public",
+            user = i18n::user(user.id), challenger = i18n::user(ctx.author().id)))
     })
     .await?;
     Ok(())
@@ -115,66 +239,97 @@ pub async fn send(
     ctx: Context<'_>,
     #[description = "Sent word"] word: String,
 ) -> Result<(), Error> {
-    let word = queries::ensure_word(&ctx.data().dict, &word)?;
-
     let own_id = ctx.author().id;
+    let lang = lang(ctx).await;
+    let (game_id, word) = ctx.data().ensure_guess(own_id, None, &word).await?;
 
-    let (stateline, content, views) = 
-        ctx.data().act_on_timed(own_id, |_ud, _gid, gamedata, remove| {
+    let (stateline, content, views, reveal, broadcast, opponent, note) = 
+        ctx.data().act_on_timed(own_id, |_ud, gid, gamedata, remove| {
             use multiplayer::GameProgress::*;
 
+            if gid != game_id {
+                return Err(CmdError::NoGame);
+            }
             if matches!(gamedata.get_progress(), multiplayer::GameProgress::Waiting) {
-                return Err(CmdError::GameStarted(false).into());
+                return Err(CmdError::GameStarted(false));
             }
             let player_index = gamedata.match_user(own_id).unwrap();
             let enemy_id = gamedata.get_user_id(1-player_index);
 
             let success = gamedata.send_guess(player_index, word.to_lowercase());
-            let progress = gamedata.get_progress().clone();
+            tracing::info!(game = gid, user = %own_id, accepted = success, "guess");
+            if success {
+                ctx.data().metrics.guess(std::time::Instant::now());
+            }
+            let progress = *gamedata.get_progress();
 
             let views = gamedata.render_views(constants::WORDUEL_VIEWSEP);
 
-            let mut content = serenity::MessageBuilder::new();
-            match progress {
+            let mut reveal = None;
+            let mut note = None;
+            let content = match progress {
                 Over(res) => {
                     remove(true);
+                    reveal = Some((gid, [0, 1].map(|i| gamedata.get_baseword(i).to_string())));
+                    // Whether the opponent won, for their DM
+                    note = Some(res.map(|i| i != player_index));
                     match res {
-                        Some(i) => content
-                            .push("Game over, ")
-                            .user(enemy_id)
-                            .push(", the victor is ")
-                            .user(gamedata.get_user_id(i))
-                            .push("!"),
-                        None => content
-                            .push("Game over, ")
-                            .user(enemy_id)
-                            .push(", this duel ended in a draw."),
+                        Some(i) => tr!(lang, "send.victor",
+                            user = i18n::user(enemy_id), winner = i18n::user(gamedata.get_user_id(i))),
+                        None => tr!(lang, "send.draw", user = i18n::user(enemy_id)),
                     }
                 },
-                _ => {
-                    if success {
-                        content.push("Word has been sent!")
-                    } else {
-                        content.push("Word rejected, wait for the other side to finish.")
-                    }
-                }
+                _ if success => tr!(lang, "send.sent"),
+                _ => tr!(lang, "send.rejected"),
             };
             Ok((
-                gamedata.render_stateline(true),
-                content.build(),
+                gamedata.render_stateline(lang, true),
+                content,
                 views,
+                reveal,
+                Broadcast::of(lang, gid, gamedata),
+                enemy_id,
+                note,
             ))
         }).await?;
 
+    let series = match &reveal {
+        Some((gid, _)) => ctx.data().series_after(*gid, own_id, opponent).await,
+        None => None,
+    };
+
     ctx.send(|m| {
         m.content(content).embed(|e| {
-            e.title("Worduel status")
-                .field("Game state", stateline, true)
-                .color((255, 204, 11))
-                .description(views)
-        }).ephemeral(false)
+            e.title(tr!(lang, "embed.status"))
+                .field(tr!(lang, "embed.game_state"), stateline, true)
+                .color(ctx.data().config.embed_color)
+                .description(views);
+            // Explain both words once they're no secret
+            if let Some((gid, words)) = &reveal {
+                for w in words {
+                    e.field(w.to_uppercase(), ctx.data().defs.describe(w, constants::EMBED_FIELD_LIMIT), false);
+                }
+                if let Some(series) = &series {
+                    e.field(tr!(lang, "embed.series"), series_field(series, lang), false);
+                }
+                e.footer(|f| f.text(tr!(lang, "embed.game_footer", game = gid)));
+            }
+            e
+        });
+        if let Some((gid, _)) = &reveal {
+            m.components(|c| rematch_button(c, *gid, lang));
+        }
+        m.ephemeral(false)
     })
     .await?;
+    broadcast.send(ctx).await;
+    if let Some(won) = note {
+        let name = &ctx.author().name;
+        notify::dm(&ctx.discord().http, ctx.data(), opponent, ctx.guild_id(), NotifyEvent::GameEnd, |lang| {
+            notify::result_note(lang, game_id, name, won)
+        }).await;
+        notify::announce(ctx, |lang| notify::result_announcement(lang, game_id, opponent, own_id, won)).await;
+    }
     Ok(())
 }
 
@@ -188,42 +343,72 @@ pub async fn forfeit(
     #[description = "Enemy username"] user: Option<serenity::User>,
 ) -> Result<(), Error> {
     let own_id = ctx.author().id;
+    let lang = lang(ctx).await;
     let user_unwrapped = user.ok_or(CmdError::ForfeitBadUser)?;
+    let opponent = user_unwrapped.id;
 
-    let (stateline, content, views) = ctx.data().act_on_timed(own_id, |_, _, gamedata, remove| {
+    let (stateline, content, views, archived, broadcast, game_id) = ctx.data().act_on_timed(own_id, |_, gid, gamedata, remove| {
         let player_index = gamedata.match_user(own_id).unwrap();
         let enemy_id = gamedata.get_user_id(1 - player_index);
 
         if user_unwrapped.id != enemy_id {
-            return Err(CmdError::ForfeitBadUser.into());
+            return Err(CmdError::ForfeitBadUser);
         }
 
         use multiplayer::GameProgress::*;
         let views = gamedata.render_views(constants::WORDUEL_VIEWSEP);
 
-        let mut content = serenity::MessageBuilder::new();
-        match gamedata.get_progress() {
-            Waiting => content
-                .user(enemy_id)
-                .push(", the game has been given up on by ")
-                .push(&ctx.author().name),
-            _ => content
-                .user(enemy_id)
-                .push(", your opponent has forfeited this game."),
+        let mut archived = None;
+        let content = match gamedata.get_progress() {
+            Waiting => tr!(lang, "forfeit.waiting", user = i18n::user(enemy_id), name = ctx.author().name),
+            _ => {
+                // Started games are archived, so they can be analysed
+                archived = Some(gid);
+                tr!(lang, "forfeit.started", user = i18n::user(enemy_id))
+            }
         };
         remove(false);
-        Ok((gamedata.render_stateline(false), content.build(), views))
+        let stateline = gamedata.render_stateline(lang, false);
+        gamedata.forfeit(player_index);
+        tracing::info!(game = gid, user = %own_id, "game forfeited");
+        let broadcast = Broadcast::forfeit(lang, gid, gamedata, player_index);
+        Ok((stateline, content, views, archived, broadcast, gid))
     }).await?;
 
+    let series = match archived {
+        Some(gid) => ctx.data().series_after(gid, own_id, opponent).await,
+        None => None,
+    };
+
     ctx.send(|m| {
         m.content(content).embed(|e| {
-            e.title("Worduel status before forfeit")
-                .field("Last game state", stateline, true)
-                .color((255, 204, 11))
-                .description(views)
-        }).ephemeral(false)
+            e.title(tr!(lang, "embed.status_forfeit"))
+                .field(tr!(lang, "embed.last_game_state"), stateline, true)
+                .color(ctx.data().config.embed_color)
+                .description(views);
+            if let Some(series) = &series {
+                e.field(tr!(lang, "embed.series"), series_field(series, lang), false);
+            }
+            if let Some(gid) = archived {
+                e.footer(|f| f.text(tr!(lang, "embed.game_footer", game = gid)));
+            }
+            e
+        });
+        if let Some(gid) = archived {
+            m.components(|c| rematch_button(c, gid, lang));
+        }
+        m.ephemeral(false)
     })
     .await?;
+    broadcast.send(ctx).await;
+    let name = &ctx.author().name;
+    notify::dm(&ctx.discord().http, ctx.data(), opponent, ctx.guild_id(), NotifyEvent::GameEnd, |lang| {
+        tr!(lang, "notify.forfeited", name = name, game = game_id)
+    }).await;
+    if archived.is_some() {
+        notify::announce(ctx, |lang| tr!(lang, "announce.forfeit",
+            winner = i18n::user(opponent), loser = i18n::user(own_id), game = game_id)).await;
+    }
     Ok(())
 }
 
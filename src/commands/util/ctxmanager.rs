@@ -1,185 +1,1001 @@
 use poise::serenity_prelude as serenity;
-use serenity::UserId;
-use crate::constants;
+use serenity::{ChannelId, GuildId, RoleId, UserId};
 use crate::game::*;
 use crate::data::*;
-use super::queries;
-use std::time::SystemTime;
-use super::{CmdError, CmdResult};
+use crate::i18n::Lang;
+use std::time::{Duration, SystemTime};
+use super::{queries, CmdError, CmdResult};
 
+// All of these follow the lock ordering described next to CtxData:
+// game first, then users in ascending ID order, then scores and the archive.
+// The maps, series and open challenges included, are leaves taken at any point.
 impl CtxData {
+    // The guild's own expiry if it set one, the configured one for the variant otherwise.
+    async fn invite_expiry(&self, variant: GameVariant, guild_id: Option<GuildId>) -> Duration {
+        let custom = self.guilds().with_read(guild_id, |g| g.and_then(|g| g.invite_expiry)).await;
+        custom.map(Duration::from_secs).unwrap_or(match variant {
+            GameVariant::Timed => self.config.expiry.timed_invite,
+            GameVariant::TurnBased => self.config.expiry.turn_invite,
+        })
+    }
+
+    // Refuse to start games outside the guild's game channels, if it has any.
+    async fn check_channel(&self, guild_id: Option<GuildId>, channel: Option<ChannelId>) -> CmdResult<()> {
+        let channel = match channel {
+            Some(c) => c,
+            None => return Ok(()),
+        };
+        self.guilds()
+            .with_read(guild_id, |g| match g {
+                Some(g) if !g.allows_channel(channel) => {
+                    Err(CmdError::ChannelNotAllowed(g.game_channels.iter().copied().collect()))
+                }
+                _ => Ok(()),
+            })
+            .await
+    }
+
+    // Refuse to let a user banned by the guild challenge anyone there.
+    async fn check_banned(&self, guild_id: Option<GuildId>, user_id: UserId) -> CmdResult<()> {
+        let now = SystemTime::now();
+        match self.guilds().with_read(guild_id, |g| g.and_then(|g| g.banned_until(user_id, now))).await {
+            Some(until) => Err(CmdError::Banned(until)),
+            None => Ok(()),
+        }
+    }
+
+    // Create a game with the guild's guess count applied.
+    async fn create_game(&self,
+        own_id: UserId, enemy_id: UserId, word: String, variant: GameVariant, guild_id: Option<GuildId>
+    ) -> CmdResult<GameMP> {
+        let mut gamedata = GameMP::create(own_id, enemy_id, word, variant, guild_id);
+        if let Some(guesses) = self.guilds().with_read(guild_id, |g| g.and_then(|g| g.guesses)).await {
+            gamedata.set_max_guesses(guesses)?;
+        }
+        Ok(gamedata)
+    }
+
+    // Length of random words in the guild when players don't say.
+    pub async fn default_length(&self, guild_id: Option<GuildId>) -> usize {
+        self.guilds()
+            .with_read(guild_id, |g| g.and_then(|g| g.word_length))
+            .await
+            .unwrap_or(self.config.words.default_length)
+    }
+
+    // A word picked by a player, as given to a command: random if it's a number,
+    // or if it's missing, of the guild's default length. Guilds may turn random words off.
+    pub async fn player_word(&self, guild_id: Option<GuildId>, word: Option<&str>) -> CmdResult<String> {
+        let word = match word {
+            Some(w) => w.to_string(),
+            None => self.default_length(guild_id).await.to_string(),
+        };
+        self.check_random(guild_id, &word).await?;
+        self.ensure_word(guild_id, &word).await
+    }
+
+    // Refuse a request for a random word if the guild turned them off.
+    async fn check_random(&self, guild_id: Option<GuildId>, word: &str) -> CmdResult<()> {
+        let random = word.parse::<usize>().is_ok();
+        if random && !self.guilds().with_read(guild_id, |g| g.is_none_or(|g| g.random_words)).await {
+            return Err(CmdError::RandomWordsOff);
+        }
+        Ok(())
+    }
+
+    // queries::ensure_word, with the word lists of the given guild.
+    pub async fn ensure_word(&self, guild_id: Option<GuildId>, word: &str) -> CmdResult<String> {
+        self.guilds()
+            .with_read(guild_id, |g| queries::ensure_word(&self.config.words, &self.dict, g.map(|g| &g.words), word))
+            .await
+    }
+
+    // Is the user playing (or waiting on) any game?
+    // Cleanup drops games without telling their players, such references are forgotten here.
+    pub async fn in_game(&self, user_id: UserId) -> bool {
+        let handle = match self.find_user(user_id).await {
+            Some(handle) => handle,
+            None => return false,
+        };
+        let (timed, turns) = {
+            let userdata = handle.lock().await;
+            (userdata.player.timed_game, userdata.player.turn_games.clone())
+        };
+
+        let mut live = false;
+        let mut stale_timed = None;
+        let mut stale_turns = Vec::new();
+        if let Some(id) = timed {
+            match self.game(id).await {
+                Some(_) => live = true,
+                None => stale_timed = Some(id),
+            }
+        }
+        for (enemy_id, id) in turns {
+            match self.game(id).await {
+                Some(_) => live = true,
+                None => stale_turns.push((enemy_id, id)),
+            }
+        }
+
+        if stale_timed.is_some() || !stale_turns.is_empty() {
+            let mut userdata = handle.lock().await;
+            if stale_timed.is_some() && userdata.player.timed_game == stale_timed {
+                userdata.player.timed_game = None;
+            }
+            for (enemy_id, id) in stale_turns {
+                if userdata.player.turn_games.get(&enemy_id) == Some(&id) {
+                    userdata.player.turn_games.remove(&enemy_id);
+                }
+            }
+        }
+        live
+    }
+
+    // `word` should already be checked against the word lists of `guild_id`,
+    // which then apply to the whole game.
     pub async fn challenge_player(&self,
-        own_id: UserId, enemy_id: UserId, word: String, variant: GameVariant
+        own_id: UserId, enemy_id: UserId, word: String, variant: GameVariant, guild_id: Option<GuildId>
+    ) -> CmdResult<GameId> {
+        let options = ChallengeOptions { guild: guild_id, ..Default::default() };
+        self.challenge_with(own_id, enemy_id, word, variant, options).await
+    }
+
+    // challenge_player, with handicaps and the like.
+    pub async fn challenge_with(&self,
+        own_id: UserId, enemy_id: UserId, word: String, variant: GameVariant, options: ChallengeOptions,
     ) -> CmdResult<GameId> {
         use GameVariant::*;
         if own_id == enemy_id {
             return Err(CmdError::ChallengedSelf);
         }
+        self.check_channel(options.guild, options.channel).await?;
+        self.check_banned(options.guild, own_id).await?;
+        let expiry = SystemTime::now() + self.invite_expiry(variant, options.guild).await;
+        let mut gamedata = self.create_game(own_id, enemy_id, word, variant, options.guild).await?;
 
-        let mut udlock = self.userdata.write().await;
-        let mut mplock = self.mpgames.write().await;
+        let own_handle = self.user(own_id).await;
+        let enemy_handle = self.user(enemy_id).await;
+        let (mut userdata1, mut userdata2) =
+            lock_users((own_id, &own_handle), (enemy_id, &enemy_handle)).await;
 
-        let (game_id, gamedata) = {
-            // Own data scope
-            let userdata1 = udlock.entry(own_id).or_default();
-            if matches!(variant, Timed) && userdata1.player.timed_game.is_some() {
-                return Err(CmdError::SelfInGame);
-            }
-            let game_id = self.pull_gameid();
-            let gamedata = GameMP::create(own_id, enemy_id, word, variant);
-            match variant {
-                Timed => {userdata1.player.timed_game = Some(game_id);},
-                TurnBased => {userdata1.player.turn_games.insert(enemy_id, game_id);}
+        if !userdata2.privacy.allows(own_id, &options.roles, options.matched) {
+            return Err(CmdError::ChallengeRefused(enemy_id));
+        }
+        if matches!(variant, Timed) && userdata1.player.timed_game.is_some() {
+            return Err(CmdError::SelfInGame);
+        }
+        if matches!(variant, TurnBased) {
+            self.limits().check_turn_games(userdata1.player.turn_games.len())?;
+        }
+        // Within a series, players take turns challenging.
+        // Both users are locked, so the series can't pick up another game meanwhile.
+        let key = series_key(own_id, enemy_id);
+        let in_series = match self.series.read().await.get(&key) {
+            Some(series) if series.get_variant() == variant && !series.is_over() => {
+                if series.get_current().is_some() {
+                    return Err(CmdError::SeriesGameRunning);
+                }
+                if series.next_picker() != own_id {
+                    return Err(CmdError::NotYourPick);
+                }
+                true
             }
-            (game_id, gamedata)
+            _ => false,
         };
+        let game_id = self.pull_gameid();
+        for (i, h) in options.handicaps.into_iter().enumerate() {
+            gamedata.set_handicap(i, h)?;
+        }
+        // Nobody else knows about this game yet, so inserting it under user locks is fine.
+        self.insert_game(game_id, gamedata).await?;
+        if in_series {
+            if let Some(series) = self.series.write().await.get_mut(&key) {
+                series.set_current(game_id);
+            }
+        }
 
-        mplock.insert(
-            game_id,
-            gamedata
-        );
+        match variant {
+            Timed => {userdata1.player.timed_game = Some(game_id);},
+            TurnBased => {userdata1.player.turn_games.insert(enemy_id, game_id);}
+        }
 
         // Access opponent data
-        udlock
-            .entry(enemy_id)
-            .or_default()
+        userdata2
             .player
             .invite(variant, own_id, Invite {
                 game: game_id,
-                expiry: SystemTime::now() + match variant {
-                    Timed => constants::TIMED_INVITE_EXPIRY,
-                    TurnBased => constants::TURN_INVITE_EXPIRY,
-                }});
+                expiry,
+                channel: options.channel,
+            });
         Ok(game_id)
     }
-    
-    pub async fn accept_invite(&self,
-        own_id: UserId, enemy_id: UserId, word: String, variant: GameVariant
+
+    // Start a best-of-`best_of` series with its first game.
+    pub async fn start_series(&self,
+        own_id: UserId, enemy_id: UserId, best_of: usize, word: String, variant: GameVariant, options: ChallengeOptions,
     ) -> CmdResult<GameId> {
-        let mut udlock = self.userdata.write().await;
+        if ![3, 5, 7].contains(&best_of) {
+            return Err(CmdError::BadSeriesLength(best_of));
+        }
+        if own_id == enemy_id {
+            return Err(CmdError::ChallengedSelf);
+        }
+        let key = series_key(own_id, enemy_id);
+        {
+            let mut series = self.series.write().await;
+            if series.get(&key).is_some_and(|s| !s.is_over()) {
+                return Err(CmdError::SeriesExists);
+            }
+            series.insert(key, Series::new([own_id, enemy_id], best_of, variant));
+        }
+        let res = self.challenge_with(own_id, enemy_id, word, variant, options).await;
+        if res.is_err() {
+            self.series.write().await.remove(&key);
+        }
+        res
+    }
 
-        let userdata = udlock.entry(own_id).or_default();
-        if userdata.player.timed_game.is_some() {
-            return Err(CmdError::SelfInGame.into());
+    // The series `game_id` belonged to, if any. Once it's over it's handed out one last time.
+    pub async fn series_after(&self, game_id: GameId, a: UserId, b: UserId) -> Option<Series> {
+        let key = series_key(a, b);
+        let mut all = self.series.write().await;
+        let series = all.get(&key).filter(|s| s.has_game(game_id))?.clone();
+        if series.is_over() {
+            all.remove(&key);
         }
+        Some(series)
+    }
+
+    // Challenge the opponent of an archived game again, with the same variant, word length and handicaps.
+    // The word is picked at random. Returns the new game and the opponent.
+    pub async fn rematch(&self,
+        own_id: UserId, game_id: GameId, channel: Option<ChannelId>, roles: Vec<RoleId>
+    ) -> CmdResult<(GameId, UserId)> {
+        let archived = self.archive().get(game_id).await
+            .ok_or(CmdError::NoArchivedGame(game_id))?;
+        let index = archived.players.iter()
+            .position(|&p| p == own_id)
+            .ok_or(CmdError::BadRematch)?;
+        let enemy_id = archived.players[1 - index];
+        let length = archived.words[index].len();
+        let word = self.ensure_word(archived.guild, &length.to_string()).await?;
+        // Handicaps stay with their players
+        let options = ChallengeOptions {
+            guild: archived.guild,
+            handicaps: [archived.handicaps[index], archived.handicaps[1 - index]],
+            channel,
+            roles,
+            matched: false,
+        };
+        let new_id = self.challenge_with(own_id, enemy_id, word, archived.variant, options).await?;
+        Ok((new_id, enemy_id))
+    }
+
+    // Like challenge_player, but the opponent is whoever claims the challenge first.
+    pub async fn open_challenge(&self,
+        own_id: UserId, word: String, variant: GameVariant, guild_id: Option<GuildId>,
+        channel: ChannelId, eligible: Eligibility,
+    ) -> CmdResult<GameId> {
+        self.check_channel(guild_id, Some(channel)).await?;
+        self.check_banned(guild_id, own_id).await?;
+        let expiry = SystemTime::now() + self.invite_expiry(variant, guild_id).await;
+        let gamedata = self.create_game(own_id, multiplayer::OPEN_SEAT, word, variant, guild_id).await?;
+
+        let own_handle = self.user(own_id).await;
+        let mut userdata = own_handle.lock().await;
+
+        if matches!(variant, GameVariant::Timed) && userdata.player.timed_game.is_some() {
+            return Err(CmdError::SelfInGame);
+        }
+        // Checked and inserted under the user lock, so there's never a second one.
+        if self.open_challenges.read().await.contains_key(&own_id) {
+            return Err(CmdError::OpenChallengeExists);
+        }
+        let game_id = self.pull_gameid();
+        self.insert_game(game_id, gamedata).await?;
+        if matches!(variant, GameVariant::Timed) {
+            userdata.player.timed_game = Some(game_id);
+        }
+        self.open_challenges.write().await.insert(own_id, OpenChallenge {
+            invite: Invite {
+                game: game_id,
+                expiry,
+                channel: Some(channel),
+            },
+            variant,
+            channel,
+            eligible,
+        });
+        Ok(game_id)
+    }
+
+    // Turn the open challenge of `enemy_id`, if there's one, into an invite for `own_id`,
+    // to be accepted with accept_invite like any other.
+    // Returns false if `enemy_id` has no open challenge of this variant.
+    pub async fn claim_open(&self,
+        own_id: UserId, enemy_id: UserId, variant: GameVariant, channel: ChannelId, roles: &[RoleId]
+    ) -> CmdResult<bool> {
+        // Scores come after users and games, so look the rating up first.
+        let rating = self.scores().get(own_id).await.unwrap_or(0);
+        let challenge = {
+            let mut open = self.open_challenges.write().await;
+            match open.get(&enemy_id) {
+                Some(c) if c.variant == variant => {
+                    if own_id == enemy_id {
+                        return Err(CmdError::ChallengedSelf);
+                    }
+                    if c.channel != channel {
+                        return Err(CmdError::WrongChannel);
+                    }
+                    if !c.eligible.allows(roles, rating) {
+                        return Err(CmdError::NotEligible);
+                    }
+                    open.remove(&enemy_id).unwrap()
+                }
+                _ => return Ok(false),
+            }
+        };
+
+        let game_id = challenge.invite.game;
+        let game_handle = self.game(game_id).await.ok_or(CmdError::GameDeleted)?;
+        let mut gamedata = game_handle.lock().await;
+        let own_handle = self.user(own_id).await;
+        let enemy_handle = self.user(enemy_id).await;
+        let (mut userdata, mut enemydata) =
+            lock_users((own_id, &own_handle), (enemy_id, &enemy_handle)).await;
 
-        let game_id = userdata.player
+        // Someone who can't accept leaves the challenge open for others.
+        let busy = userdata.player.timed_game.is_some()
+            || userdata.player.list(variant).contains_key(&enemy_id)
+            || (matches!(variant, GameVariant::TurnBased) && userdata.player.turn_games.contains_key(&enemy_id));
+        if busy {
+            let err = match userdata.player.timed_game {
+                Some(_) => CmdError::SelfInGame,
+                None => CmdError::BadAccept,
+            };
+            self.open_challenges.write().await.insert(enemy_id, challenge);
+            return Err(err);
+        }
+        if enemydata.privacy.blocked.contains(&own_id) {
+            self.open_challenges.write().await.insert(enemy_id, challenge);
+            return Err(CmdError::NotEligible);
+        }
+        if !gamedata.claim(own_id) {
+            return Err(CmdError::GameStarted(false));
+        }
+        if matches!(variant, GameVariant::TurnBased) {
+            enemydata.player.turn_games.insert(own_id, game_id);
+        }
+        userdata.player.invite(variant, enemy_id, challenge.invite);
+        Ok(true)
+    }
+
+    // Put a player in the matchmaking pool for the variant and word length.
+    pub async fn join_queue(&self,
+        own_id: UserId, variant: GameVariant, length: usize, channel: ChannelId, guild_id: Option<GuildId>
+    ) -> CmdResult<()> {
+        queries::test_length(&self.config.words, length)?;
+        self.check_channel(guild_id, Some(channel)).await?;
+        self.check_banned(guild_id, own_id).await?;
+        if let Some(handle) = self.find_user(own_id).await {
+            if matches!(variant, GameVariant::Timed) && handle.lock().await.player.timed_game.is_some() {
+                return Err(CmdError::SelfInGame);
+            }
+        }
+        let entry = queue::QueueEntry {
+            id: own_id,
+            rating: self.scores().get(own_id).await.unwrap_or(0),
+            since: SystemTime::now(),
+            channel,
+            guild: guild_id,
+        };
+        if !self.queue().join((variant, length), entry).await {
+            return Err(CmdError::AlreadyQueued);
+        }
+        Ok(())
+    }
+
+    // Start a game between two paired players, with random words on both sides.
+    // The word lists of the first player's guild apply.
+    pub async fn start_matched(&self,
+        (variant, length): queue::PoolKey, a: &queue::QueueEntry, b: &queue::QueueEntry
+    ) -> CmdResult<GameId> {
+        let word_a = self.ensure_word(a.guild, &length.to_string()).await?;
+        let word_b = self.ensure_word(a.guild, &length.to_string()).await?;
+        let options = ChallengeOptions { guild: a.guild, matched: true, ..Default::default() };
+        let game_id = self.challenge_with(a.id, b.id, word_a, variant, options).await?;
+        if let Err(e) = self.accept_invite(b.id, a.id, &word_b, variant).await {
+            // Nobody saw this game yet, drop it quietly
+            self.reject_invite(b.id, a.id, variant).await.ok();
+            return Err(e);
+        }
+        Ok(game_id)
+    }
+
+    // Withdraw an unclaimed open challenge.
+    pub async fn cancel_open(&self, own_id: UserId) -> CmdResult<GameId> {
+        let challenge = self.open_challenges.write().await
+            .remove(&own_id)
+            .ok_or(CmdError::NoOpenChallenge)?;
+        let game_id = challenge.invite.game;
+        self.remove_game(game_id).await;
+        if let Some(handle) = self.find_user(own_id).await {
+            handle.lock().await.player.forget_game(multiplayer::OPEN_SEAT, game_id);
+        }
+        Ok(game_id)
+    }
+
+    // Withdraw an invite sent to `enemy_id` that hasn't been accepted yet.
+    pub async fn cancel_invite(&self,
+        own_id: UserId, enemy_id: UserId, variant: GameVariant
+    ) -> CmdResult<GameId> {
+        let own_handle = self.user(own_id).await;
+        let game_id = {
+            let player = &own_handle.lock().await.player;
+            match variant {
+                GameVariant::Timed => player.timed_game,
+                GameVariant::TurnBased => player.turn_games.get(&enemy_id).copied(),
+            }
+        }.ok_or(CmdError::NoOutgoingInvite)?;
+        let game_handle = self.game(game_id).await.ok_or(CmdError::GameDeleted)?;
+        let gamedata = game_handle.lock().await;
+        // Open challenges have no opponent yet, they're withdrawn with cancel_open.
+        if gamedata.get_user_id(0) != own_id || gamedata.get_user_id(1) != enemy_id {
+            return Err(CmdError::NoOutgoingInvite);
+        }
+        // Holding the game keeps the invite from being accepted meanwhile.
+        if !matches!(gamedata.get_progress(), multiplayer::GameProgress::Waiting) {
+            return Err(CmdError::GameStarted(false));
+        }
+        {
+            let enemy_handle = self.user(enemy_id).await;
+            let (mut userdata1, mut userdata2) =
+                lock_users((own_id, &own_handle), (enemy_id, &enemy_handle)).await;
+            if userdata2.player.list(variant).get(&own_id).map(|i| i.game) == Some(game_id) {
+                userdata2.player.remove_invite(variant, own_id);
+            }
+            userdata1.player.forget_game(enemy_id, game_id);
+        }
+        self.remove_game(game_id).await;
+        self.retire_game(game_id, &gamedata, false).await;
+        Ok(game_id)
+    }
+
+    // Pending invites of a user as (other player, variant, expiry),
+    // received ones first, then sent ones. Open challenges are sent to `multiplayer::OPEN_SEAT`.
+    pub async fn invites_of(&self, own_id: UserId) -> (Vec<(UserId, GameVariant, SystemTime)>, Vec<(UserId, GameVariant, SystemTime)>) {
+        let own_handle = match self.find_user(own_id).await {
+            Some(handle) => handle,
+            None => return (Vec::new(), Vec::new()),
+        };
+        let mut incoming = Vec::new();
+        let mut sent = Vec::new();
+        {
+            let player = &own_handle.lock().await.player;
+            for variant in [GameVariant::Timed, GameVariant::TurnBased] {
+                for (id, invite) in player.list(variant) {
+                    incoming.push((*id, variant, invite.expiry));
+                }
+            }
+            sent.extend(player.timed_game.map(|g| (GameVariant::Timed, g)));
+            sent.extend(player.turn_games.values().map(|&g| (GameVariant::TurnBased, g)));
+        }
+
+        // Sent invites are only known to their recipients, look them up one user at a time.
+        let mut outgoing = Vec::new();
+        for (variant, game_id) in sent {
+            let enemy_id = match self.game(game_id).await {
+                Some(handle) => {
+                    let gamedata = handle.lock().await;
+                    if !matches!(gamedata.get_progress(), multiplayer::GameProgress::Waiting) {
+                        continue;
+                    }
+                    gamedata.get_user_id(1)
+                }
+                None => continue,
+            };
+            let expiry = if enemy_id == multiplayer::OPEN_SEAT {
+                self.open_challenges.read().await.get(&own_id).map(|c| c.invite.expiry)
+            } else {
+                match self.find_user(enemy_id).await {
+                    Some(handle) => handle.lock().await.player
+                        .list(variant)
+                        .get(&own_id)
+                        .filter(|i| i.game == game_id)
+                        .map(|i| i.expiry),
+                    None => None,
+                }
+            };
+            if let Some(expiry) = expiry {
+                outgoing.push((enemy_id, variant, expiry));
+            }
+        }
+        incoming.sort_by_key(|i| i.2);
+        outgoing.sort_by_key(|i| i.2);
+        (incoming, outgoing)
+    }
+
+    // Checks `word` against the word lists of the game's guild.
+    // Returns the game and the word, random words being resolved.
+    pub async fn accept_invite(&self,
+        own_id: UserId, enemy_id: UserId, word: &str, variant: GameVariant
+    ) -> CmdResult<(GameId, String)> {
+        let own_handle = self.user(own_id).await;
+
+        // Peek at the invite first, the game has to be locked before the user.
+        let game_id = own_handle.lock().await.player
             .list(variant)
             .get(&enemy_id)
             .ok_or(CmdError::NoInvite)? // important point 1
             .game;
-        let mut mplock = self.mpgames.write().await;
-        let gamedata = mplock.get_mut(&game_id).ok_or(CmdError::GameDeleted)?;
+        let game_handle = self.game(game_id).await.ok_or(CmdError::GameDeleted)?;
+        let mut gamedata = game_handle.lock().await;
+        let mut userdata = own_handle.lock().await;
+
+        if userdata.player.timed_game.is_some() {
+            return Err(CmdError::SelfInGame);
+        }
+        // The invite might have changed while nothing was locked.
+        if userdata.player.list(variant).get(&enemy_id).map(|i| i.game) != Some(game_id) {
+            return Err(CmdError::NoInvite);
+        }
 
         if !matches!(gamedata.get_progress(), multiplayer::GameProgress::Waiting) {
             userdata.player.remove_invite(variant, enemy_id);
-            return Err(CmdError::GameStarted(true).into());
+            return Err(CmdError::GameStarted(true));
+        }
+        if matches!(variant, GameVariant::TurnBased) {
+            self.limits().check_turn_games(userdata.player.turn_games.len())?;
         }
+        // Guild settings are a leaf lock, fine to take here.
+        self.check_random(gamedata.get_guild(), word).await?;
+        let word = self.ensure_word(gamedata.get_guild(), word).await?;
         if gamedata.get_word_length() != word.len() {
             userdata.player.remove_invite(variant, enemy_id);
-            return Err(CmdError::BadWordLength(word.len()).into());
+            let length = gamedata.get_word_length();
+            return Err(CmdError::BadWordLength { length: word.len(), min: length, max: length });
         }
 
         // Unwrapping because [1]
         if !userdata.player.accept(variant, enemy_id).unwrap() {
-            return Err(CmdError::BadAccept.into());
+            return Err(CmdError::BadAccept);
         }
-        gamedata.respond(word, own_id)?;
-        Ok(game_id)
+        gamedata.respond(word.clone(), own_id)?;
+        self.limits().start_game(game_id);
+        tracing::info!(game = game_id, user = %own_id, "game accepted");
+        Ok((game_id, word))
     }
-    
-    pub async fn reject_invite(&self,
-        own_id: UserId, enemy_id: UserId, variant: GameVariant
-    ) -> CmdResult<Option<GameMP>> {
-        let mut udlock = self.userdata.write().await;
-        let userdata = udlock.entry(own_id).or_default();
 
-        let game_id = userdata.player
-            .list(variant)
-            .get(&enemy_id)
-            .ok_or(CmdError::NoInvite)? // important point 1
-            .game;
-        let mut mplock = self.mpgames.write().await;
+    // Check a guess against the word lists of the guild the game was created in.
+    // Returns the game as well, the guess should only go to that one.
+    pub async fn ensure_guess(&self,
+        own_id: UserId, enemy_id: Option<UserId>, word: &str
+    ) -> CmdResult<(GameId, String)> {
+        let find = |_: &mut UserData, id: GameId, g: &mut GameMP, _: &mut dyn FnMut(bool)| Ok((id, g.get_guild()));
+        let (game_id, guild_id) = match enemy_id {
+            Some(enemy_id) => self.act_on_turnbased(own_id, enemy_id, find).await?,
+            None => self.act_on_timed(own_id, find).await?,
+        };
+        Ok((game_id, self.ensure_word(guild_id, word).await?))
+    }
 
-        userdata.player.remove_invite(variant, enemy_id).ok_or(CmdError::NoInvite)?;
-        Ok(mplock.remove(&game_id))
+    // Find a game someone else plays: the turn-based one against `opponent` if given,
+    // else their timed game, else their only turn-based game.
+    pub async fn find_game_of(&self,
+        player_id: UserId, opponent: Option<UserId>
+    ) -> CmdResult<(GameId, Shared<GameMP>)> {
+        let handle = self.find_user(player_id).await.ok_or(CmdError::NoWatchedGame)?;
+        let game_id = {
+            let userdata = handle.lock().await;
+            let player = &userdata.player;
+            match opponent {
+                Some(id) => player.turn_games.get(&id).copied(),
+                None => player.timed_game.or_else(|| match player.turn_games.len() {
+                    1 => player.turn_games.values().next().copied(),
+                    _ => None,
+                }),
+            }
+        }.ok_or(CmdError::NoWatchedGame)?;
+        let game_handle = self.game(game_id).await.ok_or(CmdError::GameDeleted)?;
+        Ok((game_id, game_handle))
     }
-    
+
+    // Language for a user: their own choice, else their guild's.
+    pub async fn lang_for(&self, user_id: UserId, guild_id: Option<GuildId>) -> Lang {
+        let own = match self.find_user(user_id).await {
+            Some(handle) => handle.lock().await.lang,
+            None => None,
+        };
+        match own {
+            Some(lang) => lang,
+            None => self.guild_lang(guild_id).await,
+        }
+    }
+
+    pub async fn guild_lang(&self, guild_id: Option<GuildId>) -> Lang {
+        self.guilds().with_read(guild_id, |g| g.map(|g| g.lang).unwrap_or_default()).await
+    }
+
+    // Snapshot for the metrics endpoint.
+    // Games and users are locked one at a time, so the numbers may be slightly off.
+    pub async fn gauges(&self) -> crate::metrics::Gauges {
+        let mut gauges = crate::metrics::Gauges::default();
+        let games: Vec<_> = self.mpgames.read().await.values().cloned().collect();
+        for handle in games {
+            match handle.lock().await.get_progress() {
+                multiplayer::GameProgress::Waiting => gauges.waiting_games += 1,
+                _ => gauges.active_games += 1,
+            }
+        }
+        let users: Vec<_> = self.userdata.read().await.values().cloned().collect();
+        for handle in users {
+            let userdata = handle.lock().await;
+            gauges.pending_invites += userdata.player.list(GameVariant::Timed).len()
+                + userdata.player.list(GameVariant::TurnBased).len();
+        }
+        gauges.open_challenges = self.open_challenges.read().await.len();
+        gauges.queued_players = self.queue().count().await;
+        gauges
+    }
+
+    // Games past their invite, as the dashboard shows them, by number.
+    pub async fn live_games(&self) -> Vec<crate::dashboard::LiveGame> {
+        let games: Vec<_> = self.mpgames.read().await.iter().map(|(id, g)| (*id, g.clone())).collect();
+        let now = std::time::Instant::now();
+        let mut live = Vec::new();
+        for (id, handle) in games {
+            live.extend(crate::dashboard::LiveGame::of(id, &*handle.lock().await, now));
+        }
+        live.sort_by_key(|g| g.id);
+        live
+    }
+
+    // A player's standing, finished games and games going on, for the dashboard.
+    pub async fn profile(&self, user: UserId) -> crate::dashboard::Profile {
+        let standing = self.scores().standing(user).await;
+        let games = self.archive().of_player(user).await;
+        // The user lock is let go before any game is locked
+        let ids: Vec<GameId> = match self.find_user(user).await {
+            Some(handle) => {
+                let userdata = handle.lock().await;
+                userdata.player.timed_game.into_iter().chain(userdata.player.turn_games.values().copied()).collect()
+            }
+            None => Vec::new(),
+        };
+        let mut live = Vec::new();
+        for id in ids {
+            if let Some(handle) = self.game(id).await {
+                if matches!(handle.lock().await.get_progress(), multiplayer::GameProgress::Started | multiplayer::GameProgress::Ending(_)) {
+                    live.push(id);
+                }
+            }
+        }
+        live.sort_unstable();
+        crate::dashboard::Profile::new(user, standing, games, live)
+    }
+
+    // Choose a language, or go back to the guild's with None.
+    pub async fn set_lang(&self, own_id: UserId, lang: Option<Lang>) {
+        self.user(own_id).await.lock().await.lang = lang;
+    }
+
+    // Turn DMs about `event` on or off, or about everything if no event is given.
+    pub async fn set_notify(&self,
+        own_id: UserId, event: Option<notify::NotifyEvent>, on: bool
+    ) -> notify::NotifyPrefs {
+        let handle = self.user(own_id).await;
+        let mut userdata = handle.lock().await;
+        match event {
+            Some(e) => userdata.notify.set(e, on),
+            None => for e in notify::NotifyEvent::ALL {
+                userdata.notify.set(e, on);
+            },
+        }
+        userdata.notify
+    }
+
+    // Change who may challenge the user. Returns the new preferences.
+    pub async fn set_privacy<F: FnOnce(&mut privacy::PrivacyPrefs)>(&self, own_id: UserId, f: F) -> privacy::PrivacyPrefs {
+        let handle = self.user(own_id).await;
+        let mut userdata = handle.lock().await;
+        f(&mut userdata.privacy);
+        userdata.privacy.clone()
+    }
+
+    // Block or unblock a user. Their pending invites are turned down on the way.
+    pub async fn block(&self, own_id: UserId, user_id: UserId, blocked: bool) -> privacy::PrivacyPrefs {
+        let prefs = self.set_privacy(own_id, |p| p.block(user_id, blocked)).await;
+        if blocked {
+            for variant in [GameVariant::Timed, GameVariant::TurnBased] {
+                self.reject_invite(own_id, user_id, variant).await.ok();
+            }
+        }
+        prefs
+    }
+
+    // Does the user want a DM about `event`?
+    pub async fn wants_dm(&self, user_id: UserId, event: notify::NotifyEvent) -> bool {
+        match self.find_user(user_id).await {
+            Some(handle) => handle.lock().await.notify.get(event),
+            None => false,
+        }
+    }
+
+    // Decline an invite, which drops its game. Returns the game if it was still around.
+    pub async fn reject_invite(&self,
+        own_id: UserId, enemy_id: UserId, variant: GameVariant
+    ) -> CmdResult<Option<Shared<GameMP>>> {
+        let own_handle = self.user(own_id).await;
+        let invite = own_handle.lock().await.player
+            .remove_invite(variant, enemy_id)
+            .ok_or(CmdError::NoInvite)?;
+        if let Some(handle) = self.find_user(enemy_id).await {
+            handle.lock().await.player.forget_game(own_id, invite.game);
+        }
+        let game = self.remove_game(invite.game).await;
+        if let Some(handle) = &game {
+            self.retire_game(invite.game, &*handle.lock().await, false).await;
+        }
+        Ok(game)
+    }
+
     // Perform a function on an active (caller-bound) timed game. 
     // Takes a function which has to take four parameters:
     // - userdata, game_id, gamedata: obvious
     // - remove: function to call if the game is to be removed
+    // Only this game and its two players are locked while `f` runs.
     pub async fn act_on_timed<T, F: FnOnce(&mut UserData, GameId, &mut GameMP, &mut dyn FnMut(bool))-> CmdResult<T>> (&self,
         own_id: UserId, f: F
     ) -> CmdResult<T> {
-        let mut udlock = self.userdata.write().await;
-        let mut mplock = self.mpgames.write().await;
-
-        let (userdata, game_id) = queries::unwrap_timedgame_id(udlock.get_mut(&own_id))?;
+        let own_handle = self.find_user(own_id).await.ok_or(CmdError::NoGame)?;
+        let game_id = own_handle.lock().await.player.timed_game.ok_or(CmdError::NoGame)?;
 
-        let gamedata = mplock.get_mut(&game_id).ok_or_else(|| {
-            userdata.player.timed_game = None;
-            CmdError::GameDeleted
-        })?;
+        let game_handle = match self.game(game_id).await {
+            Some(g) => g,
+            None => {
+                let mut userdata = own_handle.lock().await;
+                if userdata.player.timed_game == Some(game_id) {
+                    userdata.player.timed_game = None;
+                }
+                return Err(CmdError::GameDeleted);
+            }
+        };
+        let mut gamedata = game_handle.lock().await;
 
-        // If we got gamedata, this should REALLY not be None.
-        let player_index = gamedata.match_user(own_id).unwrap();
+        let player_index = gamedata.match_user(own_id).ok_or(CmdError::NoGame)?;
         let enemy_id = gamedata.get_user_id(1 - player_index);
+        let enemy_handle = self.user(enemy_id).await;
 
-        let mut should_remove = false;
-        let mut should_commit_scores = false;
-        let res = f(userdata, game_id, gamedata, &mut |scores| {
-            should_remove = true;
-            should_commit_scores = scores;
-        });
-        if should_commit_scores {
-            self.scores().add_from_game(gamedata).await;
-        }
-        if should_remove {
-            mplock.remove(&game_id);
-            userdata.player.timed_game = None;
-            if let Some(udata2) = udlock.get_mut(&enemy_id) {
-                udata2.player.timed_game = None;
+        let (should_remove, should_commit_scores, res) = {
+            let (mut userdata, mut udata2) =
+                lock_users((own_id, &own_handle), (enemy_id, &enemy_handle)).await;
+            // Game might have been swapped out while nothing was locked.
+            if userdata.player.timed_game != Some(game_id) {
+                return Err(CmdError::NoGame);
+            }
+
+            let mut should_remove = false;
+            let mut should_commit_scores = false;
+            let res = f(&mut userdata, game_id, &mut gamedata, &mut |scores| {
+                should_remove = true;
+                should_commit_scores = scores;
+            });
+            if should_remove {
+                self.remove_game(game_id).await;
+                userdata.player.timed_game = None;
+                if udata2.player.timed_game == Some(game_id) {
+                    udata2.player.timed_game = None;
+                }
             }
+            (should_remove, should_commit_scores, res)
+        };
+        if should_remove {
+            self.retire_game(game_id, &gamedata, should_commit_scores).await;
         }
         res
     }
-    
+
     // Perform a function on an active (caller-bound) turn-based game. 
     // Takes a function which has to take four parameters:
     // - userdata, game_id, gamedata: obvious
     // - remove: function to call if the game is to be removed
     //     Takes a bool indicating whether or not to commit the score.
+    // Only this game and its two players are locked while `f` runs.
     pub async fn act_on_turnbased<T, F: FnOnce(&mut UserData, GameId, &mut GameMP, &mut dyn FnMut(bool))-> CmdResult<T>> (&self,
         own_id: UserId, enemy_id: UserId, f: F
     ) -> CmdResult<T> {
-        let mut udlock = self.userdata.write().await;
-        let mut mplock = self.mpgames.write().await;
+        let own_handle = self.find_user(own_id).await.ok_or(CmdError::NoGame)?;
+        let game_id = *own_handle.lock().await.player.turn_games
+            .get(&enemy_id)
+            .ok_or(CmdError::NoGame)?;
+
+        let game_handle = match self.game(game_id).await {
+            Some(g) => g,
+            None => {
+                let mut userdata = own_handle.lock().await;
+                if userdata.player.turn_games.get(&enemy_id) == Some(&game_id) {
+                    userdata.player.turn_games.remove(&enemy_id);
+                }
+                return Err(CmdError::GameDeleted);
+            }
+        };
+        let mut gamedata = game_handle.lock().await;
+        let enemy_handle = self.user(enemy_id).await;
 
-        let (userdata, game_id) = queries::unwrap_turngame_id(udlock.get_mut(&own_id), enemy_id)?;
+        let (should_remove, should_commit_scores, res) = {
+            let (mut userdata, mut udata2) =
+                lock_users((own_id, &own_handle), (enemy_id, &enemy_handle)).await;
+            // Game might have been swapped out while nothing was locked.
+            if userdata.player.turn_games.get(&enemy_id) != Some(&game_id) {
+                return Err(CmdError::NoGame);
+            }
 
-        let gamedata = mplock.get_mut(&game_id).ok_or_else(|| {
-            userdata.player.turn_games.remove(&enemy_id);
-            CmdError::GameDeleted
-        })?;
+            let mut should_remove = false;
+            let mut should_commit_scores = false;
+            let res = f(&mut userdata, game_id, &mut gamedata, &mut |scores| {
+                should_remove = true;
+                should_commit_scores = scores;
+            });
+            if should_remove {
+                self.remove_game(game_id).await;
+                userdata.player.turn_games.remove(&enemy_id);
+                if udata2.player.turn_games.get(&own_id) == Some(&game_id) {
+                    udata2.player.turn_games.remove(&own_id);
+                }
+            }
+            (should_remove, should_commit_scores, res)
+        };
+        if should_remove {
+            self.retire_game(game_id, &gamedata, should_commit_scores).await;
+        }
+        res
+    }
 
-        let mut should_remove = false;
-        let mut should_commit_scores = false;
-        let res = f(userdata, game_id, gamedata, &mut |scores| {
-            should_remove = true;
-            should_commit_scores = scores;
+    // End a game on a moderator's say, whatever state it's in, committing its scores so far if asked to.
+    // With `guild_id`, only games started there can be ended.
+    // Its players are let go and invites to it withdrawn. Returns the players.
+    pub async fn end_game(&self,
+        game_id: GameId, commit_scores: bool, guild_id: Option<GuildId>
+    ) -> CmdResult<(UserId, UserId)> {
+        let game_handle = self.game(game_id).await.ok_or(CmdError::NoSuchGame(game_id))?;
+        let gamedata = game_handle.lock().await;
+        if guild_id.is_some() && gamedata.get_guild() != guild_id {
+            return Err(CmdError::NoSuchGame(game_id));
+        }
+        // Someone may have finished it while we waited for the lock
+        if self.remove_game(game_id).await.is_none() {
+            return Err(CmdError::NoSuchGame(game_id));
+        }
+        let players = (gamedata.get_user_id(0), gamedata.get_user_id(1));
+        let variant = gamedata.get_variant();
+        // One user at a time in ascending order, they don't need to change together
+        let mut sides = [(players.0, players.1), (players.1, players.0)];
+        sides.sort();
+        for (id, enemy_id) in sides {
+            if let Some(handle) = self.find_user(id).await {
+                let mut userdata = handle.lock().await;
+                userdata.player.forget_game(enemy_id, game_id);
+                if userdata.player.list(variant).get(&enemy_id).is_some_and(|i| i.game == game_id) {
+                    userdata.player.remove_invite(variant, enemy_id);
+                }
+            }
+        }
+        self.open_challenges.write().await.retain(|_, c| c.invite.game != game_id);
+        tracing::info!(game = game_id, scored = commit_scores, "game ended by a moderator");
+        self.retire_game(game_id, &gamedata, commit_scores).await;
+        Ok(players)
+    }
+
+    // Drop expired invites along with their games, then drop expired games.
+    // Locks one user or game at a time, so the rest of the bot keeps running meanwhile.
+    // Returns the expired invites as (challenger, challenged, variant, invite),
+    // open challenges aside, so challengers can be told.
+    pub async fn cleanup(&self, now: SystemTime) -> Vec<(UserId, UserId, GameVariant, Invite)> {
+        let users: Vec<_> = self.userdata.read().await
+            .iter()
+            .map(|(id, u)| (*id, u.clone()))
+            .collect();
+        let mut expired = Vec::new();
+        for (id, handle) in users {
+            handle.lock().await.player.clean_invites_then(now, |variant, from, invite| {
+                tracing::info!(game = invite.game, challenger = %from, challenged = %id, "invite expired");
+                expired.push((from, id, variant, invite.clone()));
+            });
+        }
+        let mut expired_open = Vec::new();
+        self.open_challenges.write().await.retain(|id, c| c.invite.expiry > now || {
+            tracing::info!(game = c.invite.game, challenger = %id, "open challenge expired");
+            expired_open.push((*id, multiplayer::OPEN_SEAT, c.invite.game));
+            false
         });
-        if should_commit_scores {
+
+        // Challengers shouldn't be left waiting on games that are gone
+        let mut void = expired_open;
+        void.extend(expired.iter().map(|(from, to, _, invite)| (*from, *to, invite.game)));
+        for &(from, to, game_id) in &void {
+            if let Some(handle) = self.find_user(from).await {
+                handle.lock().await.player.forget_game(to, game_id);
+            }
+        }
+        for (_, _, game_id) in void {
+            if let Some(handle) = self.remove_game(game_id).await {
+                self.retire_game(game_id, &*handle.lock().await, false).await;
+            }
+        }
+
+        let games: Vec<_> = self.mpgames.read().await
+            .iter()
+            .map(|(id, g)| (*id, g.clone()))
+            .collect();
+        for (id, handle) in games {
+            let gamedata = handle.lock().await;
+            if gamedata.is_expiring(self.config.expiry.timed_game) {
+                tracing::info!(game = id, progress = ?gamedata.get_progress(), "game expired");
+                self.remove_game(id).await;
+                self.retire_game(id, &gamedata, false).await;
+            }
+        }
+        self.limits().cleanup(std::time::Instant::now());
+        expired
+    }
+
+    // Scores and finished games as exported, the games limited by `filter`.
+    pub async fn snapshot(&self, filter: &export::Filter) -> export::Snapshot {
+        let scores = self.scores().list_top(usize::MAX).await
+            .into_iter()
+            .map(|(user, score)| export::ScoreRow { user, score })
+            .collect();
+        let games = self.archive().list().await;
+        export::Snapshot { scores, games }.filtered(filter)
+    }
+
+    // Restore from an export: listed players get their scores back, games go back to the archive.
+    // New games are numbered after the restored ones, so they don't replace them.
+    // Returns how many scores and games were restored.
+    pub async fn restore(&self, snapshot: export::Snapshot) -> (usize, usize) {
+        let counts = (snapshot.scores.len(), snapshot.games.len());
+        for s in snapshot.scores {
+            self.scores().set(s.user, s.score).await;
+        }
+        if let Some(last) = snapshot.games.iter().map(|g| g.id).max() {
+            self.skip_gameids(last);
+        }
+        for g in snapshot.games {
+            self.archive().add(g).await;
+        }
+        counts
+    }
+
+    // Bookkeeping for a game that was just removed: commit scores if asked to,
+    // archive it if it got past the invite, finished or not, and move its series on.
+    // Takes the score manager and the archive, so no user may be locked.
+    async fn retire_game(&self, game_id: GameId, gamedata: &GameMP, commit_scores: bool) {
+        use multiplayer::GameProgress::*;
+        match gamedata.get_progress() {
+            Over(res) => tracing::info!(
+                game = game_id,
+                winner = ?res.map(|i| gamedata.get_user_id(i).0),
+                scored = commit_scores,
+                "game over",
+            ),
+            progress => tracing::info!(game = game_id, ?progress, "game ended early"),
+        }
+        if commit_scores {
             self.scores().add_from_game(gamedata).await;
         }
-        if should_remove {
-            mplock.remove(&game_id);
-            userdata.player.turn_games.remove(&enemy_id);
-            if let Some(udata2) = udlock.get_mut(&enemy_id) {
-                udata2.player.turn_games.remove(&own_id);
+        if !matches!(gamedata.get_progress(), Waiting) {
+            self.archive().add(gamedata.archive(game_id)).await;
+        }
+        let key = series_key(gamedata.get_user_id(0), gamedata.get_user_id(1));
+        if let Some(series) = self.series.write().await.get_mut(&key) {
+            match gamedata.get_progress() {
+                Waiting => series.void(game_id),
+                Over(res) => series.record(game_id, res.map(|i| gamedata.get_user_id(i))),
+                // Interrupted midway, counts as a draw
+                _ => series.record(game_id, None),
             }
         }
-        res
     }
 }
 
@@ -189,9 +1005,14 @@ mod test {
     use crate::dict;
     use crate::commands::util::CmdError;
     use dict::{Dictionary, DictSet};
+    use crate::config::Config;
+    use crate::data::guilds::GuildManager;
+    use dict::definitions::Definitions;
     use GameVariant::*;
     use multiplayer::GameProgress::*;
-    
+    use std::sync::Arc;
+    use std::time::Duration;
+
     fn get_dict() -> Dictionary {
         let micro_dictset =
             ["rover", "tower", "ready", "tears", "river", "smile", "quick", "slate", "sheet", "rails"]
@@ -206,10 +1027,10 @@ mod test {
         let u1 = UserId::from(44210404);
         let u2 = UserId::from(44210405);
         let u3 = UserId::from(44210406);
-        let ctx = CtxData::new(get_dict());
-        assert!(ctx.accept_invite(u2, u1, "tower".to_string(), TurnBased).await.is_err());
-        assert!(ctx.challenge_player(u1, u2, "rails".to_string(), TurnBased).await.is_ok());
-        assert!(ctx.accept_invite(u2, u1, "tower".to_string(), TurnBased).await.is_ok());
+        let ctx = CtxData::new(Config::default(), get_dict(), Definitions::default(), GuildManager::new());
+        assert!(ctx.accept_invite(u2, u1, "tower", TurnBased).await.is_err());
+        assert!(ctx.challenge_player(u1, u2, "rails".to_string(), TurnBased, None).await.is_ok());
+        assert!(ctx.accept_invite(u2, u1, "tower", TurnBased).await.is_ok());
         
         assert!(matches!(ctx.act_on_turnbased(u3, u2, |_, _, _, _| Ok(())).await,
             Err(CmdError::NoGame)));
@@ -228,7 +1049,7 @@ mod test {
                 match g.get_progress() {
                     Ending(_) => {},
                     Over(Some(_)) => {r(true);},
-                    _ => {assert!(false);},
+                    _ => {panic!("unexpected progress");},
                 }
                 Ok(())
             }).await.is_ok());
@@ -241,5 +1062,393 @@ mod test {
         assert!(scores.len() == 2);
         assert!(scores[0].0 == u2);
         assert!(scores[1].0 == u1);
+        assert_eq!(ctx.scores().standing(u1).await, Some((2, scores[1].1)));
+        assert!(scores[0].1 >= scores[1].1);
+    }
+
+    // Send a guess on behalf of `own_id`, committing scores once the game is over.
+    async fn guess(ctx: &CtxData, own_id: UserId, enemy_id: UserId, variant: GameVariant, word: &str) -> CmdResult<()> {
+        let f = |_: &mut UserData, _: GameId, g: &mut GameMP, r: &mut dyn FnMut(bool)| {
+            let index = g.match_user(own_id).unwrap();
+            assert!(g.send_guess(index, word.to_string()));
+            if matches!(g.get_progress(), Over(_)) {
+                r(true);
+            }
+            Ok(())
+        };
+        match variant {
+            Timed => ctx.act_on_timed(own_id, f).await,
+            TurnBased => ctx.act_on_turnbased(own_id, enemy_id, f).await,
+        }
+    }
+
+    async fn start_game(ctx: &CtxData, a: UserId, b: UserId, variant: GameVariant) {
+        ctx.challenge_player(a, b, "rails".to_string(), variant, None).await.unwrap();
+        tokio::task::yield_now().await;
+        ctx.accept_invite(b, a, "tower", variant).await.unwrap();
+    }
+
+    // Play a started game until scores are in, yielding between steps.
+    async fn finish_game(ctx: &CtxData, a: UserId, b: UserId, variant: GameVariant) {
+        for (own, enemy, w) in [(a, b, "slate"), (b, a, "river"), (a, b, "tower"), (b, a, "ready"), (b, a, "rails")] {
+            tokio::task::yield_now().await;
+            guess(ctx, own, enemy, variant, w).await.unwrap();
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_concurrent_games() {
+        const PAIRS: u64 = 64;
+        let ctx = Arc::new(CtxData::new(Config::default(), get_dict(), Definitions::default(), GuildManager::new()));
+        let a = |i: u64| UserId::from(1_000 + i);
+        let b = |i: u64| UserId::from(2_000 + i);
+
+        // Every player is in a timed game with one opponent
+        // and in a turn-based game with another, all played at once.
+        // Nobody can accept anything while in a timed game,
+        // so turn-based games are all started first.
+        let games = |variant| (0..PAIRS).map(move |i| match variant {
+            Timed => (a(i), b(i), Timed),
+            TurnBased => (b(i), a((i + 1) % PAIRS), TurnBased),
+        });
+        for phase in [TurnBased, Timed] {
+            let tasks: Vec<_> = games(phase).map(|(x, y, v)| {
+                let ctx = ctx.clone();
+                tokio::spawn(async move { start_game(&ctx, x, y, v).await })
+            }).collect();
+            for t in tasks {
+                t.await.unwrap();
+            }
+        }
+        let tasks: Vec<_> = games(TurnBased).chain(games(Timed)).map(|(x, y, v)| {
+            let ctx = ctx.clone();
+            tokio::spawn(async move { finish_game(&ctx, x, y, v).await })
+        }).collect();
+        for t in tasks {
+            t.await.unwrap();
+        }
+
+        assert!(ctx.mpgames.read().await.is_empty());
+        for i in 0..PAIRS {
+            for id in [a(i), b(i)] {
+                let handle = ctx.find_user(id).await.unwrap();
+                let userdata = handle.lock().await;
+                assert!(userdata.player.timed_game.is_none());
+                assert!(userdata.player.turn_games.is_empty());
+                assert!(ctx.scores().get(id).await.unwrap() > 0);
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_accept_in_timed_game() {
+        let (u1, u2, u3) = (UserId::from(21), UserId::from(22), UserId::from(23));
+        let ctx = CtxData::new(Config::default(), get_dict(), Definitions::default(), GuildManager::new());
+        ctx.challenge_player(u1, u2, "rails".to_string(), Timed, None).await.unwrap();
+        ctx.challenge_player(u3, u1, "rails".to_string(), TurnBased, None).await.unwrap();
+        // No invite of any kind can be accepted while in a timed game
+        assert!(matches!(ctx.accept_invite(u1, u3, "tower", TurnBased).await,
+            Err(CmdError::SelfInGame)));
+    }
+
+    #[tokio::test]
+    async fn test_game_guild_words() {
+        let (u1, u2, u3) = (UserId::from(31), UserId::from(32), UserId::from(33));
+        let guild = GuildId(77);
+        let ctx = CtxData::new(Config::default(), get_dict(), Definitions::default(), GuildManager::new());
+        ctx.guilds().with_write(guild, |g| {
+            g.words.allow.insert("wordy".to_string());
+            g.words.deny.insert("tower".to_string());
+        }).await.unwrap();
+
+        // The lists of the guild the game was made in apply, wherever the command comes from
+        ctx.challenge_player(u1, u2, "rails".to_string(), TurnBased, Some(guild)).await.unwrap();
+        assert!(matches!(ctx.accept_invite(u2, u1, "tower", TurnBased).await, Err(CmdError::WordNotFound(_))));
+        assert_eq!(ctx.accept_invite(u2, u1, "wordy", TurnBased).await.unwrap().1, "wordy");
+        assert!(ctx.ensure_guess(u1, Some(u2), "wordy").await.is_ok());
+        assert!(ctx.ensure_guess(u1, Some(u2), "tower").await.is_err());
+
+        ctx.challenge_player(u3, u1, "rails".to_string(), TurnBased, None).await.unwrap();
+        assert!(ctx.accept_invite(u1, u3, "wordy", TurnBased).await.is_err());
+        assert!(ctx.accept_invite(u1, u3, "tower", TurnBased).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_guild_settings() {
+        let (u1, u2, u3) = (UserId::from(41), UserId::from(42), UserId::from(43));
+        let (guild, here, elsewhere) = (GuildId(78), ChannelId(10), ChannelId(11));
+        let ctx = CtxData::new(Config::default(), get_dict(), Definitions::default(), GuildManager::new());
+        ctx.guilds().with_write(guild, |g| {
+            g.game_channels.insert(here);
+            g.guesses = Some(3);
+            g.invite_expiry = Some(60);
+            g.random_words = false;
+        }).await.unwrap();
+
+        let options = |channel| ChallengeOptions { guild: Some(guild), channel: Some(channel), ..Default::default() };
+        let res = ctx.challenge_with(u1, u2, "rails".to_string(), TurnBased, options(elsewhere)).await;
+        assert!(matches!(res, Err(CmdError::ChannelNotAllowed(c)) if c == vec![here]));
+        assert!(matches!(ctx.join_queue(u1, Timed, 5, elsewhere, Some(guild)).await, Err(CmdError::ChannelNotAllowed(_))));
+        let game_id = ctx.challenge_with(u1, u2, "rails".to_string(), TurnBased, options(here)).await.unwrap();
+        assert_eq!(ctx.game(game_id).await.unwrap().lock().await.get_max_guesses(), 3);
+        let (incoming, _) = ctx.invites_of(u2).await;
+        assert!(incoming[0].2 <= SystemTime::now() + Duration::from_secs(60));
+
+        // No random words, neither for challenges nor in answers
+        assert!(matches!(ctx.player_word(Some(guild), None).await, Err(CmdError::RandomWordsOff)));
+        assert!(matches!(ctx.player_word(Some(guild), Some("5")).await, Err(CmdError::RandomWordsOff)));
+        assert!(matches!(ctx.accept_invite(u2, u1, "5", TurnBased).await, Err(CmdError::RandomWordsOff)));
+        assert!(ctx.accept_invite(u2, u1, "tower", TurnBased).await.is_ok());
+
+        // Elsewhere, a missing word is a random one of the default length
+        assert_eq!(ctx.player_word(None, None).await.unwrap().len(), 5);
+        ctx.challenge_player(u3, u1, "rails".to_string(), TurnBased, None).await.unwrap();
+        assert!(ctx.accept_invite(u1, u3, "5", TurnBased).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_moderation() {
+        let (u1, u2, u3) = (UserId::from(41), UserId::from(42), UserId::from(43));
+        let (guild, other) = (GuildId(78), GuildId(79));
+        let ctx = CtxData::new(Config::default(), get_dict(), Definitions::default(), GuildManager::new());
+        let game_id = ctx.challenge_player(u1, u2, "rails".to_string(), Timed, Some(guild)).await.unwrap();
+        ctx.accept_invite(u2, u1, "tower", Timed).await.unwrap();
+        guess(&ctx, u1, u2, Timed, "tower").await.unwrap();
+
+        // Only games of the moderator's guild
+        assert!(matches!(ctx.end_game(game_id, true, Some(other)).await, Err(CmdError::NoSuchGame(_))));
+        assert_eq!(ctx.end_game(game_id, true, Some(guild)).await.unwrap(), (u1, u2));
+        assert!(ctx.game(game_id).await.is_none());
+        assert!(!ctx.in_game(u1).await && !ctx.in_game(u2).await);
+        assert!(ctx.scores().get(u1).await.is_some());
+        assert!(ctx.archive().get(game_id).await.is_some());
+        assert!(matches!(ctx.end_game(game_id, true, None).await, Err(CmdError::NoSuchGame(_))));
+
+        // Waiting invites go along with their games
+        let game_id = ctx.challenge_player(u1, u3, "rails".to_string(), TurnBased, None).await.unwrap();
+        ctx.end_game(game_id, false, None).await.unwrap();
+        assert!(ctx.invites_of(u3).await.0.is_empty());
+
+        let until = SystemTime::now() + Duration::from_secs(3600);
+        ctx.guilds().with_write(guild, |g| {
+            g.bans.insert(u1, crate::data::audit::unix_secs(until));
+            g.bans.insert(u3, 0); // ran out long ago
+        }).await.unwrap();
+        let res = ctx.challenge_player(u1, u2, "rails".to_string(), TurnBased, Some(guild)).await;
+        assert!(matches!(res, Err(CmdError::Banned(_))));
+        assert!(matches!(ctx.join_queue(u1, Timed, 5, ChannelId(10), Some(guild)).await, Err(CmdError::Banned(_))));
+        // Banned players may still be challenged, and play elsewhere
+        ctx.challenge_player(u2, u1, "rails".to_string(), TurnBased, Some(guild)).await.unwrap();
+        ctx.challenge_player(u1, u2, "rails".to_string(), Timed, Some(other)).await.unwrap();
+        ctx.challenge_player(u3, u2, "rails".to_string(), Timed, Some(guild)).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_privacy() {
+        let (u1, u2, u3) = (UserId::from(41), UserId::from(42), UserId::from(43));
+        let ctx = CtxData::new(Config::default(), get_dict(), Definitions::default(), GuildManager::new());
+        ctx.challenge_player(u1, u2, "rails".to_string(), TurnBased, None).await.unwrap();
+        // Blocking turns down what's waiting, and what's to come
+        ctx.block(u2, u1, true).await;
+        assert!(ctx.invites_of(u2).await.0.is_empty());
+        let res = ctx.challenge_player(u1, u2, "rails".to_string(), TurnBased, None).await;
+        assert!(matches!(res, Err(CmdError::ChallengeRefused(id)) if id == u2));
+        assert!(!ctx.in_game(u1).await);
+
+        ctx.set_privacy(u3, |p| p.dnd = true).await;
+        let res = ctx.challenge_player(u1, u3, "rails".to_string(), Timed, None).await;
+        assert!(matches!(res, Err(CmdError::ChallengeRefused(_))));
+        // Do not disturb doesn't keep players out of matchmaking: by joining the queue
+        // they asked for a game, as /wd_privacy says. Blocking still does.
+        let entry = |id| queue::QueueEntry {
+            id, rating: 0, since: SystemTime::now(), channel: ChannelId(10), guild: None,
+        };
+        ctx.start_matched((Timed, 5), &entry(u1), &entry(u3)).await.unwrap();
+        let res = ctx.start_matched((TurnBased, 5), &entry(u1), &entry(u2)).await;
+        assert!(matches!(res, Err(CmdError::ChallengeRefused(_))));
+    }
+
+    #[tokio::test]
+    async fn test_in_game() {
+        let (u1, u2, u3) = (UserId::from(41), UserId::from(42), UserId::from(43));
+        let ctx = CtxData::new(Config::default(), get_dict(), Definitions::default(), GuildManager::new());
+        assert!(!ctx.in_game(u1).await);
+        let timed = ctx.challenge_player(u1, u2, "rails".to_string(), Timed, None).await.unwrap();
+        let turn = ctx.challenge_player(u1, u3, "rails".to_string(), TurnBased, None).await.unwrap();
+        assert!(ctx.in_game(u1).await);
+
+        // Games dropped by cleanup no longer count
+        ctx.remove_game(timed).await;
+        assert!(ctx.in_game(u1).await);
+        ctx.remove_game(turn).await;
+        assert!(!ctx.in_game(u1).await);
+        let handle = ctx.find_user(u1).await.unwrap();
+        let userdata = handle.lock().await;
+        assert!(userdata.player.timed_game.is_none());
+        assert!(userdata.player.turn_games.is_empty());
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_invite_inbox() {
+        let (u1, u2, u3) = (UserId::from(51), UserId::from(52), UserId::from(53));
+        let ctx = CtxData::new(Config::default(), get_dict(), Definitions::default(), GuildManager::new());
+        ctx.challenge_player(u1, u2, "rails".to_string(), TurnBased, None).await.unwrap();
+        ctx.challenge_player(u1, u3, "rails".to_string(), Timed, None).await.unwrap();
+        let (incoming, outgoing) = ctx.invites_of(u2).await;
+        assert_eq!(incoming.iter().map(|i| (i.0, i.1)).collect::<Vec<_>>(), vec![(u1, TurnBased)]);
+        assert!(outgoing.is_empty());
+        let (incoming, outgoing) = ctx.invites_of(u1).await;
+        assert!(incoming.is_empty());
+        assert_eq!(outgoing.len(), 2);
+
+        // Withdrawn
+        assert!(matches!(ctx.cancel_invite(u1, u2, Timed).await, Err(CmdError::NoOutgoingInvite)));
+        ctx.cancel_invite(u1, u3, Timed).await.unwrap();
+        assert!(ctx.invites_of(u3).await.0.is_empty());
+        assert_eq!(ctx.invites_of(u1).await.1.len(), 1);
+
+        // Rejected
+        ctx.challenge_player(u3, u1, "rails".to_string(), TurnBased, None).await.unwrap();
+        assert!(ctx.reject_invite(u1, u3, TurnBased).await.unwrap().is_some());
+        assert!(ctx.find_user(u3).await.unwrap().lock().await.player.turn_games.is_empty());
+
+        // Expired
+        let expired = ctx.cleanup(SystemTime::now() + Duration::from_secs(3600)).await;
+        assert_eq!(expired.iter().map(|e| (e.0, e.1, e.2)).collect::<Vec<_>>(), vec![(u1, u2, TurnBased)]);
+        assert!(ctx.cleanup(SystemTime::now() + Duration::from_secs(3600)).await.is_empty());
+        assert!(ctx.game(expired[0].3.game).await.is_none());
+        assert_eq!(ctx.invites_of(u1).await, (Vec::new(), Vec::new()));
+        assert!(!ctx.in_game(u1).await);
+    }
+
+    #[tokio::test]
+    async fn test_archive_forfeits() {
+        let (u1, u2, u3) = (UserId::from(51), UserId::from(52), UserId::from(53));
+        let ctx = CtxData::new(Config::default(), get_dict(), Definitions::default(), GuildManager::new());
+        let forfeit = |_: &mut UserData, _: GameId, _: &mut GameMP, r: &mut dyn FnMut(bool)| {
+            r(false);
+            Ok(())
+        };
+
+        let started = ctx.challenge_player(u1, u2, "rails".to_string(), TurnBased, None).await.unwrap();
+        ctx.accept_invite(u2, u1, "tower", TurnBased).await.unwrap();
+        guess(&ctx, u1, u2, TurnBased, "slate").await.unwrap();
+        ctx.act_on_turnbased(u2, u1, forfeit).await.unwrap();
+        assert_eq!(ctx.archive().get(started).await.unwrap().guesses[0], vec!["slate"]);
+
+        // Never accepted, nothing to analyse
+        let waiting = ctx.challenge_player(u1, u3, "rails".to_string(), TurnBased, None).await.unwrap();
+        ctx.act_on_turnbased(u1, u3, forfeit).await.unwrap();
+        assert!(ctx.archive().get(waiting).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_open_challenges() {
+        let (u1, u2, u3, u4) = (UserId::from(61), UserId::from(62), UserId::from(63), UserId::from(64));
+        let (here, elsewhere) = (ChannelId(1), ChannelId(2));
+        let ctx = CtxData::new(Config::default(), get_dict(), Definitions::default(), GuildManager::new());
+        ctx.scores().add(u3, 50).await;
+        let eligible = Eligibility { role: None, rating: (None, Some(10)) };
+        let game_id = ctx.open_challenge(u1, "rails".to_string(), TurnBased, None, here, eligible.clone()).await.unwrap();
+        assert!(matches!(ctx.open_challenge(u1, "rails".to_string(), TurnBased, None, here, eligible).await,
+            Err(CmdError::OpenChallengeExists)));
+
+        // No open challenge of the other variant, nothing to claim
+        assert!(!ctx.claim_open(u2, u1, Timed, here, &[]).await.unwrap());
+        assert!(matches!(ctx.claim_open(u2, u1, TurnBased, elsewhere, &[]).await, Err(CmdError::WrongChannel)));
+        assert!(matches!(ctx.claim_open(u3, u1, TurnBased, here, &[]).await, Err(CmdError::NotEligible)));
+
+        // First one in gets it, then it's an ordinary invite
+        assert!(ctx.claim_open(u2, u1, TurnBased, here, &[]).await.unwrap());
+        assert!(!ctx.claim_open(u4, u1, TurnBased, here, &[]).await.unwrap());
+        assert_eq!(ctx.accept_invite(u2, u1, "tower", TurnBased).await.unwrap().0, game_id);
+        guess(&ctx, u1, u2, TurnBased, "slate").await.unwrap();
+
+        // Unclaimed ones expire like invites
+        let timed = ctx.open_challenge(u4, "rails".to_string(), Timed, None, here, Eligibility::default()).await.unwrap();
+        assert!(ctx.in_game(u4).await);
+        ctx.cleanup(SystemTime::now() + ctx.config.expiry.timed_invite).await;
+        assert!(ctx.game(timed).await.is_none());
+        assert!(!ctx.in_game(u4).await);
+        assert!(matches!(ctx.cancel_open(u4).await, Err(CmdError::NoOpenChallenge)));
+    }
+
+    #[tokio::test]
+    async fn test_matched_game() {
+        let (u1, u2) = (UserId::from(91), UserId::from(92));
+        let ctx = CtxData::new(Config::default(), get_dict(), Definitions::default(), GuildManager::new());
+        assert!(matches!(ctx.join_queue(u1, Timed, 12, ChannelId(1), None).await, Err(CmdError::BadWordLength { length: 12, .. })));
+        ctx.join_queue(u1, Timed, 5, ChannelId(1), None).await.unwrap();
+        assert!(matches!(ctx.join_queue(u1, TurnBased, 5, ChannelId(1), None).await, Err(CmdError::AlreadyQueued)));
+        ctx.join_queue(u2, Timed, 5, ChannelId(2), None).await.unwrap();
+
+        let (pairs, _) = ctx.queue().pair(SystemTime::now()).await;
+        let (key, a, b) = &pairs[0];
+        let game_id = ctx.start_matched(*key, a, b).await.unwrap();
+        let handle = ctx.game(game_id).await.unwrap();
+        assert!(matches!(handle.lock().await.get_progress(), Started));
+        assert!(ctx.in_game(u1).await && ctx.in_game(u2).await);
+
+        // Can't start another timed game, and nothing is left behind
+        assert!(ctx.start_matched(*key, a, b).await.is_err());
+        assert_eq!(ctx.mpgames.read().await.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_series() {
+        let (u1, u2, u3) = (UserId::from(101), UserId::from(102), UserId::from(103));
+        let ctx = CtxData::new(Config::default(), get_dict(), Definitions::default(), GuildManager::new());
+        assert!(matches!(ctx.start_series(u1, u2, 4, "rails".to_string(), TurnBased, Default::default()).await,
+            Err(CmdError::BadSeriesLength(4))));
+        let first = ctx.start_series(u1, u2, 3, "rails".to_string(), TurnBased, Default::default()).await.unwrap();
+        assert!(matches!(ctx.start_series(u2, u1, 3, "rails".to_string(), TurnBased, Default::default()).await,
+            Err(CmdError::SeriesExists)));
+        ctx.accept_invite(u2, u1, "tower", TurnBased).await.unwrap();
+        finish_game(&ctx, u1, u2, TurnBased).await;
+
+        let series = ctx.series_after(first, u1, u2).await.unwrap();
+        assert!(!series.is_over());
+        assert_eq!(series.next_picker(), u2);
+        assert!(matches!(ctx.challenge_player(u1, u2, "rails".to_string(), TurnBased, None).await,
+            Err(CmdError::NotYourPick)));
+        // Other variants and other players are unaffected
+        assert!(ctx.challenge_player(u2, u1, "rails".to_string(), Timed, None).await.is_ok());
+        assert!(ctx.challenge_player(u3, u2, "rails".to_string(), TurnBased, None).await.is_ok());
+
+        // Forfeits count, and the rematch button works from the archive
+        let second = ctx.challenge_player(u2, u1, "rails".to_string(), TurnBased, None).await.unwrap();
+        ctx.accept_invite(u1, u2, "tower", TurnBased).await.unwrap();
+        ctx.act_on_turnbased(u1, u2, |_, _, g, r| {
+            g.forfeit(g.match_user(u1).unwrap());
+            r(false);
+            Ok(())
+        }).await.unwrap();
+        assert!(ctx.series_after(second, u1, u2).await.unwrap().has_game(second));
+        assert!(matches!(ctx.rematch(u3, second, None, Vec::new()).await, Err(CmdError::BadRematch)));
+        let (_, enemy) = ctx.rematch(u1, second, None, Vec::new()).await.unwrap();
+        assert_eq!(enemy, u2);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_game_lock_isolation() {
+        let (u1, u2, u3, u4) = (UserId::from(11), UserId::from(12), UserId::from(13), UserId::from(14));
+        let ctx = CtxData::new(Config::default(), get_dict(), Definitions::default(), GuildManager::new());
+        let busy = ctx.challenge_player(u1, u2, "rails".to_string(), TurnBased, None).await.unwrap();
+        ctx.accept_invite(u2, u1, "tower", TurnBased).await.unwrap();
+        ctx.challenge_player(u3, u4, "rails".to_string(), TurnBased, None).await.unwrap();
+        ctx.accept_invite(u4, u3, "tower", TurnBased).await.unwrap();
+
+        let handle = ctx.game(busy).await.unwrap();
+        let _guard = handle.lock().await;
+
+        // Unrelated game goes on...
+        tokio::time::timeout(Duration::from_secs(5), guess(&ctx, u3, u4, TurnBased, "slate"))
+            .await
+            .expect("blocked by an unrelated game")
+            .unwrap();
+        // ...while the held one waits.
+        assert!(tokio::time::timeout(Duration::from_millis(100), guess(&ctx, u1, u2, TurnBased, "slate"))
+            .await
+            .is_err());
+    }
+}
@@ -1,55 +1,312 @@
-use std::{fmt, error};
-
-/// Printable Discord command response.
-pub trait CmdResponse {
-    fn print<'a>(&self) -> String;
-}
+use std::{fmt, error, sync::Arc};
+use std::time::{Duration, SystemTime};
+use crate::game::GameId;
+use super::perms::Tier;
+use super::queries;
+use poise::serenity_prelude::{ChannelId, Mentionable, UserId};
+use crate::i18n::{self, tr, Lang};
+use crate::{Context, CtxData};
 
 /// Invocation-related command error.
 #[derive(Debug)]
 pub enum CmdError {
-    BadWordLength(usize),
+    BadWordLength { length: usize, min: usize, max: usize }, // outside of the allowed lengths
     WordNotFound(String),
+    InvalidWord(String), // not something that could be a word
     NoGame, // no game to operate on
     BadAccept, // cannot accept this game
     ChallengedSelf, // tried to challenge self
     NoInvite, // no invite from this user
+    NoOutgoingInvite, // no pending invite to this user
     SelfInGame, // you're in a game but shouldn't be
+    ExplorerInGame, // dictionary explorer used during a game
     TargetInGame, // opponent's in a game but shouldn't be
     GameDeleted, // game assigned but deleted
     ForfeitBadUser, // didn't mention the right user for a forfeiture
     GameStarted(bool), // game started?(bool) but opposite was expected
+    NoOpenChallenge, // nothing to cancel
+    OpenChallengeExists, // only one open challenge at a time
+    NotEligible, // open challenge restricted to others
+    WrongChannel, // open challenge posted elsewhere
+    AlreadyQueued, // waiting for a match already
+    NotQueued, // left a queue without being in one
+    BadSeriesLength(usize), // series other than best of 3, 5 or 7
+    SeriesExists, // pair already plays a series
+    SeriesGameRunning, // series game going on already
+    NotYourPick, // other player's turn to challenge in a series
+    BadRematch, // rematch asked by someone who didn't play
+    BadHandicap { guesses: usize, seconds: u64 }, // handicap over these limits or for someone not playing
+    ChallengeRefused(UserId), // they don't take challenges from this user, whatever the reason
+    NoWatchedGame, // no single game to spectate
+    WatchedOwnGame, // players can't spectate their own game
+    PredictionClosed, // prediction made after a player finished
+    BadPrediction, // predicted winner isn't playing
+    NoArchivedGame(GameId), // game never finished or forgotten
+    NoSuchGame(GameId), // no game going on with this number
+    NoPack(String), // no word pack of this name
+    BadListSize, // leaderboard size out of range
+    ChannelNotAllowed(Vec<ChannelId>), // games go to these channels of the guild
+    RandomWordsOff, // guild wants players to pick their words
+    BadSetting { name: &'static str, min: u64, max: u64 }, // guild setting out of range
+    LeaderboardOff, // guild hid the leaderboard
+    CsvUnavailable, // export only makes sense as JSON
+    BadImport(String), // file to import isn't an export, with what's wrong
+    BadDate(String), // not a date
+    Forbidden(Tier), // command needs this tier at least
+    Banned(SystemTime), // may not challenge anyone in the guild until then
+    SlowDown(Duration), // too many commands lately, may use one again after this long
+    TooManyInvites(usize), // this many invites waiting already
+    TooManyTurnGames(usize), // in this many turn-based games already
+    GuildGamesFull(usize), // guild has this many games going on already
     #[allow(dead_code)]
     Misc(String), // unsorted
     #[allow(dead_code)]
-    Hard(crate::Error), // pass down!
+    Hard(crate::Error), // not the user's fault, logged and reported with a code
 }
 
 pub type CmdResult<R> = Result<R, CmdError>;
 
-impl fmt::Display for CmdError {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+impl CmdError {
+    // The message shown to users in plain text, in their language.
+    pub fn localize(&self, lang: Lang) -> String {
+        format!("{} {}", tr!(lang, "err.prefix"), self.message(lang))
+    }
+
+    // What went wrong, without the prefix.
+    pub fn message(&self, lang: Lang) -> String {
+        use CmdError::*;
+        match self {
+            BadWordLength { length, .. } => tr!(lang, "err.bad_word_length", length = length),
+            WordNotFound(s) => tr!(lang, "err.word_not_found", word = s),
+            InvalidWord(s) => tr!(lang, "err.invalid_word", word = s),
+            NoGame => tr!(lang, "err.no_game"),
+            BadAccept => tr!(lang, "err.bad_accept"),
+            ChallengedSelf => tr!(lang, "err.challenged_self"),
+            NoInvite => tr!(lang, "err.no_invite"),
+            NoOutgoingInvite => tr!(lang, "err.no_outgoing_invite"),
+            SelfInGame => tr!(lang, "err.self_in_game"),
+            ExplorerInGame => tr!(lang, "err.explorer_in_game"),
+            TargetInGame => tr!(lang, "err.target_in_game"),
+            GameDeleted => tr!(lang, "err.game_deleted"),
+            ForfeitBadUser => tr!(lang, "err.forfeit_bad_user"),
+            GameStarted(true) => tr!(lang, "err.game_not_started"),
+            GameStarted(false) => tr!(lang, "err.game_already_started"),
+            NoOpenChallenge => tr!(lang, "err.no_open_challenge"),
+            OpenChallengeExists => tr!(lang, "err.open_challenge_exists"),
+            NotEligible => tr!(lang, "err.not_eligible"),
+            WrongChannel => tr!(lang, "err.wrong_channel"),
+            AlreadyQueued => tr!(lang, "err.already_queued"),
+            NotQueued => tr!(lang, "err.not_queued"),
+            BadSeriesLength(n) => tr!(lang, "err.bad_series_length", n = n),
+            SeriesExists => tr!(lang, "err.series_exists"),
+            SeriesGameRunning => tr!(lang, "err.series_game_running"),
+            NotYourPick => tr!(lang, "err.not_your_pick"),
+            BadRematch => tr!(lang, "err.bad_rematch"),
+            BadHandicap { guesses, seconds } => tr!(lang, "err.bad_handicap", guesses = guesses, seconds = seconds),
+            ChallengeRefused(user) => tr!(lang, "err.challenge_refused", user = i18n::user(*user)),
+            NoWatchedGame => tr!(lang, "err.no_watched_game"),
+            WatchedOwnGame => tr!(lang, "err.watched_own_game"),
+            PredictionClosed => tr!(lang, "err.prediction_closed"),
+            BadPrediction => tr!(lang, "err.bad_prediction"),
+            NoArchivedGame(game) => tr!(lang, "err.no_archived_game", game = game),
+            NoSuchGame(game) => tr!(lang, "err.no_such_game", game = game),
+            NoPack(name) => tr!(lang, "err.no_pack", name = name),
+            BadListSize => tr!(lang, "err.bad_list_size"),
+            ChannelNotAllowed(_) => tr!(lang, "err.channel_not_allowed"),
+            RandomWordsOff => tr!(lang, "err.random_words_off"),
+            BadSetting { name, min, max } => tr!(lang, "err.bad_setting", name = name, min = min, max = max),
+            LeaderboardOff => tr!(lang, "err.leaderboard_off"),
+            CsvUnavailable => tr!(lang, "err.csv_unavailable"),
+            BadImport(why) => tr!(lang, "err.bad_import", why = why),
+            BadDate(s) => tr!(lang, "err.bad_date", date = s),
+            Forbidden(Tier::Owner) => tr!(lang, "err.forbidden_owner"),
+            Forbidden(_) => tr!(lang, "err.forbidden_admin"),
+            Banned(until) => tr!(lang, "err.banned", until = queries::timestamp(*until)),
+            SlowDown(_) => tr!(lang, "err.slow_down"),
+            TooManyInvites(n) => tr!(lang, "err.too_many_invites", n = n),
+            TooManyTurnGames(n) => tr!(lang, "err.too_many_turn_games", n = n),
+            GuildGamesFull(n) => tr!(lang, "err.guild_games_full", n = n),
+            Misc(s) => s.clone(),
+            Hard(_) => tr!(lang, "err.unexpected"),
+        }
+    }
+
+    // What the user can do about it, if there's anything to suggest.
+    pub fn hint(&self, lang: Lang) -> Option<String> {
+        use CmdError::*;
+        let hint = match self {
+            BadWordLength { min, max, .. } if min == max => tr!(lang, "hint.word_length_game", length = min),
+            BadWordLength { min, max, .. } => tr!(lang, "hint.word_length", min = min, max = max),
+            WordNotFound(_) => tr!(lang, "hint.word_not_found"),
+            InvalidWord(_) => tr!(lang, "hint.invalid_word"),
+            ExplorerInGame | SelfInGame => tr!(lang, "hint.finish_game"),
+            ChallengedSelf => tr!(lang, "hint.challenged_self"),
+            NoInvite | BadAccept | NoOutgoingInvite => tr!(lang, "hint.invites"),
+            TargetInGame => tr!(lang, "hint.target_in_game"),
+            NoOpenChallenge => tr!(lang, "hint.no_open_challenge"),
+            OpenChallengeExists => tr!(lang, "hint.open_challenge_exists"),
+            NoGame => tr!(lang, "hint.no_game"),
+            ForfeitBadUser | NoWatchedGame => tr!(lang, "hint.name_opponent"),
+            GameStarted(true) => tr!(lang, "hint.game_not_started"),
+            NoArchivedGame(_) => tr!(lang, "hint.archived_game"),
+            AlreadyQueued => tr!(lang, "hint.already_queued"),
+            NotQueued => tr!(lang, "hint.not_queued"),
+            SeriesGameRunning => tr!(lang, "hint.series_game_running"),
+            NoPack(_) => tr!(lang, "hint.no_pack"),
+            BadListSize => tr!(lang, "hint.list_size"),
+            ChannelNotAllowed(channels) => tr!(lang, "hint.game_channels",
+                channels = channels.iter().map(|c| c.mention().to_string()).collect::<Vec<_>>().join(", ")),
+            RandomWordsOff => tr!(lang, "hint.random_words_off"),
+            BadImport(_) => tr!(lang, "hint.bad_import"),
+            BadDate(_) => tr!(lang, "hint.bad_date"),
+            Forbidden(Tier::Admin) => tr!(lang, "hint.forbidden_admin"),
+            SlowDown(wait) => tr!(lang, "hint.slow_down", n = wait.as_secs().max(1)),
+            TooManyInvites(_) => tr!(lang, "hint.too_many_invites"),
+            TooManyTurnGames(_) => tr!(lang, "hint.too_many_turn_games"),
+            GuildGamesFull(_) => tr!(lang, "hint.guild_games_full"),
+            Hard(_) => tr!(lang, "hint.report", code = self.code()),
+            _ => return None,
+        };
+        Some(hint)
+    }
+
+    // Stable code of the error, for players to report and to search logs by.
+    // Codes are never reused, new variants get new ones.
+    pub fn code(&self) -> &'static str {
         use CmdError::*;
-        write!(f, "**Error:** ")?;
         match self {
-            BadWordLength(size) => write!(f, "Word length invalid: {}", size),
-            WordNotFound(s) => write!(f, "Word not found in dictionary: {}", s),
-            NoGame => write!(f, "You are not in a game"),
-            BadAccept => write!(f, "Cannot accept this game"),
-            ChallengedSelf => write!(f, "Cannot challenge yourself to a game"),
-            NoInvite => write!(f, "No invite from this user"),
-            SelfInGame => write!(f, "You're already in a game"),
-            TargetInGame => write!(f, "Target is already in a game"),
-            GameDeleted => write!(f, "Game assigned but deleted"),
-            ForfeitBadUser => write!(f, "To forfeit, specify your opponent's name"),
-            GameStarted(s) => write!(f, "Game {} started", if *s {"not yet"} else {"already"}),
-            Misc(s) => s.fmt(f),
-            Hard(e) => {
-                write!(f, "An error thrown from Rust was intercepted without unwrapping.
-                    This should not appear.
-                    {}", e)
+            // Words
+            BadWordLength { .. } => "E101",
+            WordNotFound(_) => "E102",
+            InvalidWord(_) => "E103",
+            ExplorerInGame => "E104",
+            // Invites and open challenges
+            ChallengedSelf => "E201",
+            NoInvite => "E202",
+            NoOutgoingInvite => "E203",
+            BadAccept => "E204",
+            SelfInGame => "E205",
+            TargetInGame => "E206",
+            NoOpenChallenge => "E207",
+            OpenChallengeExists => "E208",
+            NotEligible => "E209",
+            WrongChannel => "E210",
+            BadHandicap { .. } => "E211",
+            ChallengeRefused(_) => "E212",
+            // Games
+            NoGame => "E301",
+            GameDeleted => "E302",
+            ForfeitBadUser => "E303",
+            GameStarted(true) => "E304",
+            GameStarted(false) => "E305",
+            NoArchivedGame(_) => "E306",
+            BadRematch => "E307",
+            NoSuchGame(_) => "E308",
+            // Matchmaking and series
+            AlreadyQueued => "E401",
+            NotQueued => "E402",
+            BadSeriesLength(_) => "E403",
+            SeriesExists => "E404",
+            SeriesGameRunning => "E405",
+            NotYourPick => "E406",
+            // Spectating
+            NoWatchedGame => "E501",
+            WatchedOwnGame => "E502",
+            PredictionClosed => "E503",
+            BadPrediction => "E504",
+            // Server settings and the rest
+            NoPack(_) => "E601",
+            BadListSize => "E602",
+            ChannelNotAllowed(_) => "E603",
+            RandomWordsOff => "E604",
+            BadSetting { .. } => "E605",
+            LeaderboardOff => "E606",
+            CsvUnavailable => "E607",
+            BadImport(_) => "E608",
+            BadDate(_) => "E609",
+            // Permissions
+            Forbidden(_) => "E701",
+            Banned(_) => "E702",
+            // Limits
+            SlowDown(_) => "E801",
+            TooManyInvites(_) => "E802",
+            TooManyTurnGames(_) => "E803",
+            GuildGamesFull(_) => "E804",
+            Misc(_) => "E699",
+            Hard(_) => UNEXPECTED_CODE,
+        }
+    }
+}
+
+// Code of errors that aren't the user's doing.
+pub const UNEXPECTED_CODE: &str = "E900";
+
+// Framework error handler.
+// Command errors are answered with an ephemeral embed in the user's language,
+// unexpected ones are logged with what was going on.
+// Everything else is left to poise.
+pub fn on_error(error: poise::FrameworkError<'_, Arc<CtxData>, crate::Error>) -> poise::BoxFuture<'_, ()> {
+    Box::pin(async move {
+        let (ctx, error) = match error {
+            poise::FrameworkError::Command { ctx, error } => (ctx, error),
+            // Permission checks deny with a CmdError too, see perms
+            poise::FrameworkError::CommandCheckFailed { ctx, error: Some(error) } => (ctx, error),
+            error => {
+                if let Err(e) = poise::builtins::on_error(error).await {
+                    tracing::warn!(error = %e, "failed to report an error");
+                }
+                return;
+            }
+        };
+        let lang = super::lang(ctx).await;
+        let (message, hint, code) = match error.downcast_ref::<CmdError>() {
+            Some(e @ CmdError::Hard(inner)) => {
+                log_unexpected(ctx, e.code(), inner);
+                (e.message(lang), e.hint(lang), e.code())
+            }
+            Some(e) => (e.message(lang), e.hint(lang), e.code()),
+            None => {
+                log_unexpected(ctx, UNEXPECTED_CODE, &error);
+                (tr!(lang, "err.unexpected"), Some(tr!(lang, "hint.report", code = UNEXPECTED_CODE)), UNEXPECTED_CODE)
             }
+        };
+        crate::metrics::command_finished(ctx, Some(code)).await;
+        let res = ctx.send(|m| {
+            m.embed(|e| {
+                let mut description = message;
+                if let Some(hint) = hint {
+                    description.push_str("\n\n");
+                    description.push_str(&hint);
+                }
+                e.title(tr!(lang, "err.title"))
+                    .description(description)
+                    .footer(|f| f.text(tr!(lang, "err.code", code = code)))
+                    .color(ctx.data().config.embed_color)
+            })
+            .ephemeral(true)
+        }).await;
+        if let Err(e) = res {
+            tracing::warn!(error = %e, "failed to report an error");
         }
+    })
+}
+
+fn log_unexpected(ctx: Context<'_>, code: &str, error: &crate::Error) {
+    tracing::error!(
+        code,
+        command = %ctx.command().qualified_name,
+        user = %ctx.author().id,
+        user_tag = %ctx.author().tag(),
+        guild = ?ctx.guild_id().map(|g| g.0),
+        channel = %ctx.channel_id(),
+        error = ?error,
+        "unexpected command error",
+    );
+}
+
+impl fmt::Display for CmdError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.localize(Lang::En))
     }
 }
 
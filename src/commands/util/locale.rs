@@ -0,0 +1,7 @@
+use crate::Context;
+use crate::i18n::Lang;
+
+// Language to answer a command in, see CtxData::lang_for.
+pub async fn lang(ctx: Context<'_>) -> Lang {
+    ctx.data().lang_for(ctx.author().id, ctx.guild_id()).await
+}
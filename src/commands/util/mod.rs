@@ -1,10 +1,10 @@
 pub mod errors;
 pub mod queries;
 pub mod ctxmanager;
+pub mod locale;
+pub mod perms;
+pub use locale::lang;
 pub use errors::{
     CmdError,
     CmdResult
-};
-pub use queries::{
-    ensure_word,
 };
\ No newline at end of file
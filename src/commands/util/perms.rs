@@ -0,0 +1,99 @@
+// Who may use which commands.
+//
+// Commands above the player tier say so with `check = "perms::admin"` or `check = "perms::owner"`,
+// a denial then reaches on_error as a CmdError like any other. So does going over the rate limit.
+use super::CmdError;
+use crate::{Context, Error};
+use poise::serenity_prelude as serenity;
+use serenity::{Permissions, RoleId};
+use std::collections::BTreeSet;
+use std::time::Instant;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Tier {
+    Player, // everyone
+    Admin, // can manage the server, or has one of its admin roles
+    Owner, // runs the bot, see FrameworkOptions::owners
+}
+
+// The highest tier the author of the command is in, where it was used.
+pub async fn tier(ctx: Context<'_>) -> Tier {
+    if ctx.framework().options().owners.contains(&ctx.author().id) {
+        return Tier::Owner;
+    }
+    let member = match (ctx.guild_id(), ctx.author_member().await) {
+        (Some(_), Some(member)) => member,
+        _ => return Tier::Player,
+    };
+    // Interactions come with the permissions, text commands need the cache
+    let permissions = member.permissions.or_else(|| member.permissions(ctx.discord()).ok());
+    ctx.data()
+        .guilds()
+        .with_read(ctx.guild_id(), |g| guild_tier(permissions, &member.roles, g.map(|g| &g.admin_roles)))
+        .await
+}
+
+fn guild_tier(permissions: Option<Permissions>, roles: &[RoleId], admin_roles: Option<&BTreeSet<RoleId>>) -> Tier {
+    let manager = permissions.is_some_and(|p| p.administrator() || p.manage_guild());
+    let admin_role = admin_roles.is_some_and(|admin| roles.iter().any(|r| admin.contains(r)));
+    if manager || admin_role {
+        Tier::Admin
+    } else {
+        Tier::Player
+    }
+}
+
+async fn require(ctx: Context<'_>, needed: Tier) -> Result<bool, Error> {
+    let tier = tier(ctx).await;
+    if tier < needed {
+        tracing::info!(
+            command = %ctx.command().qualified_name,
+            user = %ctx.author().id,
+            ?tier,
+            ?needed,
+            "command denied",
+        );
+        return Err(CmdError::Forbidden(needed).into());
+    }
+    Ok(true)
+}
+
+// Framework-wide command check, run before every command:
+// keeps anyone from using more commands per minute than configured.
+pub fn rate_limit(ctx: Context<'_>) -> poise::BoxFuture<'_, Result<bool, Error>> {
+    Box::pin(async move {
+        if let Err(e) = ctx.data().limits().command(ctx.author().id, Instant::now()) {
+            tracing::info!(command = %ctx.command().qualified_name, user = %ctx.author().id, "rate limited");
+            return Err(e.into());
+        }
+        Ok(true)
+    })
+}
+
+// Command check: server admins and bot owners only.
+pub fn admin(ctx: Context<'_>) -> poise::BoxFuture<'_, Result<bool, Error>> {
+    Box::pin(require(ctx, Tier::Admin))
+}
+
+// Command check: bot owners only.
+pub fn owner(ctx: Context<'_>) -> poise::BoxFuture<'_, Result<bool, Error>> {
+    Box::pin(require(ctx, Tier::Owner))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_guild_tier() {
+        let admin_roles: BTreeSet<RoleId> = [RoleId(7)].into_iter().collect();
+        let player = Permissions::SEND_MESSAGES;
+        assert_eq!(guild_tier(Some(player), &[RoleId(1)], Some(&admin_roles)), Tier::Player);
+        assert_eq!(guild_tier(Some(player), &[RoleId(1), RoleId(7)], Some(&admin_roles)), Tier::Admin);
+        assert_eq!(guild_tier(Some(Permissions::MANAGE_GUILD), &[], None), Tier::Admin);
+        assert_eq!(guild_tier(Some(Permissions::ADMINISTRATOR), &[], None), Tier::Admin);
+        // Unknown permissions grant nothing
+        assert_eq!(guild_tier(None, &[RoleId(7)], None), Tier::Player);
+        assert!(Tier::Owner > Tier::Admin && Tier::Admin > Tier::Player);
+    }
+}
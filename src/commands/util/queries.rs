@@ -3,19 +3,19 @@
  * Designed to wrap around other functions and emit CmdErrors on failure.
  */
 use super::errors::{CmdError, CmdResult};
-use crate::constants;
+use crate::config;
 use crate::dict;
-use crate::UserData;
-use poise::serenity_prelude as serenity;
-use crate::game;
+use crate::game::multiplayer::Handicap;
+use dict::overlay::WordOverlay;
+use poise::serenity_prelude::{self as serenity, UserId};
 
 /**
  * Check if a given word length is acceptable.
  * Only returns anything meaningful – that is, an error – if not.
  */
-pub fn test_length(l: usize) -> CmdResult<()> {
-    if l > constants::MAX_WORDSIZE || l < constants::MIN_WORDSIZE {
-        return Err(CmdError::BadWordLength(l));
+pub fn test_length(lengths: &config::Words, l: usize) -> CmdResult<()> {
+    if !lengths.allows(l) {
+        return Err(CmdError::BadWordLength { length: l, min: lengths.min_length, max: lengths.max_length });
     }
     Ok(())
 }
@@ -23,39 +23,146 @@ pub fn test_length(l: usize) -> CmdResult<()> {
 /**
  * Ensure a word is in the dictionary, return it (lowercase) if so.
  * Also handles random word queries!
+ * If given, the guild's overlay is applied on top of the dictionary.
  */
-pub fn ensure_word(d: &dict::Dictionary, s: &str) -> CmdResult<String> {
+pub fn ensure_word(
+    lengths: &config::Words, d: &dict::Dictionary, overlay: Option<&WordOverlay>, s: &str
+) -> CmdResult<String> {
     if let Ok(len) = s.parse::<usize>() {
         // Get a random word instead.
-        test_length(len)?;
-        return d.random_with_len(len)
-            .ok_or(CmdError::BadWordLength(s.len()))
+        test_length(lengths, len)?;
+        let word = match overlay {
+            Some(o) => o.random_with_len(d, len),
+            None => d.random_with_len(len).map(|s| s.to_string()),
+        };
+        return word
+            .ok_or(CmdError::BadWordLength { length: len, min: lengths.min_length, max: lengths.max_length })
             .map(|s| s.to_lowercase());
     }
-    test_length(s.len())?;
-    if !d.contains(s) {
+    test_length(lengths, s.len())?;
+    if !overlay.map_or_else(|| d.contains(s), |o| o.contains(d, s)) {
         return Err(CmdError::WordNotFound(s.into()));
     }
     Ok(s.to_lowercase())
 }
 
 /**
- * Extract user data of own user and ID of game owner from get_mut, assuming the user is in a game.
+ * Put a handicap on one side of a challenge, the challenged one unless `target` says otherwise.
+ * Returns handicaps by side, the challenger being side 0.
  */
-pub fn unwrap_timedgame_id(userdata: Option<&mut UserData>) -> CmdResult<(&mut UserData, game::GameId)> {
-    userdata.and_then(|udata| {
-        udata.player.timed_game.map(|gid| (udata, gid))
-    }).ok_or(CmdError::NoGame)
+pub fn handicaps(
+    limits: &config::Handicaps, own_id: UserId, enemy_id: UserId, target: Option<UserId>, handicap: Handicap
+) -> CmdResult<[Handicap; 2]> {
+    let bad = CmdError::BadHandicap { guesses: limits.max_extra_guesses, seconds: limits.max_time_bonus };
+    if handicap.extra_guesses > limits.max_extra_guesses || handicap.time_bonus > limits.max_time_bonus {
+        return Err(bad);
+    }
+    let mut out = [Handicap::default(); 2];
+    match target.unwrap_or(enemy_id) {
+        id if id == own_id => out[0] = handicap,
+        id if id == enemy_id => out[1] = handicap,
+        _ => return Err(bad),
+    }
+    Ok(out)
+}
+
+/**
+ * Split a list of words separated by spaces or commas.
+ * Every word has to be lowercase ASCII letters within length bounds.
+ */
+pub fn parse_word_list(lengths: &config::Words, s: &str) -> CmdResult<Vec<String>> {
+    let words: Vec<String> = s
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|w| !w.is_empty())
+        .map(|w| w.to_lowercase())
+        .collect();
+    if words.is_empty() {
+        return Err(CmdError::InvalidWord(s.into()));
+    }
+    for w in &words {
+        if !w.bytes().all(|c| c.is_ascii_lowercase()) {
+            return Err(CmdError::InvalidWord(w.clone()));
+        }
+        test_length(lengths, w.len())?;
+    }
+    Ok(words)
 }
 
 /**
- * Extract user data of own user and ID of game owner from get_mut, assuming the user is in a game.
+ * Cut a message down to what Discord accepts, marking the cut.
  */
-pub fn unwrap_turngame_id(userdata: Option<&mut UserData>, userid: serenity::UserId) -> CmdResult<(&mut UserData, game::GameId)> {
-    userdata.and_then(|udata| {
-        udata.player.turn_games
-            .get(&userid)
-            .map(|g| *g) // something about "move out of udata occurs here"
-            .map(|g| (udata, g))
-    }).ok_or(CmdError::NoGame)
-}
\ No newline at end of file
+pub fn truncate_message(mut s: String) -> String {
+    const LIMIT: usize = 1990;
+    if s.len() > LIMIT {
+        let mut end = LIMIT;
+        while !s.is_char_boundary(end) {
+            end -= 1;
+        }
+        s.truncate(end);
+        s.push('\u{2026}');
+    }
+    s
+}
+
+/**
+ * Joins `lines` into as few texts of at most `limit` bytes as possible,
+ * for embed fields. Lines too long by themselves are cut short.
+ */
+pub fn split_lines(lines: &[String], limit: usize) -> Vec<String> {
+    let mut out: Vec<String> = Vec::new();
+    for line in lines {
+        let mut line = line.clone();
+        if line.len() > limit {
+            let mut end = limit - '\u{2026}'.len_utf8();
+            while !line.is_char_boundary(end) {
+                end -= 1;
+            }
+            line.truncate(end);
+            line.push('\u{2026}');
+        }
+        match out.last_mut() {
+            Some(last) if last.len() + 1 + line.len() <= limit => {
+                last.push('\n');
+                last.push_str(&line);
+            }
+            _ => out.push(line),
+        }
+    }
+    out
+}
+
+/**
+ * Discord markup showing a point in time in each reader's own time zone.
+ */
+pub fn timestamp(time: std::time::SystemTime) -> String {
+    format!("<t:{}:f>", crate::data::audit::unix_secs(time))
+}
+
+/**
+ * Parse a date given as YYYY-MM-DD, or with a time as in RFC 3339, into Unix time in seconds.
+ * With `end_of_day`, a plain date stands for the end of that day instead of its start.
+ */
+pub fn parse_date(s: &str, end_of_day: bool) -> CmdResult<u64> {
+    let s = s.trim();
+    let (full, day) = match s.contains('T') {
+        true => (s.to_string(), 0),
+        false => (format!("{}T00:00:00Z", s), if end_of_day {86400} else {0}),
+    };
+    let time = serenity::Timestamp::parse(&full).map_err(|_| CmdError::BadDate(s.to_string()))?;
+    u64::try_from(time.unix_timestamp())
+        .map(|t| t + day)
+        .map_err(|_| CmdError::BadDate(s.to_string()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_split_lines() {
+        let lines: Vec<String> = ["aaaa", "bbb", "cc", "dddddddddddd"].map(String::from).to_vec();
+        let fields = split_lines(&lines, 10);
+        assert_eq!(fields, ["aaaa\nbbb", "cc", "ddddddd\u{2026}"]);
+        assert!(fields.iter().all(|f| f.len() <= 10));
+    }
+}
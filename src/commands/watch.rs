@@ -0,0 +1,149 @@
+use crate::constants;
+use crate::game::*;
+use crate::{Context, Error};
+use crate::i18n::{self, tr, Lang};
+use multiplayer::{GameProgress, Watcher};
+use poise::serenity_prelude as serenity;
+use serenity::UserId;
+use std::collections::HashMap;
+
+/// Spectate someone's Worduel
+///
+/// You get a live view of the game without the letters,
+/// they're revealed once it's over.
+/// If the player has several turn-based games, name the opponent too.
+#[poise::command(slash_command, category = "Worduel", rename = "wd_watch", ephemeral)]
+pub async fn watch(
+    ctx: Context<'_>,
+    #[description = "Watched player"] user: serenity::User,
+    #[description = "Their turn-based opponent"] opponent: Option<serenity::User>,
+    #[description = "Player you expect to win"] predict: Option<serenity::User>,
+) -> Result<(), Error> {
+    let own_id = ctx.author().id;
+    let lang = super::util::lang(ctx).await;
+    let (game_id, game_handle) = ctx.data().find_game_of(user.id, opponent.map(|u| u.id)).await?;
+
+    let (players, stateline, views) = {
+        let mut gamedata = game_handle.lock().await;
+        gamedata.watch(own_id, predict.as_ref().map(|u| u.id))?;
+        (
+            [0, 1].map(|i| gamedata.get_user_id(i)),
+            gamedata.render_stateline(lang, false),
+            gamedata.render_views_spectator(constants::WORDUEL_VIEWSEP),
+        )
+    };
+
+    let view = ctx.channel_id().send_message(&ctx.discord().http, |m| {
+        m.content(tr!(lang, "watch.spectating",
+            a = i18n::user(players[0]), b = i18n::user(players[1]), user = i18n::user(own_id)))
+        .embed(|e| spectator_embed(e, ctx, lang, game_id, &stateline, &views))
+    })
+    .await?;
+    // The game might be gone by now, then there's nothing left to update.
+    game_handle.lock().await.set_watch_view(own_id, (view.channel_id, view.id));
+
+    ctx.say(match predict {
+        Some(p) => tr!(lang, "watch.watching_predicted", game = game_id, name = p.name),
+        None => tr!(lang, "watch.watching", game = game_id),
+    }).await?;
+    Ok(())
+}
+
+fn spectator_embed<'a>(
+    e: &'a mut serenity::CreateEmbed, ctx: Context<'_>, lang: Lang, game_id: GameId, stateline: &str, views: &str
+) -> &'a mut serenity::CreateEmbed {
+    e.title(tr!(lang, "watch.title", game = game_id))
+        .field(tr!(lang, "embed.game_state"), stateline, true)
+        .color(ctx.data().config.embed_color)
+        .description(views)
+}
+
+// What spectators get to see after a move, copied out of the game
+// so it can be sent once nothing is locked.
+pub struct Broadcast {
+    lang: Lang,
+    game_id: GameId,
+    watchers: Vec<Watcher>,
+    stateline: String,
+    views: String,
+    winner: Option<Option<(usize, UserId)>>, // set once the game has ended, None inside for a draw
+}
+
+impl Broadcast {
+    // Snapshot after a move, with the result if it ended the game.
+    pub fn of(lang: Lang, game_id: GameId, gamedata: &GameMP) -> Broadcast {
+        let winner = match gamedata.get_progress() {
+            GameProgress::Over(res) => Some(res.map(|i| (i, gamedata.get_user_id(i)))),
+            _ => None,
+        };
+        Broadcast {
+            lang,
+            game_id,
+            watchers: gamedata.get_watchers().to_vec(),
+            stateline: gamedata.render_stateline(lang, false),
+            views: gamedata.render_views_spectator(constants::WORDUEL_VIEWSEP),
+            winner,
+        }
+    }
+
+    // Snapshot of a game given up on by player `loser`.
+    pub fn forfeit(lang: Lang, game_id: GameId, gamedata: &GameMP, loser: usize) -> Broadcast {
+        Broadcast {
+            lang,
+            game_id,
+            watchers: gamedata.get_watchers().to_vec(),
+            stateline: tr!(lang, "state.forfeited"),
+            views: gamedata.render_views(constants::WORDUEL_VIEWSEP),
+            winner: Some(Some((1 - loser, gamedata.get_user_id(1 - loser)))),
+        }
+    }
+
+    // Update live views, and tell spectators how it went once the game is over.
+    // Failures only concern spectators, so they're logged rather than returned.
+    pub async fn send(self, ctx: Context<'_>) {
+        let http = &ctx.discord().http;
+        for (channel, message) in self.watchers.iter().filter_map(|w| w.view) {
+            let res = channel.edit_message(http, message, |m| {
+                m.embed(|e| spectator_embed(e, ctx, self.lang, self.game_id, &self.stateline, &self.views))
+            }).await;
+            if let Err(e) = res {
+                tracing::warn!(game = self.game_id, error = %e, "failed to update a spectator view");
+            }
+        }
+
+        let winner = match self.winner {
+            Some(w) => w,
+            None => return,
+        };
+        // One message per channel spectators watch from
+        let mut channels: HashMap<_, Vec<&Watcher>> = HashMap::new();
+        for w in &self.watchers {
+            if let Some((channel, _)) = w.view {
+                channels.entry(channel).or_default().push(w);
+            }
+        }
+        for (channel, watchers) in channels {
+            let mut content = serenity::MessageBuilder::new();
+            content.push(match winner {
+                Some((_, id)) => tr!(self.lang, "watch.over_victor", game = self.game_id, user = i18n::user(id)),
+                None => tr!(self.lang, "watch.over_draw", game = self.game_id),
+            });
+            let right: Vec<_> = watchers.iter()
+                .filter(|w| w.prediction.is_some() && w.prediction == winner.map(|(i, _)| i))
+                .collect();
+            if !right.is_empty() {
+                content.push("\n").push(tr!(self.lang, "watch.predicted"));
+                for w in right {
+                    content.push(" ").user(w.id);
+                }
+            }
+            content.push("\n");
+            for w in &watchers {
+                content.user(w.id).push(" ");
+            }
+            if let Err(e) = channel.send_message(http, |m| m.content(content.build())).await {
+                tracing::warn!(game = self.game_id, error = %e, "failed to notify spectators");
+            }
+        }
+    }
+}
@@ -0,0 +1,155 @@
+use crate::{Context, Error};
+use crate::i18n::tr;
+use super::util::*;
+use poise::serenity_prelude as serenity;
+
+/// Manage this server's word lists
+///
+/// Allowed words and enabled packs can be played and drawn as random words.
+/// Denied words can't, even if they're in the dictionary.
+#[poise::command(
+    slash_command,
+    category = "Admin",
+    rename = "wd_words",
+    guild_only,
+    subcommands("allow", "deny", "unlist", "pack", "pack_drop", "pack_toggle", "list")
+)]
+pub async fn words(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Allow words missing from the dictionary
+#[poise::command(slash_command, guild_only, check = "perms::admin", ephemeral)]
+pub async fn allow(
+    ctx: Context<'_>,
+    #[description = "Words, separated by spaces or commas"] words: String,
+) -> Result<(), Error> {
+    let lang = lang(ctx).await;
+    let words = queries::parse_word_list(&ctx.data().config.words, &words)?;
+    let count = words.len();
+    ctx.data().guilds().with_write(ctx.guild_id().unwrap(), |g| {
+        for w in words {
+            g.words.deny.remove(&w);
+            g.words.allow.insert(w);
+        }
+    }).await?;
+    ctx.say(tr!(lang, "words.allowed", n = count)).await?;
+    Ok(())
+}
+
+/// Deny words, also removing them from random picks
+#[poise::command(slash_command, guild_only, check = "perms::admin", ephemeral)]
+pub async fn deny(
+    ctx: Context<'_>,
+    #[description = "Words, separated by spaces or commas"] words: String,
+) -> Result<(), Error> {
+    let lang = lang(ctx).await;
+    let words = queries::parse_word_list(&ctx.data().config.words, &words)?;
+    let count = words.len();
+    ctx.data().guilds().with_write(ctx.guild_id().unwrap(), |g| {
+        for w in words {
+            g.words.allow.remove(&w);
+            g.words.deny.insert(w);
+        }
+    }).await?;
+    ctx.say(tr!(lang, "words.denied", n = count)).await?;
+    Ok(())
+}
+
+/// Remove words from both the allow-list and the deny-list
+#[poise::command(slash_command, guild_only, check = "perms::admin", ephemeral)]
+pub async fn unlist(
+    ctx: Context<'_>,
+    #[description = "Words, separated by spaces or commas"] words: String,
+) -> Result<(), Error> {
+    let lang = lang(ctx).await;
+    let words = queries::parse_word_list(&ctx.data().config.words, &words)?;
+    let removed = ctx.data().guilds().with_write(ctx.guild_id().unwrap(), |g| {
+        words.iter()
+            .filter(|w| g.words.allow.remove(*w) | g.words.deny.remove(*w))
+            .count()
+    }).await?;
+    ctx.say(tr!(lang, "words.unlisted", n = removed)).await?;
+    Ok(())
+}
+
+/// Add words to a themed pack, creating it if needed
+///
+/// New packs start disabled.
+#[poise::command(slash_command, guild_only, check = "perms::admin", ephemeral)]
+pub async fn pack(
+    ctx: Context<'_>,
+    #[description = "Pack name"] name: String,
+    #[description = "Words, separated by spaces or commas"] words: String,
+) -> Result<(), Error> {
+    let lang = lang(ctx).await;
+    let words = queries::parse_word_list(&ctx.data().config.words, &words)?;
+    let size = ctx.data().guilds().with_write(ctx.guild_id().unwrap(), |g| {
+        let pack = g.words.packs.entry(name.clone()).or_default();
+        pack.words.extend(words);
+        pack.words.len()
+    }).await?;
+    ctx.say(tr!(lang, "words.pack_size", name = name, n = size)).await?;
+    Ok(())
+}
+
+/// Delete a themed pack
+#[poise::command(slash_command, guild_only, check = "perms::admin", ephemeral)]
+pub async fn pack_drop(
+    ctx: Context<'_>,
+    #[description = "Pack name"] name: String,
+) -> Result<(), Error> {
+    ctx.data().guilds().with_write(ctx.guild_id().unwrap(), |g| {
+        g.words.packs.remove(&name)
+    }).await?.ok_or_else(|| CmdError::NoPack(name.clone()))?;
+    ctx.say(tr!(lang(ctx).await, "words.pack_deleted", name = name)).await?;
+    Ok(())
+}
+
+/// Enable or disable a themed pack
+#[poise::command(slash_command, guild_only, check = "perms::admin", ephemeral)]
+pub async fn pack_toggle(
+    ctx: Context<'_>,
+    #[description = "Pack name"] name: String,
+    #[description = "Should the pack be used?"] enabled: bool,
+) -> Result<(), Error> {
+    ctx.data().guilds().with_write(ctx.guild_id().unwrap(), |g| {
+        g.words.packs.get_mut(&name).map(|p| p.enabled = enabled)
+    }).await?.ok_or_else(|| CmdError::NoPack(name.clone()))?;
+    let key = if enabled {"words.pack_enabled"} else {"words.pack_disabled"};
+    ctx.say(tr!(lang(ctx).await, key, name = name)).await?;
+    Ok(())
+}
+
+/// Show this server's word lists
+#[poise::command(slash_command, guild_only, check = "perms::admin", ephemeral)]
+pub async fn list(ctx: Context<'_>) -> Result<(), Error> {
+    let lang = lang(ctx).await;
+    let content = ctx.data().guilds().with_read(ctx.guild_id(), |g| {
+        let words = g.map(|g| g.words.clone()).unwrap_or_default();
+        let join = |set: &std::collections::BTreeSet<String>| {
+            if set.is_empty() {
+                tr!(lang, "words.list_none")
+            } else {
+                set.iter().cloned().collect::<Vec<_>>().join(", ")
+            }
+        };
+        let mut out = serenity::MessageBuilder::new();
+        out.push_bold(tr!(lang, "words.list_allowed")).push(" ").push_line_safe(join(&words.allow));
+        out.push_bold(tr!(lang, "words.list_denied")).push(" ").push_line_safe(join(&words.deny));
+        out.push_bold_line(tr!(lang, "words.list_packs"));
+        for (name, pack) in &words.packs {
+            let key = if pack.enabled {"words.pack_entry_enabled"} else {"words.pack_entry_disabled"};
+            out.push("- ")
+                .push_safe(name)
+                .push(format!(" ({})", tr!(lang, key, n = pack.words.len())))
+                .push("\n");
+        }
+        if words.packs.is_empty() {
+            out.push(tr!(lang, "words.list_none"));
+        }
+        out.build()
+    }).await;
+    ctx.say(queries::truncate_message(content)).await?;
+    Ok(())
+}
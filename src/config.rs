@@ -0,0 +1,311 @@
+// Runtime settings, read from a TOML file at startup.
+//
+// Every key is optional, missing ones keep the defaults below,
+// so an empty or missing file gives the bot as it always was.
+
+use crate::data::persist;
+use serde::Deserialize;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use std::{error, fmt, fs, io};
+
+pub const CONFIG_VARNAME: &str = "WORDCLASH_CONFIG";
+pub const CONFIG_FILENAME: &str = "config.toml";
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    // Prefix of text commands
+    pub prefix: String,
+    // RGB colour of the bot's embeds
+    pub embed_color: (u8, u8, u8),
+    // User IDs of bot owners, besides the owner of the application
+    pub owners: Vec<u64>,
+    pub words: Words,
+    pub handicaps: Handicaps,
+    pub expiry: Expiry,
+    pub matchmaking: Matchmaking,
+    pub limits: Limits,
+    pub dashboard: Dashboard,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Words {
+    // Bounds on the length of words played, inclusive
+    pub min_length: usize,
+    pub max_length: usize,
+    // Of random words for challenges without one, unless a guild says otherwise
+    pub default_length: usize,
+}
+
+// Largest handicaps one side of a challenge can get.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Handicaps {
+    pub max_extra_guesses: usize,
+    pub max_time_bonus: u64, // in seconds
+}
+
+// Durations are given in seconds.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Expiry {
+    // How long does each invite type take to expire?
+    #[serde(with = "seconds")]
+    pub timed_invite: Duration,
+    #[serde(with = "seconds")]
+    pub turn_invite: Duration,
+    // How long do timed games take to be interrupted early?
+    // Turn-based games do not expire with time once accepted
+    #[serde(with = "seconds")]
+    pub timed_game: Duration,
+    // How often does the cleanup task run?
+    // It locks one game or user at a time, but still walks every user and game.
+    // Too rare will make garbage stick around longer, too frequent will waste time.
+    // Effectively limits the granularity of the three above.
+    #[serde(with = "seconds")]
+    pub cleanup_interval: Duration,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Matchmaking {
+    // How far apart players' scores may be to get paired,
+    // widened by `window_widening` per minute spent in the queue.
+    pub window_base: u64,
+    pub window_widening: u64,
+    // How long does the queue wait for an opponent before giving up?
+    #[serde(with = "seconds")]
+    pub queue_expiry: Duration,
+    // How often are queued players paired up?
+    #[serde(with = "seconds")]
+    pub interval: Duration,
+}
+
+// Caps keeping a single user or guild from flooding the bot.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Limits {
+    // Invites a user may have waiting at once, open challenges included
+    pub outgoing_invites: usize,
+    // Turn-based games a user may be in at once, unaccepted ones included
+    pub turn_games: usize,
+    pub commands_per_minute: usize,
+    // Games a guild may have going on at once, unaccepted ones included
+    pub guild_games: usize,
+}
+
+// The optional web dashboard, see dashboard.rs.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Dashboard {
+    // Where to serve it, e.g. "127.0.0.1:8080". No dashboard without one
+    pub address: Option<SocketAddr>,
+    // It has no authentication, so anything but a loopback address has to be allowed explicitly
+    pub allow_remote: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            prefix: "!".into(),
+            embed_color: (255, 204, 11),
+            owners: Vec::new(),
+            words: Words::default(),
+            handicaps: Handicaps::default(),
+            expiry: Expiry::default(),
+            matchmaking: Matchmaking::default(),
+            limits: Limits::default(),
+            dashboard: Dashboard::default(),
+        }
+    }
+}
+
+impl Default for Words {
+    fn default() -> Self {
+        Words { min_length: 4, max_length: 8, default_length: 5 }
+    }
+}
+
+impl Default for Handicaps {
+    fn default() -> Self {
+        Handicaps { max_extra_guesses: 3, max_time_bonus: 120 }
+    }
+}
+
+impl Default for Expiry {
+    fn default() -> Self {
+        Expiry {
+            timed_invite: Duration::from_secs(300),
+            turn_invite: Duration::from_secs(900),
+            timed_game: Duration::from_secs(600),
+            cleanup_interval: Duration::from_secs(30),
+        }
+    }
+}
+
+impl Default for Matchmaking {
+    fn default() -> Self {
+        Matchmaking {
+            window_base: 20,
+            window_widening: 60,
+            queue_expiry: Duration::from_secs(900),
+            interval: Duration::from_secs(10),
+        }
+    }
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Limits {
+            outgoing_invites: 5,
+            turn_games: 10,
+            commands_per_minute: 30,
+            guild_games: 200,
+        }
+    }
+}
+
+impl Words {
+    pub fn allows(&self, length: usize) -> bool {
+        (self.min_length..=self.max_length).contains(&length)
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    NoPath(io::Error), // no path given and executable location unknown
+    Io(PathBuf, io::Error),
+    Parse(PathBuf, toml::de::Error),
+    Invalid(PathBuf, &'static str),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use ConfigError::*;
+        match self {
+            NoPath(e) => write!(f, "Configuration path unknown: {}", e),
+            Io(p, e) => write!(f, "Failed to read configuration from {}: {}", p.display(), e),
+            Parse(p, e) => write!(f, "Malformed configuration in {}: {}", p.display(), e),
+            Invalid(p, why) => write!(f, "Invalid configuration in {}: {}", p.display(), why),
+        }
+    }
+}
+
+impl error::Error for ConfigError {}
+
+impl Config {
+    // Load from the path in WORDCLASH_CONFIG, or config.toml next to the executable.
+    // A missing file is not an error, the defaults are used then.
+    pub fn load() -> Result<Config, ConfigError> {
+        let path = persist::data_path(CONFIG_VARNAME, CONFIG_FILENAME).map_err(ConfigError::NoPath)?;
+        match fs::read_to_string(&path) {
+            Ok(text) => Config::parse(&path, &text),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                tracing::info!(path = %path.display(), "no configuration file, using defaults");
+                Ok(Config::default())
+            }
+            Err(e) => Err(ConfigError::Io(path, e)),
+        }
+    }
+
+    // `path` is only used in errors.
+    pub fn parse(path: &Path, text: &str) -> Result<Config, ConfigError> {
+        let config: Config = toml::from_str(text).map_err(|e| ConfigError::Parse(path.into(), e))?;
+        config.validate().map_err(|why| ConfigError::Invalid(path.into(), why))?;
+        Ok(config)
+    }
+
+    fn validate(&self) -> Result<(), &'static str> {
+        if self.prefix.is_empty() || self.prefix.contains(char::is_whitespace) {
+            return Err("prefix must be non-empty and without spaces");
+        }
+        if self.words.min_length == 0 || self.words.min_length > self.words.max_length {
+            return Err("words.min_length must be at least 1 and at most words.max_length");
+        }
+        if !self.words.allows(self.words.default_length) {
+            return Err("words.default_length must be between words.min_length and words.max_length");
+        }
+        let limits = [
+            self.limits.outgoing_invites,
+            self.limits.turn_games,
+            self.limits.commands_per_minute,
+            self.limits.guild_games,
+        ];
+        if limits.contains(&0) {
+            return Err("limits must be at least 1");
+        }
+        let durations = [
+            self.expiry.timed_invite,
+            self.expiry.turn_invite,
+            self.expiry.timed_game,
+            self.expiry.cleanup_interval,
+            self.matchmaking.queue_expiry,
+            self.matchmaking.interval,
+        ];
+        if durations.contains(&Duration::ZERO) {
+            return Err("expiry times and intervals must be at least a second");
+        }
+        if self.dashboard.address.is_some_and(|a| !a.ip().is_loopback()) && !self.dashboard.allow_remote {
+            return Err("dashboard.address must be a loopback address unless dashboard.allow_remote is set");
+        }
+        Ok(())
+    }
+}
+
+// Read a Duration given in whole seconds.
+mod seconds {
+    use serde::{Deserialize, Deserializer};
+    use std::time::Duration;
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Duration, D::Error> {
+        u64::deserialize(d).map(Duration::from_secs)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let path = Path::new("config.toml");
+        let config = Config::parse(path, "").unwrap();
+        assert_eq!(config.prefix, "!");
+        assert_eq!(config.expiry.turn_invite, Duration::from_secs(900));
+        // The example lists the defaults
+        let example = Config::parse(path, include_str!("../config.example.toml")).unwrap();
+        assert_eq!(format!("{:?}", example), format!("{:?}", Config::default()));
+
+        let text = "
+            prefix = \"?\"
+            embed_color = [0, 128, 255]
+            [words]
+            max_length = 10
+            [expiry]
+            timed_game = 1200
+        ";
+        let config = Config::parse(path, text).unwrap();
+        assert_eq!(config.prefix, "?");
+        assert_eq!(config.embed_color, (0, 128, 255));
+        assert!(config.words.allows(4) && config.words.allows(10) && !config.words.allows(11));
+        assert_eq!(config.expiry.timed_game, Duration::from_secs(1200));
+        assert_eq!(config.expiry.timed_invite, Duration::from_secs(300));
+
+        assert!(matches!(Config::parse(path, "[words]\nmin_length = 9"), Err(ConfigError::Invalid(..))));
+        assert!(matches!(Config::parse(path, "[words]\ndefault_length = 3"), Err(ConfigError::Invalid(..))));
+        assert!(matches!(Config::parse(path, "[expiry]\ncleanup_interval = 0"), Err(ConfigError::Invalid(..))));
+        assert!(matches!(Config::parse(path, "prefix = \"\""), Err(ConfigError::Invalid(..))));
+        assert!(matches!(Config::parse(path, "[limits]\nturn_games = 0"), Err(ConfigError::Invalid(..))));
+        // The dashboard stays local unless allowed out
+        let local = Config::parse(path, "[dashboard]\naddress = \"127.0.0.1:8080\"").unwrap();
+        assert_eq!(local.dashboard.address, Some("127.0.0.1:8080".parse().unwrap()));
+        assert!(matches!(Config::parse(path, "[dashboard]\naddress = \"0.0.0.0:8080\""), Err(ConfigError::Invalid(..))));
+        assert!(Config::parse(path, "[dashboard]\naddress = \"0.0.0.0:8080\"\nallow_remote = true").is_ok());
+        // Typos don't go unnoticed
+        assert!(matches!(Config::parse(path, "[word]\nmax_length = 9"), Err(ConfigError::Parse(..))));
+        assert!(matches!(Config::parse(path, "[expiry]\ntimed_game = -1"), Err(ConfigError::Parse(..))));
+    }
+}
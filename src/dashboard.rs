@@ -0,0 +1,577 @@
+// Optional web dashboard: the leaderboard, games being played and player profiles,
+// as JSON under /api and as plain pages for a screen that isn't logged in to Discord.
+//
+// There's no authentication, so the configuration only lets it listen on loopback addresses
+// unless told otherwise. Boards never show letters, so the pages can't be used to help a player.
+// Only users the bot has scores or games of are shown, their names are looked up
+// through Discord and the latest few hundred remembered.
+
+use crate::data::export::{PlayerRow, ScoreRow, Snapshot};
+use crate::data::CtxData;
+use crate::dict::wordmatch::MatchLetter;
+use crate::game::multiplayer::{GameMP, GameProgress};
+use crate::game::{ArchivedGame, GameId, GameVariant};
+use crate::i18n::{tr, Lang};
+use hyper::service::{make_service_fn, service_fn};
+use hyper::header::{HeaderValue, CONTENT_TYPE};
+use hyper::{Body, Method, Request, Response, Server, StatusCode};
+use poise::serenity_prelude as serenity;
+use serde::Serialize;
+use serenity::{GuildId, UserId};
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::fmt::Write;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+// Leaderboard length, like /wd_top.
+const DEFAULT_COUNT: usize = 10;
+const MAX_COUNT: usize = 50;
+// Finished games listed on a profile.
+const RECENT_GAMES: usize = 10;
+// Pages reload themselves this often, in seconds.
+const REFRESH_SECS: u64 = 15;
+// Names remembered, the least recently shown are forgotten first.
+const NAMES_CAPACITY: usize = 500;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Standing {
+    pub rank: usize,
+    pub user: UserId,
+    pub score: u64,
+}
+
+// One side of a game being played.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LiveSide {
+    pub user: UserId,
+    pub score: u64,
+    pub finished: bool,
+    pub guess_limit: usize,
+    // Feedback on each guess so far: 0 letter not in the word, 1 elsewhere, 2 in place
+    pub rows: Vec<Vec<u8>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LiveGame {
+    pub id: GameId,
+    pub variant: GameVariant,
+    pub guild: Option<GuildId>,
+    pub word_length: usize,
+    pub seconds: u64, // since it started
+    pub over: bool,
+    pub winner: Option<usize>, // side, None for draws and games still going
+    pub sides: [LiveSide; 2],
+}
+
+impl LiveGame {
+    // None for games still waiting for the challenged player.
+    pub fn of(id: GameId, game: &GameMP, now: Instant) -> Option<LiveGame> {
+        let (over, winner) = match *game.get_progress() {
+            GameProgress::Waiting => return None,
+            GameProgress::Over(winner) => (true, winner),
+            _ => (false, None),
+        };
+        let side = |i| LiveSide {
+            user: game.get_user_id(i),
+            score: game.get_score()[i],
+            finished: over || game.get_end(i).is_some(),
+            guess_limit: game.get_guess_limit(i),
+            rows: game.get_matches(i).map(|m| m.iter().map(|&l| l as u8).collect()).collect(),
+        };
+        Some(LiveGame {
+            id,
+            variant: game.get_variant(),
+            guild: game.get_guild(),
+            word_length: game.get_word_length(),
+            seconds: now.saturating_duration_since(game.get_start()).as_secs(),
+            over,
+            winner,
+            sides: [side(0), side(1)],
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Outcome {
+    Win,
+    Loss,
+    Draw,
+    Unfinished,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RecentGame {
+    pub id: GameId,
+    pub variant: GameVariant,
+    pub opponent: UserId,
+    pub outcome: Outcome,
+    pub score: u64,
+    pub ended: u64, // Unix time in seconds
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Profile {
+    pub user: UserId,
+    pub rank: Option<usize>, // None if not on the leaderboard
+    pub score: u64,
+    pub record: PlayerRow, // of the games still in the archive
+    pub recent: Vec<RecentGame>, // latest first
+    pub live: Vec<GameId>,
+}
+
+impl Profile {
+    // `standing` is the user's rank and score, `games` the archived ones they played, oldest first.
+    pub fn new(user: UserId, standing: Option<(usize, u64)>, games: Vec<ArchivedGame>, live: Vec<GameId>) -> Profile {
+        let (rank, score) = match standing {
+            Some((rank, score)) => (Some(rank), score),
+            None => (None, 0),
+        };
+        let games: Vec<_> = games.into_iter().filter(|g| g.players.contains(&user)).collect();
+        let recent = games.iter().rev().take(RECENT_GAMES).map(|g| {
+            let side = if g.players[0] == user {0} else {1};
+            RecentGame {
+                id: g.id,
+                variant: g.variant,
+                opponent: g.players[1 - side],
+                outcome: match (g.finished, g.winner) {
+                    (false, _) => Outcome::Unfinished,
+                    (true, None) => Outcome::Draw,
+                    (true, Some(w)) if w == side => Outcome::Win,
+                    (true, Some(_)) => Outcome::Loss,
+                },
+                score: g.scores[side],
+                ended: g.ended,
+            }
+        }).collect();
+        let snapshot = Snapshot { scores: vec![ScoreRow { user, score }], games };
+        let record = snapshot.players().into_iter().find(|p| p.user == user)
+            .unwrap_or(PlayerRow { user, score, ..Default::default() });
+        Profile { user, rank, score, record, recent, live }
+    }
+
+    // Nothing the bot knows of, whoever the user is.
+    pub fn is_empty(&self) -> bool {
+        self.rank.is_none() && self.record.games == 0 && self.live.is_empty()
+    }
+}
+
+// What the API returns: the data, and the names of the users in it.
+#[derive(Serialize)]
+struct Reply<'a, T> {
+    data: &'a T,
+    names: &'a HashMap<UserId, String>,
+}
+
+enum Page {
+    Leaderboard(usize),
+    Games,
+    Player(UserId),
+}
+
+// Names looked up lately, at most NAMES_CAPACITY of them.
+#[derive(Default)]
+struct NameCache {
+    names: HashMap<UserId, (String, u64)>, // with when they were last used
+    clock: u64,
+}
+
+impl NameCache {
+    fn get(&mut self, user: UserId) -> Option<String> {
+        self.clock += 1;
+        let entry = self.names.get_mut(&user)?;
+        entry.1 = self.clock;
+        Some(entry.0.clone())
+    }
+
+    fn insert(&mut self, user: UserId, name: String) {
+        if self.names.len() >= NAMES_CAPACITY && !self.names.contains_key(&user) {
+            let oldest = self.names.iter().min_by_key(|(_, (_, used))| *used).map(|(u, _)| *u);
+            if let Some(oldest) = oldest {
+                self.names.remove(&oldest);
+            }
+        }
+        self.clock += 1;
+        self.names.insert(user, (name, self.clock));
+    }
+}
+
+struct Dashboard {
+    data: Arc<CtxData>,
+    cache: Arc<serenity::Cache>,
+    http: Arc<serenity::Http>,
+    names: Mutex<NameCache>, // a leaf, never held across an await
+}
+
+impl Dashboard {
+    // Only ever given users the bot has scores or games of, so lookups stay few.
+    async fn names(&self, users: Vec<UserId>) -> HashMap<UserId, String> {
+        let mut names = HashMap::new();
+        for user in users {
+            if names.contains_key(&user) {
+                continue;
+            }
+            let known = self.names.lock().unwrap().get(user);
+            let name = match known {
+                Some(name) => name,
+                None => {
+                    let name = match user.to_user((&self.cache, &*self.http)).await {
+                        Ok(u) => u.name,
+                        Err(e) => {
+                            tracing::debug!(%user, error = %e, "no name for the dashboard");
+                            user.to_string()
+                        }
+                    };
+                    self.names.lock().unwrap().insert(user, name.clone());
+                    name
+                }
+            };
+            names.insert(user, name);
+        }
+        names
+    }
+}
+
+// Serve the dashboard on `addr` for as long as the bot runs.
+pub async fn serve(addr: SocketAddr, data: Arc<CtxData>, cache: Arc<serenity::Cache>, http: Arc<serenity::Http>) {
+    let dashboard = Arc::new(Dashboard { data, cache, http, names: Mutex::default() });
+    let make_service = make_service_fn(move |_| {
+        let dashboard = dashboard.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |req| respond(req, dashboard.clone())))
+        }
+    });
+    let server = match Server::try_bind(&addr) {
+        Ok(builder) => builder.serve(make_service),
+        Err(e) => {
+            tracing::error!(%addr, error = %e, "dashboard not started");
+            return;
+        }
+    };
+    tracing::info!(%addr, "serving the dashboard");
+    if let Err(e) = server.await {
+        tracing::error!(error = %e, "dashboard stopped");
+    }
+}
+
+async fn respond(req: Request<Body>, dashboard: Arc<Dashboard>) -> Result<Response<Body>, Infallible> {
+    let query = query(req.uri().query());
+    let lang = match query.get("lang").map(String::as_str) {
+        Some("pl") => Lang::Pl,
+        _ => Lang::En,
+    };
+    let (api, path) = match req.uri().path().strip_prefix("/api") {
+        Some(path) => (true, path),
+        None => (false, req.uri().path()),
+    };
+    let page = match (req.method(), route(path, query.get("count").map(String::as_str))) {
+        (&Method::GET, Some(page)) => page,
+        _ => return Ok(status(StatusCode::NOT_FOUND)),
+    };
+
+    let data = &dashboard.data;
+    let body = match page {
+        Page::Leaderboard(count) => {
+            let top: Vec<_> = data.scores().list_top(count).await
+                .into_iter()
+                .enumerate()
+                .map(|(i, (user, score))| Standing { rank: i + 1, user, score })
+                .collect();
+            let names = dashboard.names(top.iter().map(|s| s.user).collect()).await;
+            match api {
+                true => json(&top, &names),
+                false => Ok(render_leaderboard(lang, &top, &names)),
+            }
+        }
+        Page::Games => {
+            let games = data.live_games().await;
+            let names = dashboard.names(games.iter().flat_map(|g| [g.sides[0].user, g.sides[1].user]).collect()).await;
+            match api {
+                true => json(&games, &names),
+                false => Ok(render_games(lang, &games, &names)),
+            }
+        }
+        Page::Player(user) => {
+            let profile = data.profile(user).await;
+            // Strangers aren't looked up, there'd be no end to them
+            if profile.is_empty() {
+                return Ok(status(StatusCode::NOT_FOUND));
+            }
+            let names = dashboard.names(std::iter::once(user).chain(profile.recent.iter().map(|g| g.opponent)).collect()).await;
+            match api {
+                true => json(&profile, &names),
+                false => Ok(render_profile(lang, &profile, &names)),
+            }
+        }
+    };
+    let body = match body {
+        Ok(body) => body,
+        Err(e) => {
+            tracing::error!(path = %req.uri().path(), error = %e, "dashboard reply not serialized");
+            return Ok(status(StatusCode::INTERNAL_SERVER_ERROR));
+        }
+    };
+    let content_type = if api {"application/json"} else {"text/html; charset=utf-8"};
+    let mut response = Response::new(Body::from(body));
+    response.headers_mut().insert(CONTENT_TYPE, HeaderValue::from_static(content_type));
+    Ok(response)
+}
+
+fn status(code: StatusCode) -> Response<Body> {
+    let mut response = Response::new(Body::empty());
+    *response.status_mut() = code;
+    response
+}
+
+// The page at `path`, with the /api prefix taken off.
+fn route(path: &str, count: Option<&str>) -> Option<Page> {
+    match path.trim_end_matches('/') {
+        "" | "/leaderboard" => {
+            let count = match count {
+                Some(c) => c.parse().ok().filter(|c| (1..=MAX_COUNT).contains(c))?,
+                None => DEFAULT_COUNT,
+            };
+            Some(Page::Leaderboard(count))
+        }
+        "/games" => Some(Page::Games),
+        p => p.strip_prefix("/players/")?.parse().ok().map(|id| Page::Player(UserId(id))),
+    }
+}
+
+fn query(query: Option<&str>) -> HashMap<String, String> {
+    query.unwrap_or_default()
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect()
+}
+
+fn json<T: Serialize>(data: &T, names: &HashMap<UserId, String>) -> serde_json::Result<String> {
+    serde_json::to_string(&Reply { data, names })
+}
+
+// Text safe to put in HTML.
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+const STYLE: &str = "\
+body{font-family:sans-serif;background:#222;color:#eee;margin:2em}\
+a{color:#8cf}nav a{margin-right:1em}table{border-collapse:collapse}\
+td,th{padding:.3em .8em;text-align:left}.game{display:inline-block;margin:0 2em 2em 0;vertical-align:top}\
+.board{display:inline-block;margin-right:1em}.row{display:flex}\
+.sq{width:1.2em;height:1.2em;margin:1px;background:#555}\
+.n{background:#3a3a3c}.c{background:#b59f3b}.e{background:#538d4e}";
+
+fn page(lang: Lang, title: &str, body: &str) -> String {
+    let code = match lang {
+        Lang::En => "en",
+        Lang::Pl => "pl",
+    };
+    format!(
+        "<!DOCTYPE html><html lang=\"{code}\"><head><meta charset=\"utf-8\">\
+        <meta http-equiv=\"refresh\" content=\"{REFRESH_SECS}\"><title>{title}</title><style>{STYLE}</style></head>\
+        <body><nav><a href=\"/?lang={code}\">{}</a><a href=\"/games?lang={code}\">{}</a></nav><h1>{title}</h1>{body}</body></html>",
+        tr!(lang, "dash.leaderboard"), tr!(lang, "dash.games"),
+    )
+}
+
+fn player_link(lang: Lang, user: UserId, names: &HashMap<UserId, String>) -> String {
+    let name = names.get(&user).cloned().unwrap_or_else(|| user.to_string());
+    let code = if lang == Lang::Pl {"pl"} else {"en"};
+    format!("<a href=\"/players/{}?lang={}\">{}</a>", user, code, escape(&name))
+}
+
+fn render_leaderboard(lang: Lang, top: &[Standing], names: &HashMap<UserId, String>) -> String {
+    let mut body = String::new();
+    if top.is_empty() {
+        let _ = write!(body, "<p>{}</p>", tr!(lang, "scores.empty"));
+    } else {
+        let _ = write!(body, "<table><tr><th>{}</th><th>{}</th><th>{}</th></tr>",
+            tr!(lang, "dash.rank"), tr!(lang, "dash.player"), tr!(lang, "dash.score"));
+        for s in top {
+            let _ = write!(body, "<tr><td>{}</td><td>{}</td><td>{}</td></tr>", s.rank, player_link(lang, s.user, names), s.score);
+        }
+        body.push_str("</table>");
+    }
+    page(lang, &tr!(lang, "dash.leaderboard"), &body)
+}
+
+fn render_board(side: &LiveSide, word_length: usize) -> String {
+    let mut out = String::from("<div class=\"board\">");
+    for i in 0..side.guess_limit.max(side.rows.len()) {
+        out.push_str("<div class=\"row\">");
+        for j in 0..word_length {
+            let class = match side.rows.get(i).and_then(|r| r.get(j)) {
+                Some(&m) if m == MatchLetter::Exact as u8 => "sq e",
+                Some(&m) if m == MatchLetter::Close as u8 => "sq c",
+                Some(_) => "sq n",
+                None => "sq",
+            };
+            let _ = write!(out, "<div class=\"{}\"></div>", class);
+        }
+        out.push_str("</div>");
+    }
+    out.push_str("</div>");
+    out
+}
+
+fn render_games(lang: Lang, games: &[LiveGame], names: &HashMap<UserId, String>) -> String {
+    let mut body = String::new();
+    if games.is_empty() {
+        let _ = write!(body, "<p>{}</p>", tr!(lang, "dash.no_games"));
+    }
+    for g in games {
+        let variant = match g.variant {
+            GameVariant::Timed => tr!(lang, "variant.timed"),
+            GameVariant::TurnBased => tr!(lang, "variant.turn"),
+        };
+        let state = match (g.over, g.winner) {
+            (false, _) => tr!(lang, "dash.minutes", n = g.seconds / 60),
+            (true, None) => tr!(lang, "state.draw"),
+            (true, Some(w)) => tr!(lang, "dash.winner", winner = player_link(lang, g.sides[w].user, names)),
+        };
+        let _ = write!(body, "<div class=\"game\"><h2>#{} ({})</h2><p>{}</p>", g.id, variant, state);
+        for side in &g.sides {
+            let mark = if side.finished && !g.over {" ✓"} else {""};
+            let _ = write!(body, "<div class=\"board\"><p>{}{}</p>{}</div>",
+                player_link(lang, side.user, names), mark, render_board(side, g.word_length));
+        }
+        body.push_str("</div>");
+    }
+    page(lang, &tr!(lang, "dash.games"), &body)
+}
+
+fn render_profile(lang: Lang, profile: &Profile, names: &HashMap<UserId, String>) -> String {
+    let name = names.get(&profile.user).cloned().unwrap_or_else(|| profile.user.to_string());
+    let mut body = String::new();
+    let standing = match profile.rank {
+        Some(rank) => tr!(lang, "dash.ranked", rank = rank, score = profile.score),
+        None => tr!(lang, "dash.unranked"),
+    };
+    let r = &profile.record;
+    let _ = write!(body, "<p>{}</p><p>{}</p>", standing, tr!(lang, "dash.record",
+        games = r.games, wins = r.wins, losses = r.losses, draws = r.draws, unfinished = r.unfinished));
+    if !profile.live.is_empty() {
+        let live: Vec<_> = profile.live.iter().map(|id| format!("#{}", id)).collect();
+        let _ = write!(body, "<p>{}</p>", tr!(lang, "dash.playing", games = live.join(", ")));
+    }
+    if !profile.recent.is_empty() {
+        let _ = write!(body, "<h2>{}</h2><table><tr><th>#</th><th>{}</th><th>{}</th><th>{}</th><th>{}</th></tr>",
+            tr!(lang, "dash.recent"), tr!(lang, "dash.opponent"), tr!(lang, "dash.outcome"),
+            tr!(lang, "dash.score"), tr!(lang, "dash.ended"));
+        for g in &profile.recent {
+            let outcome = match g.outcome {
+                Outcome::Win => tr!(lang, "dash.win"),
+                Outcome::Loss => tr!(lang, "dash.loss"),
+                Outcome::Draw => tr!(lang, "dash.draw"),
+                Outcome::Unfinished => tr!(lang, "dash.unfinished"),
+            };
+            let _ = write!(body, "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+                g.id, player_link(lang, g.opponent, names), outcome, g.score, date(g.ended));
+        }
+        body.push_str("</table>");
+    }
+    page(lang, &escape(&name), &body)
+}
+
+// "YYYY-MM-DD HH:MM UTC" of a Unix time.
+fn date(secs: u64) -> String {
+    match serenity::Timestamp::from_unix_timestamp(secs as i64) {
+        Ok(t) => format!("{} UTC", t.to_string().get(..16).unwrap_or_default().replace('T', " ")),
+        Err(_) => secs.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_live_game() {
+        let (u1, u2) = (UserId(1), UserId(2));
+        let mut game = GameMP::create(u1, u2, "north".to_string(), GameVariant::Timed, None);
+        assert!(LiveGame::of(7, &game, Instant::now()).is_none());
+        game.respond("slide".to_string(), u2).unwrap();
+        assert!(game.send_guess(0, "tower".to_string()));
+
+        let live = LiveGame::of(7, &game, Instant::now()).unwrap();
+        assert!(!live.over);
+        assert_eq!(live.sides[0].rows.len(), 1);
+        assert!(live.sides[1].rows.is_empty());
+        let names = HashMap::from([(u1, "<b>ann</b>".to_string())]);
+        let page = render_games(Lang::En, std::slice::from_ref(&live), &names);
+        let text = json(&[live], &names).unwrap();
+        // No letters, and names can't inject markup
+        for out in [&page, &text] {
+            assert!(!out.contains("tower") && !out.contains("slide"));
+        }
+        assert!(page.contains("&lt;b&gt;ann") && !page.contains("<b>ann"));
+        assert!(text.contains("\"1\":\"<b>ann</b>\""));
+    }
+
+    #[test]
+    fn test_profile() {
+        let (u1, u2, u3) = (UserId(1), UserId(2), UserId(3));
+        let game = |id, players, finished, winner| ArchivedGame {
+            id,
+            players,
+            words: Default::default(),
+            guesses: Default::default(),
+            variant: GameVariant::Timed,
+            guild: None,
+            handicaps: Default::default(),
+            scores: [5, 2],
+            finished,
+            winner,
+            ended: 1_700_000_000,
+        };
+        let games = vec![game(1, [u1, u2], true, Some(0)), game(2, [u3, u1], true, Some(0)), game(3, [u2, u3], false, None)];
+        let profile = Profile::new(u1, Some((2, 5)), games, vec![4]);
+        assert_eq!((profile.rank, profile.score), (Some(2), 5));
+        assert_eq!((profile.record.games, profile.record.wins, profile.record.losses), (2, 1, 1));
+        let outcomes: Vec<_> = profile.recent.iter().map(|g| (g.id, g.opponent, g.outcome, g.score)).collect();
+        assert_eq!(outcomes, [(2, u3, Outcome::Loss, 2), (1, u2, Outcome::Win, 5)]);
+        assert_eq!(date(1_700_000_000), "2023-11-14 22:13 UTC");
+
+        assert!(!profile.is_empty());
+        assert!(Profile::new(UserId(9), None, vec![], vec![]).is_empty());
+    }
+
+    #[test]
+    fn test_name_cache() {
+        let mut cache = NameCache::default();
+        for id in 0..NAMES_CAPACITY as u64 {
+            cache.insert(UserId(id), id.to_string());
+        }
+        // The first one was used lately, so the second goes instead
+        assert!(cache.get(UserId(0)).is_some());
+        cache.insert(UserId(9999), "new".to_string());
+        assert_eq!(cache.names.len(), NAMES_CAPACITY);
+        assert!(cache.get(UserId(0)).is_some() && cache.get(UserId(1)).is_none());
+    }
+
+    #[test]
+    fn test_route() {
+        assert!(matches!(route("", None), Some(Page::Leaderboard(DEFAULT_COUNT))));
+        assert!(matches!(route("/leaderboard", Some("25")), Some(Page::Leaderboard(25))));
+        assert!(route("/leaderboard", Some("500")).is_none());
+        assert!(matches!(route("/games/", None), Some(Page::Games)));
+        assert!(matches!(route("/players/42", None), Some(Page::Player(UserId(42)))));
+        assert!(route("/players/bob", None).is_none());
+        assert_eq!(query(Some("lang=pl&count=5")).get("count").map(String::as_str), Some("5"));
+    }
+}
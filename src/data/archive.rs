@@ -0,0 +1,51 @@
+use crate::game::{ArchivedGame, GameId};
+use poise::serenity_prelude::UserId;
+use tokio::sync::RwLock as TokioRwLock;
+use std::collections::BTreeMap;
+
+// How many finished games to keep around.
+pub const ARCHIVE_CAPACITY: usize = 1000;
+
+// Recently finished games, oldest dropped first.
+pub struct GameArchive {
+    games: TokioRwLock<BTreeMap<GameId, ArchivedGame>>,
+    capacity: usize,
+}
+
+impl GameArchive {
+    pub fn new(capacity: usize) -> GameArchive {
+        GameArchive {
+            games: TokioRwLock::new(BTreeMap::new()),
+            capacity,
+        }
+    }
+
+    pub async fn add(&self, game: ArchivedGame) {
+        let mut guard = self.games.write().await;
+        guard.insert(game.id, game);
+        // Game IDs are sequential, so the first ones are the oldest.
+        while guard.len() > self.capacity {
+            guard.pop_first();
+        }
+    }
+
+    pub async fn get(&self, id: GameId) -> Option<ArchivedGame> {
+        self.games.read().await.get(&id).cloned()
+    }
+
+    // Games kept that `player` played in, oldest first.
+    pub async fn of_player(&self, player: UserId) -> Vec<ArchivedGame> {
+        self.games.read().await.values().filter(|g| g.players.contains(&player)).cloned().collect()
+    }
+
+    // Every game kept, oldest first.
+    pub async fn list(&self) -> Vec<ArchivedGame> {
+        self.games.read().await.values().cloned().collect()
+    }
+}
+
+impl Default for GameArchive {
+    fn default() -> Self {
+        Self::new(ARCHIVE_CAPACITY)
+    }
+}
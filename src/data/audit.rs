@@ -0,0 +1,68 @@
+// Record of what moderators did on a guild, kept with its settings.
+use poise::serenity_prelude as serenity;
+use serenity::UserId;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::time::{SystemTime, UNIX_EPOCH};
+use crate::game::GameId;
+
+// How many entries each guild keeps, oldest dropped first.
+pub const AUDIT_CAPACITY: usize = 200;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ModAction {
+    EndGame { game: GameId, scored: bool },
+    Score { user: UserId, change: i64, score: u64 },
+    ScoreReset { user: UserId },
+    Ban { user: UserId, until: u64 }, // Unix time in seconds
+    Unban { user: UserId },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub at: u64, // Unix time in seconds
+    pub moderator: UserId,
+    pub action: ModAction,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct AuditLog {
+    entries: VecDeque<AuditEntry>,
+}
+
+impl AuditLog {
+    pub fn record(&mut self, moderator: UserId, action: ModAction) {
+        self.entries.push_back(AuditEntry { at: unix_secs(SystemTime::now()), moderator, action });
+        while self.entries.len() > AUDIT_CAPACITY {
+            self.entries.pop_front();
+        }
+    }
+
+    // Newest first.
+    pub fn latest(&self, count: usize) -> impl Iterator<Item = &AuditEntry> {
+        self.entries.iter().rev().take(count)
+    }
+}
+
+pub fn unix_secs(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_capacity() {
+        let mut log = AuditLog::default();
+        for i in 0..AUDIT_CAPACITY as u64 + 5 {
+            log.record(UserId(1), ModAction::Unban { user: UserId(i) });
+        }
+        assert_eq!(log.latest(usize::MAX).count(), AUDIT_CAPACITY);
+        let newest: Vec<_> = log.latest(2).map(|e| e.action.clone()).collect();
+        let expected = AUDIT_CAPACITY as u64 + 4;
+        assert_eq!(newest, [ModAction::Unban { user: UserId(expected) }, ModAction::Unban { user: UserId(expected - 1) }]);
+        assert_eq!(log.latest(usize::MAX).last().unwrap().action, ModAction::Unban { user: UserId(5) });
+    }
+}
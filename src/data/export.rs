@@ -0,0 +1,359 @@
+// Exports of scores, player stats and finished games, and restoring from them.
+//
+// CSV gets a header and one row per record, JSON an array of the same records.
+// Everything is JSON only: an object with the scores and the games,
+// which is what the command line export reads and what restores the most.
+use crate::commands::util::{CmdError, CmdResult};
+use crate::game::{ArchivedGame, GameVariant};
+use poise::serenity_prelude as serenity;
+use serde::{Deserialize, Serialize};
+use serenity::{GuildId, Timestamp, UserId};
+use std::collections::{BTreeMap, BTreeSet};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, poise::ChoiceParameter)]
+pub enum Dataset {
+    #[name = "Scores"]
+    Scores,
+    #[name = "Player stats"]
+    Players,
+    #[name = "Finished games"]
+    Games,
+    #[name = "Everything (JSON only)"]
+    Everything,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, poise::ChoiceParameter)]
+pub enum Format {
+    #[name = "CSV"]
+    Csv,
+    #[name = "JSON"]
+    Json,
+}
+
+impl Dataset {
+    pub fn filename(&self, format: Format) -> String {
+        let name = match self {
+            Dataset::Scores => "scores",
+            Dataset::Players => "players",
+            Dataset::Games => "games",
+            Dataset::Everything => "wordclash",
+        };
+        let extension = match format {
+            Format::Csv => "csv",
+            Format::Json => "json",
+        };
+        format!("{}.{}", name, extension)
+    }
+}
+
+// Which games to export, all of them by default.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Filter {
+    pub since: Option<u64>, // Unix time in seconds, inclusive
+    pub until: Option<u64>, // exclusive
+    pub guild: Option<GuildId>,
+}
+
+impl Filter {
+    pub fn allows(&self, game: &ArchivedGame) -> bool {
+        self.since.is_none_or(|t| game.ended >= t)
+            && self.until.is_none_or(|t| game.ended < t)
+            && self.guild.is_none_or(|g| game.guild == Some(g))
+    }
+
+    fn is_all(&self) -> bool {
+        *self == Filter::default()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScoreRow {
+    pub user: UserId,
+    pub score: u64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlayerRow {
+    pub user: UserId,
+    pub score: u64, // overall, not only from the games exported
+    pub games: u64,
+    pub wins: u64,
+    pub losses: u64,
+    pub draws: u64,
+    pub unfinished: u64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub scores: Vec<ScoreRow>,
+    pub games: Vec<ArchivedGame>,
+}
+
+impl Snapshot {
+    // Only the games passing the filter, and the scores of their players unless it lets everything through.
+    pub fn filtered(mut self, filter: &Filter) -> Snapshot {
+        if filter.is_all() {
+            return self;
+        }
+        self.games.retain(|g| filter.allows(g));
+        let players: BTreeSet<_> = self.games.iter().flat_map(|g| g.players).collect();
+        self.scores.retain(|s| players.contains(&s.user));
+        self
+    }
+
+    // Everyone with a score or a game, best scores first.
+    pub fn players(&self) -> Vec<PlayerRow> {
+        let mut rows: BTreeMap<UserId, PlayerRow> = BTreeMap::new();
+        fn row(rows: &mut BTreeMap<UserId, PlayerRow>, user: UserId) -> &mut PlayerRow {
+            rows.entry(user).or_insert_with(|| PlayerRow { user, ..Default::default() })
+        }
+        for s in &self.scores {
+            row(&mut rows, s.user).score = s.score;
+        }
+        for g in &self.games {
+            for (side, &user) in g.players.iter().enumerate() {
+                let r = row(&mut rows, user);
+                r.games += 1;
+                match (g.finished, g.winner) {
+                    (false, _) => r.unfinished += 1,
+                    (true, None) => r.draws += 1,
+                    (true, Some(w)) if w == side => r.wins += 1,
+                    (true, Some(_)) => r.losses += 1,
+                }
+            }
+        }
+        let mut rows: Vec<_> = rows.into_values().collect();
+        rows.sort_by(|a, b| b.score.cmp(&a.score).then(a.user.cmp(&b.user)));
+        rows
+    }
+
+    pub fn render(&self, dataset: Dataset, format: Format) -> CmdResult<String> {
+        let json = match (dataset, format) {
+            (Dataset::Everything, Format::Csv) => return Err(CmdError::CsvUnavailable),
+            (Dataset::Scores, Format::Csv) => return Ok(csv(
+                &["user", "score"],
+                self.scores.iter().map(|s| vec![s.user.to_string(), s.score.to_string()]),
+            )),
+            (Dataset::Players, Format::Csv) => return Ok(csv(
+                &["user", "score", "games", "wins", "losses", "draws", "unfinished"],
+                self.players().iter().map(|p| {
+                    [p.user.0, p.score, p.games, p.wins, p.losses, p.draws, p.unfinished].map(|n| n.to_string()).to_vec()
+                }),
+            )),
+            (Dataset::Games, Format::Csv) => return Ok(csv(&GAME_COLUMNS, self.games.iter().map(game_row))),
+            (Dataset::Scores, Format::Json) => serde_json::to_string_pretty(&self.scores),
+            (Dataset::Players, Format::Json) => serde_json::to_string_pretty(&self.players()),
+            (Dataset::Games, Format::Json) => serde_json::to_string_pretty(&self.games),
+            (Dataset::Everything, Format::Json) => serde_json::to_string_pretty(self),
+        };
+        json.map_err(|e| CmdError::Hard(e.into()))
+    }
+
+    // Read an export back. Player stats only restore scores, the rest follows from the games.
+    pub fn parse(dataset: Dataset, text: &str) -> CmdResult<Snapshot> {
+        let bad = |e: &dyn std::fmt::Display| CmdError::BadImport(e.to_string());
+        let is_json = text.trim_start().starts_with(['[', '{']);
+        match (dataset, is_json) {
+            (Dataset::Scores | Dataset::Players, false) => {
+                let scores = parse_csv(text)?
+                    .into_iter()
+                    .map(|row| Ok(ScoreRow { user: UserId(number(&row, "user")?), score: number(&row, "score")? }))
+                    .collect::<CmdResult<_>>()?;
+                Ok(Snapshot { scores, games: Vec::new() })
+            }
+            (Dataset::Scores, true) => {
+                let scores = serde_json::from_str(text).map_err(|e| bad(&e))?;
+                Ok(Snapshot { scores, games: Vec::new() })
+            }
+            (Dataset::Players, true) => {
+                let players: Vec<PlayerRow> = serde_json::from_str(text).map_err(|e| bad(&e))?;
+                let scores = players.into_iter().map(|p| ScoreRow { user: p.user, score: p.score }).collect();
+                Ok(Snapshot { scores, games: Vec::new() })
+            }
+            (Dataset::Games, true) => {
+                let games = serde_json::from_str(text).map_err(|e| bad(&e))?;
+                Ok(Snapshot { scores: Vec::new(), games })
+            }
+            (Dataset::Everything, true) => serde_json::from_str(text).map_err(|e| bad(&e)),
+            // Handicaps aren't in there
+            (Dataset::Games | Dataset::Everything, false) => Err(bad(&"games can only be restored from JSON")),
+        }
+    }
+}
+
+const GAME_COLUMNS: [&str; 14] = [
+    "id", "ended", "variant", "guild", "player_1", "player_2", "word_1", "word_2",
+    "guesses_1", "guesses_2", "score_1", "score_2", "finished", "winner",
+];
+
+fn game_row(g: &ArchivedGame) -> Vec<String> {
+    let ended = Timestamp::from_unix_timestamp(g.ended as i64).map_or_else(|_| g.ended.to_string(), |t| t.to_string());
+    let variant = match g.variant {
+        GameVariant::Timed => "timed",
+        GameVariant::TurnBased => "turn-based",
+    };
+    vec![
+        g.id.to_string(),
+        ended,
+        variant.to_string(),
+        g.guild.map_or_else(String::new, |g| g.to_string()),
+        g.players[0].to_string(),
+        g.players[1].to_string(),
+        g.words[0].clone(),
+        g.words[1].clone(),
+        g.guesses[0].join(" "),
+        g.guesses[1].join(" "),
+        g.scores[0].to_string(),
+        g.scores[1].to_string(),
+        g.finished.to_string(),
+        g.winner.map_or_else(String::new, |w| (w + 1).to_string()),
+    ]
+}
+
+fn csv(header: &[&str], rows: impl Iterator<Item = Vec<String>>) -> String {
+    let mut out = header.join(",");
+    out.push('\n');
+    for row in rows {
+        out.push_str(&row.iter().map(|f| csv_field(f)).collect::<Vec<_>>().join(","));
+        out.push('\n');
+    }
+    out
+}
+
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+// Rows of a CSV file with a header, as column name to value.
+fn parse_csv(text: &str) -> CmdResult<Vec<BTreeMap<String, String>>> {
+    let mut records = Vec::new();
+    let (mut record, mut field) = (Vec::new(), String::new());
+    let (mut quoted, mut chars) = (false, text.chars().peekable());
+    while let Some(c) = chars.next() {
+        match (c, quoted) {
+            ('"', true) if chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            ('"', _) => quoted = !quoted,
+            (',', false) => record.push(std::mem::take(&mut field)),
+            ('\n', false) => {
+                record.push(std::mem::take(&mut field));
+                records.push(std::mem::take(&mut record));
+            }
+            ('\r', false) => {}
+            (c, _) => field.push(c),
+        }
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+    records.retain(|r| r.iter().any(|f| !f.trim().is_empty()));
+    let mut records = records.into_iter();
+    let header: Vec<String> = records.next().unwrap_or_default().iter().map(|h| h.trim().to_lowercase()).collect();
+    records
+        .map(|r| match r.len() == header.len() {
+            true => Ok(header.iter().cloned().zip(r).collect()),
+            false => Err(CmdError::BadImport(format!("expected {} columns, got {}", header.len(), r.len()))),
+        })
+        .collect()
+}
+
+fn number(row: &BTreeMap<String, String>, column: &str) -> CmdResult<u64> {
+    let value = row.get(column).ok_or_else(|| CmdError::BadImport(format!("no {} column", column)))?;
+    value.trim().parse().map_err(|_| CmdError::BadImport(format!("{} isn't a number: {}", column, value)))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::game::multiplayer::Handicap;
+
+    fn game(id: u64, players: [u64; 2], guild: Option<u64>, ended: u64, winner: Option<usize>) -> ArchivedGame {
+        ArchivedGame {
+            id,
+            players: players.map(UserId),
+            words: ["rails".into(), "tower".into()],
+            guesses: [vec!["tower".into()], vec!["smile".into(), "rails".into()]],
+            variant: GameVariant::Timed,
+            guild: guild.map(GuildId),
+            handicaps: [Handicap::default(); 2],
+            scores: [6, 5],
+            finished: true,
+            winner,
+            ended,
+        }
+    }
+
+    fn snapshot() -> Snapshot {
+        Snapshot {
+            scores: vec![ScoreRow { user: UserId(1), score: 11 }, ScoreRow { user: UserId(2), score: 5 }],
+            games: vec![game(1, [1, 2], Some(9), 1000, Some(0)), game(2, [2, 3], None, 2000, None)],
+        }
+    }
+
+    #[test]
+    fn test_filter() {
+        let filter = Filter { since: Some(1500), ..Default::default() };
+        let s = snapshot().filtered(&filter);
+        assert_eq!(s.games.iter().map(|g| g.id).collect::<Vec<_>>(), [2]);
+        assert_eq!(s.scores, [ScoreRow { user: UserId(2), score: 5 }]);
+        let filter = Filter { guild: Some(GuildId(9)), until: Some(1001), ..Default::default() };
+        assert_eq!(snapshot().filtered(&filter).games.len(), 1);
+        assert_eq!(snapshot().filtered(&Filter::default()), snapshot());
+    }
+
+    #[test]
+    fn test_players() {
+        let players = snapshot().players();
+        assert_eq!(players.iter().map(|p| p.user.0).collect::<Vec<_>>(), [1, 2, 3]);
+        assert_eq!((players[0].wins, players[0].games), (1, 1));
+        assert_eq!((players[1].losses, players[1].draws, players[1].games), (1, 1, 2));
+        assert_eq!((players[2].score, players[2].draws), (0, 1));
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let s = snapshot();
+        for format in [Format::Csv, Format::Json] {
+            for dataset in [Dataset::Scores, Dataset::Players] {
+                let restored = Snapshot::parse(dataset, &s.render(dataset, format).unwrap()).unwrap();
+                let expected = match dataset {
+                    Dataset::Players => vec![
+                        ScoreRow { user: UserId(1), score: 11 },
+                        ScoreRow { user: UserId(2), score: 5 },
+                        ScoreRow { user: UserId(3), score: 0 },
+                    ],
+                    _ => s.scores.clone(),
+                };
+                assert_eq!(restored.scores, expected);
+            }
+        }
+        let restored = Snapshot::parse(Dataset::Games, &s.render(Dataset::Games, Format::Json).unwrap()).unwrap();
+        assert_eq!(restored.games, s.games);
+        let everything = s.render(Dataset::Everything, Format::Json).unwrap();
+        assert_eq!(Snapshot::parse(Dataset::Everything, &everything).unwrap(), s);
+        assert!(matches!(s.render(Dataset::Everything, Format::Csv), Err(CmdError::CsvUnavailable)));
+
+        let games = s.render(Dataset::Games, Format::Csv).unwrap();
+        assert_eq!(games.lines().nth(1), Some("1,1970-01-01T00:16:40.000Z,timed,9,1,2,rails,tower,tower,smile rails,6,5,true,1"));
+        assert!(matches!(Snapshot::parse(Dataset::Games, &games), Err(CmdError::BadImport(_))));
+    }
+
+    #[test]
+    fn test_csv() {
+        assert_eq!(csv_field("a,\"b\""), "\"a,\"\"b\"\"\"");
+        let rows = parse_csv("User,Score,note\r\n1,2,\"a, \"\"b\"\"\"\n\n3,4,\"multi\nline\"").unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0]["note"], "a, \"b\"");
+        assert_eq!(rows[1]["note"], "multi\nline");
+        assert_eq!(number(&rows[1], "score").unwrap(), 4);
+        assert!(matches!(parse_csv("user,score\n1"), Err(CmdError::BadImport(_))));
+        assert!(matches!(number(&rows[0], "games"), Err(CmdError::BadImport(_))));
+    }
+}
@@ -0,0 +1,181 @@
+use poise::serenity_prelude as serenity;
+use serenity::{ChannelId, GuildId, RoleId, UserId};
+use serde::{Deserialize, Serialize};
+use tokio::sync::{RwLock as TokioRwLock, Mutex as TokioMutex};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use crate::dict::overlay::WordOverlay;
+use crate::i18n::Lang;
+use super::audit::AuditLog;
+use super::persist::{self, PersistError};
+
+pub const GUILDS_VARNAME: &str = "WORDCLASH_GUILDS";
+pub const GUILDS_FILENAME: &str = "guilds.json";
+
+// Per-guild settings, saved across restarts.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GuildData {
+    pub words: WordOverlay,
+    pub lang: Lang,
+    pub game_channels: BTreeSet<ChannelId>, // games may start only here, anywhere if empty
+    pub word_length: Option<usize>, // of random words for challenges without one
+    pub guesses: Option<usize>, // instead of one more than the word length
+    pub invite_expiry: Option<u64>, // in seconds, for both variants
+    pub random_words: bool, // may players ask for a random word?
+    pub leaderboard: LeaderboardMode,
+    pub announcements: Option<ChannelId>, // where game results are posted
+    pub admin_roles: BTreeSet<RoleId>, // members with these are admins to the bot
+    pub bans: BTreeMap<UserId, u64>, // may not challenge anyone until then, Unix time in seconds
+    pub audit: AuditLog,
+}
+
+impl Default for GuildData {
+    fn default() -> Self {
+        GuildData {
+            words: WordOverlay::default(),
+            lang: Lang::default(),
+            game_channels: BTreeSet::new(),
+            word_length: None,
+            guesses: None,
+            invite_expiry: None,
+            random_words: true,
+            leaderboard: LeaderboardMode::default(),
+            announcements: None,
+            admin_roles: BTreeSet::new(),
+            bans: BTreeMap::new(),
+            audit: AuditLog::default(),
+        }
+    }
+}
+
+impl GuildData {
+    pub fn allows_channel(&self, channel: ChannelId) -> bool {
+        self.game_channels.is_empty() || self.game_channels.contains(&channel)
+    }
+
+    // When the user's ban ends, if they're banned at `now`.
+    pub fn banned_until(&self, user: UserId, now: SystemTime) -> Option<SystemTime> {
+        let until = UNIX_EPOCH + Duration::from_secs(*self.bans.get(&user)?);
+        (until > now).then_some(until)
+    }
+}
+
+// Who gets to see the leaderboard.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, poise::ChoiceParameter)]
+pub enum LeaderboardMode {
+    #[default]
+    #[name = "Only who asked"]
+    Private,
+    #[name = "Everyone in the channel"]
+    Public,
+    #[name = "Nobody"]
+    Off,
+}
+
+pub struct GuildManager {
+    guilds: TokioRwLock<HashMap<GuildId, GuildData>>,
+    path: Option<PathBuf>, // None keeps everything in memory
+    // Bumped on every change, under the map's write lock.
+    generation: AtomicU64,
+    // Serializes file writes and holds the generation last written,
+    // so an older snapshot never overwrites a newer one.
+    saved: TokioMutex<u64>,
+}
+
+impl GuildManager {
+    pub fn new() -> GuildManager {
+        GuildManager {
+            guilds: TokioRwLock::new(HashMap::new()),
+            path: None,
+            generation: AtomicU64::new(0),
+            saved: TokioMutex::new(0),
+        }
+    }
+
+    pub fn load_from(path: PathBuf) -> Result<GuildManager, PersistError> {
+        Ok(GuildManager {
+            guilds: TokioRwLock::new(persist::load(&path)?),
+            path: Some(path),
+            generation: AtomicU64::new(0),
+            saved: TokioMutex::new(0),
+        })
+    }
+
+    pub fn load() -> Result<GuildManager, PersistError> {
+        Self::load_from(persist::data_path(GUILDS_VARNAME, GUILDS_FILENAME).map_err(PersistError::NoPath)?)
+    }
+
+    // Read a guild's data. Gets None outside of guilds or if the guild never changed anything.
+    pub async fn with_read<T, F: FnOnce(Option<&GuildData>) -> T>(&self, id: Option<GuildId>, f: F) -> T {
+        let guard = self.guilds.read().await;
+        f(id.and_then(|id| guard.get(&id)))
+    }
+
+    // Modify a guild's data, then save everything.
+    // Only the snapshot is taken under the lock, games don't wait for the disk.
+    pub async fn with_write<T, F: FnOnce(&mut GuildData) -> T>(&self, id: GuildId, f: F) -> Result<T, PersistError> {
+        let (res, snapshot) = {
+            let mut guard = self.guilds.write().await;
+            let res = f(guard.entry(id).or_default());
+            let generation = self.generation.fetch_add(1, Ordering::Relaxed) + 1;
+            let snapshot = match &self.path {
+                Some(path) => Some((path, generation, persist::to_json(path, &*guard)?)),
+                None => None,
+            };
+            (res, snapshot)
+        };
+        if let Some((path, generation, buf)) = snapshot {
+            let mut saved = self.saved.lock().await;
+            if *saved < generation {
+                persist::write(path, buf).await?;
+                *saved = generation;
+            }
+        }
+        Ok(res)
+    }
+}
+
+impl Default for GuildManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_persistence() {
+        let path = std::env::temp_dir().join(format!("wordclash-guilds-{}.json", std::process::id()));
+        let guild = GuildId(4444);
+        {
+            let manager = GuildManager::load_from(path.clone()).unwrap();
+            manager.with_write(guild, |g| g.words.deny.insert("crude".to_string())).await.unwrap();
+        }
+        let manager = GuildManager::load_from(path.clone()).unwrap();
+        assert!(manager.with_read(Some(guild), |g| g.unwrap().words.deny.contains("crude")).await);
+
+        // Concurrent changes all make it to the file
+        let manager = std::sync::Arc::new(manager);
+        let tasks: Vec<_> = (0..16).map(|i| {
+            let manager = manager.clone();
+            tokio::spawn(async move {
+                manager.with_write(GuildId(5000 + i), |g| g.words.allow.insert("wordy".to_string())).await.unwrap();
+            })
+        }).collect();
+        for t in tasks {
+            t.await.unwrap();
+        }
+        let manager = GuildManager::load_from(path.clone()).unwrap();
+        for i in 0..16 {
+            assert!(manager.with_read(Some(GuildId(5000 + i)), |g| g.is_some()).await);
+        }
+        assert!(manager.with_read(Some(GuildId(4445)), |g| g.is_none()).await);
+        assert!(manager.with_read(None, |g| g.is_none()).await);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
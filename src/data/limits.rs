@@ -0,0 +1,139 @@
+// Rate limits and caps on games, see config::Limits.
+//
+// Keeps its own record of every game, so counting doesn't need to lock any of them.
+// It's a leaf like the metrics: the mutex is never held across an await.
+use crate::commands::util::{CmdError, CmdResult};
+use crate::config;
+use crate::game::GameId;
+use poise::serenity_prelude as serenity;
+use serenity::{GuildId, UserId};
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+// Window of the commands per minute limit.
+const COMMAND_WINDOW: Duration = Duration::from_secs(60);
+
+struct TrackedGame {
+    challenger: UserId,
+    guild: Option<GuildId>,
+    started: bool,
+}
+
+#[derive(Default)]
+struct State {
+    commands: HashMap<UserId, VecDeque<Instant>>,
+    games: HashMap<GameId, TrackedGame>,
+}
+
+pub struct Limiter {
+    limits: config::Limits,
+    state: Mutex<State>,
+}
+
+impl Limiter {
+    pub fn new(limits: config::Limits) -> Limiter {
+        Limiter {
+            limits,
+            state: Mutex::new(State::default()),
+        }
+    }
+
+    // Count a command of the user, unless they used up their commands for the minute.
+    pub fn command(&self, user: UserId, now: Instant) -> CmdResult<()> {
+        let mut state = self.state.lock().unwrap();
+        let recent = state.commands.entry(user).or_default();
+        prune(recent, now);
+        if recent.len() >= self.limits.commands_per_minute {
+            let wait = COMMAND_WINDOW.saturating_sub(now - recent[0]);
+            return Err(CmdError::SlowDown(wait));
+        }
+        recent.push_back(now);
+        Ok(())
+    }
+
+    // Keep track of a new game, if neither its challenger nor its guild has too many going already.
+    pub fn add_game(&self, id: GameId, challenger: UserId, guild: Option<GuildId>) -> CmdResult<()> {
+        let mut state = self.state.lock().unwrap();
+        let waiting = state.games.values().filter(|g| g.challenger == challenger && !g.started).count();
+        if waiting >= self.limits.outgoing_invites {
+            return Err(CmdError::TooManyInvites(self.limits.outgoing_invites));
+        }
+        if guild.is_some() && state.games.values().filter(|g| g.guild == guild).count() >= self.limits.guild_games {
+            return Err(CmdError::GuildGamesFull(self.limits.guild_games));
+        }
+        state.games.insert(id, TrackedGame { challenger, guild, started: false });
+        Ok(())
+    }
+
+    // The game's invite was accepted, it no longer counts as one.
+    pub fn start_game(&self, id: GameId) {
+        if let Some(game) = self.state.lock().unwrap().games.get_mut(&id) {
+            game.started = true;
+        }
+    }
+
+    pub fn remove_game(&self, id: GameId) {
+        self.state.lock().unwrap().games.remove(&id);
+    }
+
+    // Refuse to let a user get into more turn-based games than allowed, `current` counted.
+    pub fn check_turn_games(&self, current: usize) -> CmdResult<()> {
+        if current >= self.limits.turn_games {
+            return Err(CmdError::TooManyTurnGames(self.limits.turn_games));
+        }
+        Ok(())
+    }
+
+    // Forget users who haven't used a command lately.
+    pub fn cleanup(&self, now: Instant) {
+        self.state.lock().unwrap().commands.retain(|_, recent| {
+            prune(recent, now);
+            !recent.is_empty()
+        });
+    }
+}
+
+fn prune(recent: &mut VecDeque<Instant>, now: Instant) {
+    while recent.front().is_some_and(|t| now - *t >= COMMAND_WINDOW) {
+        recent.pop_front();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_limits() {
+        let limits = config::Limits { outgoing_invites: 2, turn_games: 1, commands_per_minute: 3, guild_games: 3 };
+        let limiter = Limiter::new(limits);
+        let (u1, u2, guild) = (UserId(1), UserId(2), Some(GuildId(9)));
+
+        let start = Instant::now();
+        for i in 0..3 {
+            limiter.command(u1, start + Duration::from_secs(i)).unwrap();
+        }
+        let res = limiter.command(u1, start + Duration::from_secs(10));
+        assert!(matches!(res, Err(CmdError::SlowDown(wait)) if wait == Duration::from_secs(50)));
+        limiter.command(u2, start + Duration::from_secs(10)).unwrap();
+        limiter.command(u1, start + Duration::from_secs(60)).unwrap();
+        limiter.cleanup(start + Duration::from_secs(120));
+        assert!(limiter.state.lock().unwrap().commands.is_empty());
+
+        limiter.add_game(1, u1, guild).unwrap();
+        limiter.add_game(2, u1, None).unwrap();
+        assert!(matches!(limiter.add_game(3, u1, guild), Err(CmdError::TooManyInvites(2))));
+        // Accepted games don't count as invites
+        limiter.start_game(1);
+        limiter.add_game(3, u1, guild).unwrap();
+        limiter.add_game(4, u2, guild).unwrap();
+        assert!(matches!(limiter.add_game(5, u2, guild), Err(CmdError::GuildGamesFull(3))));
+        limiter.add_game(5, u2, None).unwrap();
+        limiter.remove_game(4);
+        limiter.add_game(6, u2, guild).unwrap();
+
+        assert!(limiter.check_turn_games(0).is_ok());
+        assert!(matches!(limiter.check_turn_games(1), Err(CmdError::TooManyTurnGames(1))));
+    }
+}
@@ -1,14 +1,19 @@
 use crate::game;
-use tokio::sync::RwLock as TokioRwLock;
-use std::sync::atomic;
+use tokio::sync::{RwLock as TokioRwLock, Mutex as TokioMutex, MutexGuard};
+use std::sync::{atomic, Arc};
 use std::collections::HashMap;
 use poise::serenity_prelude as serenity;
 use serenity::UserId;
 use crate::dict;
+use crate::i18n::Lang;
+use crate::commands::util::CmdResult;
 
 pub struct UserData {
     pub player: game::PlayerData,
     pub score: u64,
+    pub notify: notify::NotifyPrefs,
+    pub privacy: privacy::PrivacyPrefs,
+    pub lang: Option<Lang>, // None follows the guild
 }
 
 impl UserData {
@@ -16,6 +21,9 @@ impl UserData {
         UserData {
             player: game::PlayerData::new(),
             score: 0,
+            notify: notify::NotifyPrefs::default(),
+            privacy: privacy::PrivacyPrefs::default(),
+            lang: None,
         }
     }
 }
@@ -27,32 +35,170 @@ impl Default for UserData {
 }
 
 pub mod scores;
+pub mod guilds;
+pub mod archive;
+pub mod audit;
+pub mod export;
+pub mod persist;
+pub mod limits;
+pub mod queue;
+pub mod notify;
+pub mod privacy;
 
+// Individually lockable piece of shared state.
+pub type Shared<T> = Arc<TokioMutex<T>>;
+
+// Lock ordering
+// -------------
+// Every game and every user sits behind its own mutex, the maps only hold handles.
+// To stay deadlock-free, locks are always taken in this order:
+// 1. at most one game (`mpgames` entry),
+// 2. user entries (`userdata`), several at once only in ascending UserId order
+//    (use `lock_users`),
+// 3. the score manager, then the game archive.
+// The map locks themselves are leaves: they're held just long enough to clone
+// or insert a handle, and nothing else is awaited while holding them.
+// That goes for `series` and `open_challenges` too, which hold data rather than handles:
+// they're read or changed in place, often with game and user locks held,
+// and always released before any other lock is taken.
+// The matchmaking queue is a leaf too, games are started after it's released,
+// and so is the limiter.
+// Guild settings are a leaf as well: their closures never take other locks,
+// and changes are written to disk after the guild map lock is released.
 pub struct CtxData {
+    pub config: crate::config::Config, // immutable
     pub dict: dict::Dictionary, // immutable
-    pub mpgames: TokioRwLock<HashMap<game::GameId, game::GameMP>>,
-    pub userdata: TokioRwLock<HashMap<UserId, UserData>>,
+    pub defs: dict::definitions::Definitions, // immutable
+    pub mpgames: TokioRwLock<HashMap<game::GameId, Shared<game::GameMP>>>,
+    pub userdata: TokioRwLock<HashMap<UserId, Shared<UserData>>>,
+    // At most one per challenger
+    pub open_challenges: TokioRwLock<HashMap<UserId, game::OpenChallenge>>,
+    // Keyed by the pair in ascending order, see series_key
+    pub series: TokioRwLock<HashMap<(UserId, UserId), game::Series>>,
+    pub metrics: crate::metrics::Metrics, // a leaf too, behind a std mutex
     // Used internally. Generates sequential IDs.
     gameid_gen: game::AtomicGameId,
     scores: scores::ScoreManager,
+    archive: archive::GameArchive,
+    guilds: guilds::GuildManager,
+    queue: queue::MatchQueue,
+    limits: limits::Limiter,
 }
 
 impl CtxData {
-    pub fn new(dict: dict::Dictionary) -> CtxData {
+    pub fn new(
+        config: crate::config::Config,
+        dict: dict::Dictionary,
+        defs: dict::definitions::Definitions,
+        guilds: guilds::GuildManager,
+    ) -> CtxData {
         CtxData {
             dict,
+            defs,
             mpgames: TokioRwLock::new(HashMap::new()),
             userdata: TokioRwLock::new(HashMap::new()),
+            open_challenges: TokioRwLock::new(HashMap::new()),
+            series: TokioRwLock::new(HashMap::new()),
+            metrics: Default::default(),
             gameid_gen: game::AtomicGameId::new(0),
             scores: scores::ScoreManager::new(),
+            archive: archive::GameArchive::default(),
+            guilds,
+            queue: queue::MatchQueue::new(config.matchmaking.clone()),
+            limits: limits::Limiter::new(config.limits.clone()),
+            config,
         }
     }
-    
+
     pub fn pull_gameid(&self) -> game::GameId {
         self.gameid_gen.fetch_add(1, atomic::Ordering::Relaxed)
     }
 
+    // Make sure IDs pulled from now on come after `id`.
+    pub fn skip_gameids(&self, id: game::GameId) {
+        self.gameid_gen.fetch_max(id + 1, atomic::Ordering::Relaxed);
+    }
+
     pub fn scores(&self) -> &scores::ScoreManager {
         &self.scores
     }
-}
\ No newline at end of file
+
+    pub fn archive(&self) -> &archive::GameArchive {
+        &self.archive
+    }
+
+    pub fn guilds(&self) -> &guilds::GuildManager {
+        &self.guilds
+    }
+
+    pub fn queue(&self) -> &queue::MatchQueue {
+        &self.queue
+    }
+
+    pub fn limits(&self) -> &limits::Limiter {
+        &self.limits
+    }
+
+    // Get a handle to a game, if it exists.
+    pub async fn game(&self, id: game::GameId) -> Option<Shared<game::GameMP>> {
+        self.mpgames.read().await.get(&id).cloned()
+    }
+
+    // Fails if the challenger or the guild has too many games already.
+    pub async fn insert_game(&self, id: game::GameId, gamedata: game::GameMP) -> CmdResult<Shared<game::GameMP>> {
+        self.limits.add_game(id, gamedata.get_user_id(0), gamedata.get_guild())?;
+        tracing::info!(
+            game = id,
+            variant = ?gamedata.get_variant(),
+            challenger = %gamedata.get_user_id(0),
+            challenged = %gamedata.get_user_id(1),
+            guild = ?gamedata.get_guild().map(|g| g.0),
+            "game created",
+        );
+        let handle = Arc::new(TokioMutex::new(gamedata));
+        self.mpgames.write().await.insert(id, handle.clone());
+        Ok(handle)
+    }
+
+    pub async fn remove_game(&self, id: game::GameId) -> Option<Shared<game::GameMP>> {
+        let handle = self.mpgames.write().await.remove(&id);
+        if handle.is_some() {
+            self.limits.remove_game(id);
+        }
+        handle
+    }
+
+    // Get a handle to user data, if the user is known.
+    pub async fn find_user(&self, id: UserId) -> Option<Shared<UserData>> {
+        self.userdata.read().await.get(&id).cloned()
+    }
+
+    // Get a handle to user data, creating empty data if the user is not known yet.
+    pub async fn user(&self, id: UserId) -> Shared<UserData> {
+        if let Some(handle) = self.find_user(id).await {
+            return handle;
+        }
+        self.userdata.write().await.entry(id).or_default().clone()
+    }
+}
+
+pub fn series_key(a: UserId, b: UserId) -> (UserId, UserId) {
+    (a.min(b), a.max(b))
+}
+
+// Lock two distinct users in ascending UserId order, see the lock ordering above.
+// Guards are returned in the order of the arguments.
+pub async fn lock_users<'a>(
+    a: (UserId, &'a Shared<UserData>),
+    b: (UserId, &'a Shared<UserData>),
+) -> (MutexGuard<'a, UserData>, MutexGuard<'a, UserData>) {
+    if a.0 <= b.0 {
+        let guard_a = a.1.lock().await;
+        let guard_b = b.1.lock().await;
+        (guard_a, guard_b)
+    } else {
+        let guard_b = b.1.lock().await;
+        let guard_a = a.1.lock().await;
+        (guard_a, guard_b)
+    }
+}
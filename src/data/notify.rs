@@ -0,0 +1,92 @@
+// Direct message preferences.
+
+use crate::i18n::{tr, Lang};
+
+// What a user can get a DM about.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, poise::ChoiceParameter)]
+pub enum NotifyEvent {
+    #[name = "Challenged"]
+    Challenged,
+    #[name = "Challenge accepted"]
+    Accepted,
+    #[name = "Your turn"]
+    Turn,
+    #[name = "Game over"]
+    GameEnd,
+}
+
+impl NotifyEvent {
+    pub const ALL: [NotifyEvent; 4] = [
+        NotifyEvent::Challenged,
+        NotifyEvent::Accepted,
+        NotifyEvent::Turn,
+        NotifyEvent::GameEnd,
+    ];
+
+    pub fn describe(&self, lang: Lang) -> String {
+        use NotifyEvent::*;
+        match self {
+            Challenged => tr!(lang, "notify.event.challenged"),
+            Accepted => tr!(lang, "notify.event.accepted"),
+            Turn => tr!(lang, "notify.event.turn"),
+            GameEnd => tr!(lang, "notify.event.game_end"),
+        }
+    }
+}
+
+// Events a user wants DMs about. Nothing is sent unless asked for.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct NotifyPrefs {
+    challenged: bool,
+    accepted: bool,
+    turn: bool,
+    game_end: bool,
+}
+
+impl NotifyPrefs {
+    fn flag(&mut self, event: NotifyEvent) -> &mut bool {
+        use NotifyEvent::*;
+        match event {
+            Challenged => &mut self.challenged,
+            Accepted => &mut self.accepted,
+            Turn => &mut self.turn,
+            GameEnd => &mut self.game_end,
+        }
+    }
+
+    pub fn get(&self, event: NotifyEvent) -> bool {
+        let mut prefs = *self;
+        *prefs.flag(event)
+    }
+
+    pub fn set(&mut self, event: NotifyEvent, on: bool) {
+        *self.flag(event) = on;
+    }
+
+    // One line per event.
+    pub fn render(&self, lang: Lang) -> String {
+        NotifyEvent::ALL
+            .iter()
+            .map(|&e| format!("{}: {}", e.describe(lang), tr!(lang, if self.get(e) {"notify.on"} else {"notify.off"})))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_prefs() {
+        let mut prefs = NotifyPrefs::default();
+        assert!(NotifyEvent::ALL.iter().all(|&e| !prefs.get(e)));
+        prefs.set(NotifyEvent::Turn, true);
+        assert!(prefs.get(NotifyEvent::Turn));
+        assert!(!prefs.get(NotifyEvent::GameEnd));
+        assert_eq!(prefs.render(Lang::En).lines().nth(2), Some("Your turn: on"));
+        assert_eq!(prefs.render(Lang::Pl).lines().nth(2), Some("Twoja kolej: wł."));
+        prefs.set(NotifyEvent::Turn, false);
+        assert_eq!(prefs, NotifyPrefs::default());
+    }
+}
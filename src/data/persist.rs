@@ -0,0 +1,64 @@
+// Saving and loading of bot state as JSON files.
+use serde::{de::DeserializeOwned, Serialize};
+use std::path::{Path, PathBuf};
+use std::{env, error, fmt, fs, io};
+
+#[derive(Debug)]
+pub enum PersistError {
+    NoPath(io::Error), // no path given and executable location unknown
+    Io(PathBuf, io::Error),
+    Json(PathBuf, serde_json::Error),
+}
+
+impl fmt::Display for PersistError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use PersistError::*;
+        match self {
+            NoPath(e) => write!(f, "Data path unknown: {}", e),
+            Io(p, e) => write!(f, "Failed to access {}: {}", p.display(), e),
+            Json(p, e) => write!(f, "Malformed data in {}: {}", p.display(), e),
+        }
+    }
+}
+
+impl error::Error for PersistError {}
+
+// Get a data file path from an environment variable, or put it next to the executable.
+// Fails only if the executable's location is unknown.
+pub fn data_path(varname: &str, filename: &str) -> io::Result<PathBuf> {
+    env::var(varname)
+        .map(PathBuf::from)
+        .or_else(|_| {
+            env::current_exe().map(|mut p| {
+                p.pop();
+                p.push(filename);
+                p
+            })
+        })
+}
+
+// Load JSON from `path`. A missing file is not an error, it just means nothing was saved yet.
+pub fn load<T: DeserializeOwned + Default>(path: &Path) -> Result<T, PersistError> {
+    match fs::read(path) {
+        Ok(buf) => serde_json::from_slice(&buf).map_err(|e| PersistError::Json(path.into(), e)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(T::default()),
+        Err(e) => Err(PersistError::Io(path.into(), e)),
+    }
+}
+
+// Serialize what is to be saved to `path`.
+// Kept apart from `write` so it can be done under a lock while the file I/O isn't.
+pub fn to_json<T: Serialize>(path: &Path, value: &T) -> Result<Vec<u8>, PersistError> {
+    serde_json::to_vec(value).map_err(|e| PersistError::Json(path.into(), e))
+}
+
+// Write to `path`, going through a temporary file so a crash can't leave half a file.
+pub async fn write(path: &Path, buf: Vec<u8>) -> Result<(), PersistError> {
+    let tmp = path.with_extension("tmp");
+    tokio::fs::write(&tmp, buf)
+        .await
+        .map_err(|e| PersistError::Io(tmp.clone(), e))?;
+    tokio::fs::rename(&tmp, path)
+        .await
+        .map_err(|e| PersistError::Io(path.into(), e))
+}
@@ -0,0 +1,121 @@
+// Who may challenge a user.
+
+use crate::i18n::{tr, Lang};
+use poise::serenity_prelude as serenity;
+use serenity::{Mentionable, RoleId, UserId};
+use std::collections::BTreeSet;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, poise::ChoiceParameter)]
+pub enum ChallengeFrom {
+    #[default]
+    #[name = "Everyone"]
+    Everyone,
+    #[name = "Friends only"]
+    Friends,
+    #[name = "Friends and members of a role"]
+    Role,
+}
+
+// Everyone may challenge a user unless they say otherwise.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PrivacyPrefs {
+    pub blocked: BTreeSet<UserId>, // never, not even through matchmaking
+    pub friends: BTreeSet<UserId>, // always, unless not disturbed
+    pub dnd: bool, // nobody, but matchmaking still pairs them
+    pub from: ChallengeFrom,
+    pub role: Option<RoleId>, // for ChallengeFrom::Role
+}
+
+impl PrivacyPrefs {
+    // May `challenger`, with `roles` where the challenge is made, challenge this user?
+    // Paired players asked for a game, only blocking keeps them apart.
+    pub fn allows(&self, challenger: UserId, roles: &[RoleId], matched: bool) -> bool {
+        if self.blocked.contains(&challenger) {
+            return false;
+        }
+        if matched {
+            return true;
+        }
+        if self.dnd {
+            return false;
+        }
+        if self.friends.contains(&challenger) {
+            return true;
+        }
+        match self.from {
+            ChallengeFrom::Everyone => true,
+            ChallengeFrom::Friends => false,
+            ChallengeFrom::Role => self.role.is_some_and(|r| roles.contains(&r)),
+        }
+    }
+
+    pub fn block(&mut self, user: UserId, blocked: bool) {
+        if blocked {
+            self.friends.remove(&user);
+            self.blocked.insert(user);
+        } else {
+            self.blocked.remove(&user);
+        }
+    }
+
+    pub fn befriend(&mut self, user: UserId, friend: bool) {
+        if friend {
+            self.blocked.remove(&user);
+            self.friends.insert(user);
+        } else {
+            self.friends.remove(&user);
+        }
+    }
+
+    pub fn render(&self, lang: Lang) -> String {
+        let list = |users: &BTreeSet<UserId>| match users.is_empty() {
+            true => tr!(lang, "privacy.nobody"),
+            false => users.iter().map(|u| u.mention().to_string()).collect::<Vec<_>>().join(", "),
+        };
+        let from = match (self.from, self.role) {
+            (ChallengeFrom::Everyone, _) => tr!(lang, "privacy.from_everyone"),
+            (ChallengeFrom::Friends, _) | (ChallengeFrom::Role, None) => tr!(lang, "privacy.from_friends"),
+            (ChallengeFrom::Role, Some(role)) => tr!(lang, "privacy.from_role", role = role.mention()),
+        };
+        [
+            tr!(lang, "privacy.dnd", value = tr!(lang, if self.dnd {"notify.on"} else {"notify.off"})),
+            tr!(lang, "privacy.from", value = from),
+            tr!(lang, "privacy.friends", value = list(&self.friends)),
+            tr!(lang, "privacy.blocked", value = list(&self.blocked)),
+        ].join("\n")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_allows() {
+        let (friend, stranger, pest) = (UserId(1), UserId(2), UserId(3));
+        let mut prefs = PrivacyPrefs::default();
+        assert!(prefs.allows(stranger, &[], false));
+        prefs.befriend(friend, true);
+        prefs.block(pest, true);
+        assert!(!prefs.allows(pest, &[], false) && !prefs.allows(pest, &[], true));
+
+        prefs.from = ChallengeFrom::Friends;
+        assert!(prefs.allows(friend, &[], false));
+        assert!(!prefs.allows(stranger, &[RoleId(7)], false));
+        prefs.from = ChallengeFrom::Role;
+        assert!(!prefs.allows(stranger, &[RoleId(7)], false));
+        prefs.role = Some(RoleId(7));
+        assert!(prefs.allows(stranger, &[RoleId(7)], false));
+        assert!(!prefs.allows(stranger, &[RoleId(8)], false));
+
+        prefs.dnd = true;
+        assert!(!prefs.allows(friend, &[], false));
+        assert!(prefs.allows(stranger, &[], true));
+
+        // Blocking a friend unfriends them, and the other way round
+        prefs.block(friend, true);
+        assert!(!prefs.friends.contains(&friend));
+        prefs.befriend(friend, true);
+        assert!(!prefs.blocked.contains(&friend));
+    }
+}
@@ -0,0 +1,145 @@
+use poise::serenity_prelude as serenity;
+use serenity::{ChannelId, GuildId, UserId};
+use tokio::sync::Mutex as TokioMutex;
+use std::collections::HashMap;
+use std::time::SystemTime;
+use crate::config::Matchmaking;
+use crate::game::GameVariant;
+
+// Someone waiting for an opponent.
+#[derive(Debug, Clone)]
+pub struct QueueEntry {
+    pub id: UserId,
+    pub rating: u64,
+    pub since: SystemTime,
+    pub channel: ChannelId, // where to tell them about the match
+    pub guild: Option<GuildId>,
+}
+
+impl QueueEntry {
+    // Largest rating difference this player takes, growing the longer they wait.
+    fn window(&self, now: SystemTime, settings: &Matchmaking) -> u64 {
+        let waited = now.duration_since(self.since).unwrap_or_default().as_secs();
+        settings.window_base + waited * settings.window_widening / 60
+    }
+}
+
+// Players are only paired within a variant and word length.
+pub type PoolKey = (GameVariant, usize);
+
+pub struct MatchQueue {
+    pools: TokioMutex<HashMap<PoolKey, Vec<QueueEntry>>>,
+    settings: Matchmaking,
+}
+
+impl MatchQueue {
+    pub fn new(settings: Matchmaking) -> MatchQueue {
+        MatchQueue {
+            pools: TokioMutex::new(HashMap::new()),
+            settings,
+        }
+    }
+
+    // False if the player waits in some pool already.
+    pub async fn join(&self, key: PoolKey, entry: QueueEntry) -> bool {
+        let mut pools = self.pools.lock().await;
+        if pools.values().flatten().any(|e| e.id == entry.id) {
+            return false;
+        }
+        pools.entry(key).or_default().push(entry);
+        true
+    }
+
+    pub async fn leave(&self, id: UserId) -> bool {
+        let mut found = false;
+        for pool in self.pools.lock().await.values_mut() {
+            pool.retain(|e| e.id != id || {
+                found = true;
+                false
+            });
+        }
+        found
+    }
+
+    pub async fn count(&self) -> usize {
+        self.pools.lock().await.values().map(Vec::len).sum()
+    }
+
+    // Take out every pair that can play now, and everyone who waited too long.
+    // Neighbours by rating are paired if either of them is willing to.
+    pub async fn pair(&self, now: SystemTime) -> (Vec<(PoolKey, QueueEntry, QueueEntry)>, Vec<QueueEntry>) {
+        let mut pairs = Vec::new();
+        let mut expired = Vec::new();
+        let mut pools = self.pools.lock().await;
+        for (key, pool) in pools.iter_mut() {
+            pool.retain(|e| e.since + self.settings.queue_expiry > now || {
+                expired.push(e.clone());
+                false
+            });
+            pool.sort_by_key(|e| e.rating);
+
+            let mut left = Vec::new();
+            let mut entries = std::mem::take(pool).into_iter().peekable();
+            while let Some(a) = entries.next() {
+                let fits = entries.peek().is_some_and(|b| {
+                    b.rating - a.rating <= a.window(now, &self.settings).max(b.window(now, &self.settings))
+                });
+                if fits {
+                    pairs.push((*key, a, entries.next().unwrap()));
+                } else {
+                    left.push(a);
+                }
+            }
+            *pool = left;
+        }
+        pools.retain(|_, pool| !pool.is_empty());
+        (pairs, expired)
+    }
+}
+
+impl Default for MatchQueue {
+    fn default() -> Self {
+        Self::new(Matchmaking::default())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::time::Duration;
+
+    fn entry(id: u64, rating: u64, since: SystemTime) -> QueueEntry {
+        QueueEntry { id: UserId(id), rating, since, channel: ChannelId(1), guild: None }
+    }
+
+    #[tokio::test]
+    async fn test_pairing() {
+        let queue = MatchQueue::default();
+        let start = SystemTime::now();
+        let timed = (GameVariant::Timed, 5);
+        assert!(queue.join(timed, entry(1, 0, start)).await);
+        assert!(queue.join(timed, entry(2, 500, start)).await);
+        assert!(queue.join(timed, entry(3, 10, start)).await);
+        // Same rating, but another pool
+        assert!(queue.join((GameVariant::TurnBased, 5), entry(4, 500, start)).await);
+        assert!(!queue.join((GameVariant::Timed, 6), entry(4, 500, start)).await);
+
+        let (pairs, expired) = queue.pair(start).await;
+        assert!(expired.is_empty());
+        assert_eq!(pairs.len(), 1);
+        assert_eq!((pairs[0].1.id, pairs[0].2.id), (UserId(1), UserId(3)));
+
+        // The window widens until the far apart ones meet
+        assert!(queue.join(timed, entry(5, 0, start)).await);
+        assert!(queue.pair(start + Duration::from_secs(60)).await.0.is_empty());
+        let (pairs, _) = queue.pair(start + Duration::from_secs(600)).await;
+        assert_eq!((pairs[0].1.id, pairs[0].2.id), (UserId(5), UserId(2)));
+
+        assert!(queue.leave(UserId(4)).await);
+        assert!(!queue.leave(UserId(4)).await);
+        assert!(queue.join(timed, entry(6, 0, start)).await);
+        let (pairs, expired) = queue.pair(start + Matchmaking::default().queue_expiry).await;
+        assert!(pairs.is_empty());
+        assert_eq!(expired[0].id, UserId(6));
+    }
+}
@@ -30,10 +30,19 @@ impl ScoreManager {
         // Complexity's pretty weak, but it works
         let mut res = guard.iter().map(|(k, v)| (*k, *v))
             .collect::<Vec<_>>();
-        res.sort_by(|a, b| b.cmp(a));
+        // Highest scores first, ties by user ID
+        res.sort_by_key(|&(user, score)| (std::cmp::Reverse(score), user));
         res.iter().take(count).copied().collect()
     }
 
+    // A player's place on the leaderboard as list_top orders it, from 1, and their score.
+    pub async fn standing(&self, player: UserId) -> Option<(usize, u64)> {
+        let guard = self.scores.read().await;
+        let score = *guard.get(&player)?;
+        let ahead = guard.iter().filter(|&(&u, &s)| s > score || (s == score && u < player)).count();
+        Some((ahead + 1, score))
+    }
+
     pub async fn add(&self, player: UserId, score: u64) {
         self.with_write(|guard| {
             *guard.entry(player).or_default() += score;
@@ -42,15 +51,37 @@ impl ScoreManager {
     
     pub async fn add_from_game(&self, game: &game::GameMP) {
         self.with_write(|guard| {
-            let score = game.get_score();
-            for i in 0..=1 {
-                *guard.entry(game.get_user_id(i)).or_default() += score[i];
+            for (i, score) in game.get_score().iter().enumerate() {
+                *guard.entry(game.get_user_id(i)).or_default() += score;
             }
         }).await;
     }
 
+    // Add to or take from a player's score, which stays at least 0. Returns the new score.
+    pub async fn adjust(&self, player: UserId, change: i64) -> u64 {
+        let mut guard = self.scores.write().await;
+        let score = guard.entry(player).or_default();
+        *score = score.saturating_add_signed(change);
+        *score
+    }
+
+    pub async fn set(&self, player: UserId, score: u64) {
+        self.scores.write().await.insert(player, score);
+    }
+
+    // Drop a player from the scores altogether. Returns what they had.
+    pub async fn reset(&self, player: UserId) -> Option<u64> {
+        self.scores.write().await.remove(&player)
+    }
+
     pub async fn get(&self, player: UserId) -> Option<u64> {
         let guard = self.scores.read().await;
         guard.get(&player).copied()
     }
-}
\ No newline at end of file
+}
+
+impl Default for ScoreManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,135 @@
+use std::cmp::Ordering;
+use std::ops::Range;
+
+// All dictionary words of a single (byte) length, packed back to back.
+// Words are sorted and unique, so lookups are binary searches over fixed-width slots.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Bucket {
+    width: usize,
+    words: Vec<u8>,
+}
+
+impl Bucket {
+    // Build from arbitrary words of length `width`. Sorts and deduplicates.
+    pub fn from_words(width: usize, mut words: Vec<String>) -> Bucket {
+        words.sort_unstable();
+        words.dedup();
+        let mut packed = Vec::with_capacity(width * words.len());
+        for w in words {
+            debug_assert_eq!(w.len(), width);
+            packed.extend_from_slice(w.as_bytes());
+        }
+        Bucket { width, words: packed }
+    }
+
+    // Build from already packed data, checking that it's well-formed.
+    pub fn from_packed(width: usize, words: Vec<u8>) -> Result<Bucket, String> {
+        if width == 0 || !words.len().is_multiple_of(width) {
+            return Err(format!("bucket of width {} has {} bytes", width, words.len()));
+        }
+        let bucket = Bucket { width, words };
+        let mut prev: Option<&[u8]> = None;
+        for w in bucket.words.chunks_exact(width) {
+            if std::str::from_utf8(w).is_err() {
+                return Err(format!("non-UTF-8 word in bucket of width {}", width));
+            }
+            if prev.is_some_and(|p| p >= w) {
+                return Err(format!("bucket of width {} is not sorted", width));
+            }
+            prev = Some(w);
+        }
+        Ok(bucket)
+    }
+
+    #[inline]
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    #[inline]
+    pub fn count(&self) -> usize {
+        self.words.len() / self.width
+    }
+
+    pub fn packed(&self) -> &[u8] {
+        &self.words
+    }
+
+    #[inline]
+    fn bytes_at(&self, index: usize) -> &[u8] {
+        &self.words[index * self.width..(index + 1) * self.width]
+    }
+
+    pub fn get(&self, index: usize) -> Option<&str> {
+        if index >= self.count() {
+            return None;
+        }
+        // Validated on construction.
+        std::str::from_utf8(self.bytes_at(index)).ok()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &str> {
+        self.words
+            .chunks_exact(self.width)
+            .filter_map(|w| std::str::from_utf8(w).ok())
+    }
+
+    // Index of the first word for which `pred` is false, assuming it's true for a prefix of words.
+    fn partition_point<P: Fn(&[u8]) -> bool>(&self, pred: P) -> usize {
+        let (mut lo, mut hi) = (0, self.count());
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            if pred(self.bytes_at(mid)) {
+                lo = mid + 1;
+            } else {
+                hi = mid;
+            }
+        }
+        lo
+    }
+
+    pub fn contains(&self, word: &str) -> bool {
+        let word = word.as_bytes();
+        if word.len() != self.width {
+            return false;
+        }
+        let index = self.partition_point(|w| w < word);
+        index < self.count() && self.bytes_at(index) == word
+    }
+
+    // Range of indices of words starting with `prefix`.
+    pub fn prefix_range(&self, prefix: &str) -> Range<usize> {
+        let prefix = prefix.as_bytes();
+        if prefix.len() > self.width {
+            return 0..0;
+        }
+        let start = self.partition_point(|w| w < prefix);
+        let end = self.partition_point(|w| w[..prefix.len()].cmp(prefix) != Ordering::Greater);
+        start..end
+    }
+
+    pub fn with_prefix<'a>(&'a self, prefix: &str) -> impl Iterator<Item = &'a str> {
+        self.prefix_range(prefix).filter_map(move |i| self.get(i))
+    }
+
+    // Words matching a pattern of the same length, where `wildcard` matches any letter.
+    pub fn matching<'a>(&'a self, pattern: &'a str, wildcard: u8) -> impl Iterator<Item = &'a str> {
+        let pattern = pattern.as_bytes();
+        // Only the part up to the first wildcard can be binary searched.
+        let fixed = pattern.iter().position(|&c| c == wildcard).unwrap_or(pattern.len());
+        let range = if pattern.len() == self.width {
+            // Safe to slice, `fixed` is at a byte that's plain ASCII or the end.
+            self.prefix_range(std::str::from_utf8(&pattern[..fixed]).unwrap_or(""))
+        } else {
+            0..0
+        };
+        range.filter_map(move |i| {
+            let w = self.bytes_at(i);
+            w.iter()
+                .zip(pattern)
+                .all(|(&c, &p)| p == wildcard || c == p)
+                .then(|| self.get(i))
+                .flatten()
+        })
+    }
+}
@@ -0,0 +1,57 @@
+// Binary dictionary cache.
+// Layout (integers little-endian):
+//   magic (8 bytes)
+//   bucket count: u32
+//   per bucket: word width: u32, word count: u32, width * count bytes of packed words
+use super::bucket::Bucket;
+use super::Dictionary;
+
+const MAGIC: &[u8; 8] = b"WCDICT\x00\x01";
+
+pub fn encode(dict: &Dictionary) -> Vec<u8> {
+    let buckets: Vec<&Bucket> = dict.buckets().collect();
+    let size: usize = buckets.iter().map(|b| 8 + b.packed().len()).sum();
+    let mut out = Vec::with_capacity(MAGIC.len() + 4 + size);
+    out.extend_from_slice(MAGIC);
+    out.extend_from_slice(&(buckets.len() as u32).to_le_bytes());
+    for b in buckets {
+        out.extend_from_slice(&(b.width() as u32).to_le_bytes());
+        out.extend_from_slice(&(b.count() as u32).to_le_bytes());
+        out.extend_from_slice(b.packed());
+    }
+    out
+}
+
+fn take<'a>(src: &mut &'a [u8], n: usize) -> Result<&'a [u8], String> {
+    if src.len() < n {
+        return Err("unexpected end of data".to_string());
+    }
+    let (head, tail) = src.split_at(n);
+    *src = tail;
+    Ok(head)
+}
+
+fn take_u32(src: &mut &[u8]) -> Result<usize, String> {
+    let bytes = take(src, 4)?;
+    Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize)
+}
+
+pub fn decode(mut src: &[u8]) -> Result<Dictionary, String> {
+    if take(&mut src, MAGIC.len())? != MAGIC {
+        return Err("not a dictionary cache".to_string());
+    }
+    let bucket_count = take_u32(&mut src)?;
+    // Each bucket header takes 8 bytes, don't let a corrupt count reserve more than that.
+    let mut buckets = Vec::with_capacity(bucket_count.min(src.len() / 8));
+    for _ in 0..bucket_count {
+        let width = take_u32(&mut src)?;
+        let count = take_u32(&mut src)?;
+        let size = width.checked_mul(count).ok_or("bucket size overflow")?;
+        let packed = take(&mut src, size)?.to_vec();
+        buckets.push(Bucket::from_packed(width, packed)?);
+    }
+    if !src.is_empty() {
+        return Err("trailing data".to_string());
+    }
+    Ok(Dictionary::from_buckets(buckets))
+}
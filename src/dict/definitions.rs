@@ -0,0 +1,157 @@
+// Offline word definitions, loaded from a WordNet-style JSON file:
+// { "word": [ { "pos": "noun", "definition": "...", "examples": ["..."] }, ... ], ... }
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::{fs, io};
+use super::DictError;
+use crate::data::persist;
+
+pub const DEFS_VARNAME: &str = "WORDCLASH_DEFINITIONS";
+pub const DEFS_FILENAME: &str = "definitions.json";
+// How many senses of a word to show at most.
+const MAX_SENSES: usize = 3;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Sense {
+    pub pos: String,
+    pub definition: String,
+    #[serde(default)]
+    pub examples: Vec<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct Definitions {
+    entries: HashMap<String, Vec<Sense>>,
+}
+
+// Base forms to try for inflected words, WordNet only lists lemmas.
+fn candidate_lemmas(word: &str) -> Vec<String> {
+    let mut out = vec![word.to_string()];
+    let mut strip = |suffix: &str, replacement: &str| {
+        if let Some(stem) = word.strip_suffix(suffix) {
+            if stem.len() >= 2 {
+                out.push(format!("{}{}", stem, replacement));
+            }
+        }
+    };
+    strip("ies", "y");
+    strip("es", "");
+    strip("s", "");
+    strip("ied", "y");
+    strip("ed", "");
+    strip("ed", "e");
+    strip("ing", "");
+    strip("ing", "e");
+    out
+}
+
+impl Definitions {
+    pub fn new(entries: HashMap<String, Vec<Sense>>) -> Definitions {
+        Definitions { entries }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    // Find senses of a word, falling back to its base form.
+    // Returns the matched headword too.
+    pub fn lookup(&self, word: &str) -> Option<(String, &[Sense])> {
+        let word = word.to_lowercase();
+        candidate_lemmas(&word)
+            .into_iter()
+            .find_map(|w| self.entries.get(&w).map(|s| (w, s.as_slice())))
+            .filter(|(_, s)| !s.is_empty())
+    }
+
+    // Human readable description of a word, cut to at most `limit` bytes.
+    pub fn describe(&self, word: &str, limit: usize) -> String {
+        let (headword, senses) = match self.lookup(word) {
+            Some(found) => found,
+            None => return "No definition available".to_string(),
+        };
+        let mut out = String::new();
+        if headword != word.to_lowercase() {
+            out.push_str(&format!("(see *{}*)\n", headword));
+        }
+        for sense in senses.iter().take(MAX_SENSES) {
+            let mut line = format!("*{}* \u{2014} {}\n", sense.pos, sense.definition);
+            if let Some(example) = sense.examples.first() {
+                line.push_str(&format!("> {}\n", example));
+            }
+            if out.len() + line.len() > limit {
+                break;
+            }
+            out.push_str(&line);
+        }
+        if out.is_empty() {
+            // Even the first sense is too long, cut it.
+            let first = &senses[0].definition;
+            let mut end = limit.min(first.len());
+            while !first.is_char_boundary(end) {
+                end -= 1;
+            }
+            out.push_str(&first[..end]);
+        }
+        out.trim_end().to_string()
+    }
+}
+
+// Get definitions path from environment variables or executable path.
+pub fn get_defs_path() -> Result<PathBuf, DictError> {
+    persist::data_path(DEFS_VARNAME, DEFS_FILENAME).map_err(DictError::NoPath)
+}
+
+// Load definitions. They're optional, so a missing file gives an empty database.
+pub fn load_definitions_from(path: &Path) -> Result<Definitions, DictError> {
+    let buf = match fs::read(path) {
+        Ok(buf) => buf,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Definitions::default()),
+        Err(e) => return Err(DictError::Io(path.into(), e)),
+    };
+    serde_json::from_slice(&buf)
+        .map(Definitions::new)
+        .map_err(|e| DictError::Parse(path.into(), e))
+}
+
+pub fn load_definitions() -> Result<Definitions, DictError> {
+    load_definitions_from(&get_defs_path()?)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn get_defs() -> Definitions {
+        let json = r#"{
+            "tear": [
+                {"pos": "noun", "definition": "a drop of clear salty liquid", "examples": ["her eyes filled with tears"]},
+                {"pos": "verb", "definition": "separate or cause to separate abruptly"}
+            ],
+            "slate": [{"pos": "noun", "definition": "a fine-grained metamorphic rock"}]
+        }"#;
+        Definitions::new(serde_json::from_str(json).unwrap())
+    }
+
+    #[test]
+    fn test_lookup() {
+        let defs = get_defs();
+        assert_eq!(defs.lookup("slate").unwrap().1.len(), 1);
+        assert_eq!(defs.lookup("tears").unwrap().0, "tear");
+        assert_eq!(defs.lookup("slated").unwrap().0, "slate");
+        assert!(defs.lookup("rover").is_none());
+    }
+
+    #[test]
+    fn test_describe() {
+        let defs = get_defs();
+        let text = defs.describe("tears", 1024);
+        assert!(text.starts_with("(see *tear*)"));
+        assert!(!defs.describe("Slate", 1024).contains("(see"));
+        assert!(text.contains("*verb*"));
+        assert!(text.contains("> her eyes"));
+        assert!(defs.describe("tear", 40).len() <= 40);
+        assert_eq!(defs.describe("rover", 1024), "No definition available");
+    }
+}
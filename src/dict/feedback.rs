@@ -0,0 +1,305 @@
+// Fast guess feedback for solvers and analysis.
+// Words are fixed-size letter arrays and feedback is a base-3 pattern ID:
+// digit i (least significant first) is the MatchLetter value at position i.
+use super::bucket::Bucket;
+use super::wordmatch::MatchLetter;
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+// Longest word the engine handles. 3^16 still fits a PatternId.
+pub const MAX_LEN: usize = 16;
+// Above this length, pattern counts go to a map instead of a dense array,
+// and patterns no longer fit the u16 entries of a PatternTable.
+const DENSE_LEN: usize = 10;
+// Largest pattern table, 8 MB of entries, and most guesses one covers.
+const TABLE_ENTRIES: usize = 4 << 20;
+const TABLE_GUESSES: usize = 1000;
+
+pub type PatternId = u32;
+
+// Lowercase ASCII word, letters stored as 0..26.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Word {
+    letters: [u8; MAX_LEN],
+    len: u8,
+}
+
+impl Word {
+    // None if the word is too long or has anything but lowercase ASCII letters.
+    pub fn new(s: &str) -> Option<Word> {
+        if s.len() > MAX_LEN {
+            return None;
+        }
+        let mut letters = [0u8; MAX_LEN];
+        for (slot, c) in letters.iter_mut().zip(s.bytes()) {
+            if !c.is_ascii_lowercase() {
+                return None;
+            }
+            *slot = c - b'a';
+        }
+        Some(Word { letters, len: s.len() as u8 })
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len as usize
+    }
+}
+
+impl std::fmt::Display for Word {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        for &c in &self.letters[..self.len()] {
+            write!(f, "{}", (c + b'a') as char)?;
+        }
+        Ok(())
+    }
+}
+
+// Number of distinct patterns for words of this length.
+pub fn pattern_count(len: usize) -> usize {
+    3usize.pow(len as u32)
+}
+
+// Feedback for `guess` against `answer`, same rules as wordmatch::match_word.
+// Both words must have the same length.
+#[inline]
+pub fn pattern(answer: &Word, guess: &Word) -> PatternId {
+    debug_assert_eq!(answer.len, guess.len);
+    let len = answer.len();
+    let mut unmatched = [0u8; 26];
+    let mut exact: u32 = 0;
+    for i in 0..len {
+        if answer.letters[i] == guess.letters[i] {
+            exact |= 1 << i;
+        } else {
+            unmatched[answer.letters[i] as usize] += 1;
+        }
+    }
+    let mut id: PatternId = 0;
+    let mut place: PatternId = 1;
+    for i in 0..len {
+        if exact & (1 << i) != 0 {
+            id += place * MatchLetter::Exact as PatternId;
+        } else {
+            let slot = &mut unmatched[guess.letters[i] as usize];
+            if *slot > 0 {
+                *slot -= 1;
+                id += place * MatchLetter::Close as PatternId;
+            }
+        }
+        place *= 3;
+    }
+    id
+}
+
+pub fn decode(mut id: PatternId, len: usize) -> Vec<MatchLetter> {
+    (0..len)
+        .map(|_| {
+            let digit = id % 3;
+            id /= 3;
+            match digit {
+                2 => MatchLetter::Exact,
+                1 => MatchLetter::Close,
+                _ => MatchLetter::Null,
+            }
+        })
+        .collect()
+}
+
+// Encode every word of a dictionary bucket, skipping the ones the engine can't handle.
+// Buckets are sorted, and so is the result.
+pub fn encode_bucket(bucket: &Bucket) -> Vec<Word> {
+    bucket.iter().filter_map(Word::new).collect()
+}
+
+// Batch API: feedback of one guess against every answer.
+pub fn patterns_for_guess(guess: &Word, answers: &[Word], out: &mut Vec<PatternId>) {
+    out.clear();
+    out.extend(answers.iter().map(|a| pattern(a, guess)));
+}
+
+// How many answers give each pattern, as (pattern, count) pairs.
+pub fn partition(guess: &Word, answers: &[Word]) -> Vec<(PatternId, usize)> {
+    if guess.len() <= DENSE_LEN {
+        let mut counts = vec![0usize; pattern_count(guess.len())];
+        for a in answers {
+            counts[pattern(a, guess) as usize] += 1;
+        }
+        counts
+            .into_iter()
+            .enumerate()
+            .filter(|(_, n)| *n > 0)
+            .map(|(p, n)| (p as PatternId, n))
+            .collect()
+    } else {
+        let mut counts: HashMap<PatternId, usize> = HashMap::new();
+        for a in answers {
+            *counts.entry(pattern(a, guess)).or_default() += 1;
+        }
+        counts.into_iter().collect()
+    }
+}
+
+// Expected information in bits of playing `guess`, all answers being equally likely.
+pub fn entropy(guess: &Word, answers: &[Word]) -> f64 {
+    let total = answers.len() as f64;
+    partition(guess, answers)
+        .into_iter()
+        .map(|(_, n)| {
+            let p = n as f64 / total;
+            -p * p.log2()
+        })
+        .sum()
+}
+
+// Up to `n` words, evenly spread over `words`.
+pub fn stride_sample(words: &[Word], n: usize) -> Vec<Word> {
+    if words.len() <= n {
+        return words.to_vec();
+    }
+    (0..n).map(|i| words[i * words.len() / n]).collect()
+}
+
+// Feedback of a fixed sample of guesses against every word of a bucket, worked out once.
+// Rows are guesses, columns answers, both sorted.
+pub struct PatternTable {
+    guesses: Vec<Word>,
+    answers: Vec<Word>,
+    ids: Vec<u16>,
+}
+
+impl PatternTable {
+    // None for words too long for the table, or no words at all.
+    pub fn build(answers: Vec<Word>) -> Option<PatternTable> {
+        let len = answers.first()?.len();
+        if len > DENSE_LEN {
+            return None;
+        }
+        let count = (TABLE_ENTRIES / answers.len()).min(TABLE_GUESSES);
+        if count == 0 {
+            return None;
+        }
+        let guesses = stride_sample(&answers, count);
+        let mut ids = Vec::with_capacity(guesses.len() * answers.len());
+        for g in &guesses {
+            ids.extend(answers.iter().map(|a| pattern(a, g) as u16));
+        }
+        Some(PatternTable { guesses, answers, ids })
+    }
+
+    pub fn guesses(&self) -> &[Word] {
+        &self.guesses
+    }
+
+    pub fn answer_index(&self, answer: &Word) -> Option<usize> {
+        self.answers.binary_search(answer).ok()
+    }
+
+    // Feedback of the guess in row `guess` against the answer in column `answer`.
+    #[inline]
+    pub fn get(&self, guess: usize, answer: usize) -> PatternId {
+        self.ids[guess * self.answers.len() + answer] as PatternId
+    }
+
+    // Like `entropy`, for a row of the table against the answers in columns `answers`.
+    pub fn entropy(&self, guess: usize, answers: &[usize]) -> f64 {
+        let mut counts = vec![0usize; pattern_count(self.guesses[guess].len())];
+        for &a in answers {
+            counts[self.get(guess, a) as usize] += 1;
+        }
+        let total = answers.len() as f64;
+        counts.into_iter()
+            .filter(|&n| n > 0)
+            .map(|n| {
+                let p = n as f64 / total;
+                -p * p.log2()
+            })
+            .sum()
+    }
+}
+
+// A pattern table for each word length, built the first time it's asked for.
+#[derive(Default)]
+pub struct PatternTables {
+    tables: [OnceLock<Option<PatternTable>>; MAX_LEN + 1],
+}
+
+impl PatternTables {
+    pub fn get(&self, bucket: &Bucket) -> Option<&PatternTable> {
+        self.tables.get(bucket.width())?
+            .get_or_init(|| PatternTable::build(encode_bucket(bucket)))
+            .as_ref()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::dict::wordmatch::{match_word, match_word_chars};
+    use MatchLetter::*;
+    use rand::prelude::*;
+
+    fn w(s: &str) -> Word {
+        Word::new(s).unwrap()
+    }
+
+    #[test]
+    fn test_encoding() {
+        assert_eq!(decode(pattern(&w("slide"), &w("lease")), 5), vec![Close, Null, Null, Close, Exact]);
+        assert_eq!(decode(pattern(&w("slide"), &w("tower")), 5), vec![Null, Null, Null, Close, Null]);
+        assert_eq!(pattern(&w("slide"), &w("slide")) as usize, pattern_count(5) - 1);
+        assert!(Word::new("Slide").is_none());
+        assert_eq!(w("slide").to_string(), "slide");
+    }
+
+    #[test]
+    fn test_matches_reference() {
+        // Small alphabet, so repeated letters come up a lot.
+        let mut rng = StdRng::seed_from_u64(3);
+        let mut random_word = |len: usize| -> String {
+            (0..len).map(|_| (b'a' + rng.gen_range(0..4)) as char).collect()
+        };
+        for len in 1..=8 {
+            for _ in 0..500 {
+                let (a, g) = (random_word(len), random_word(len));
+                assert_eq!(decode(pattern(&w(&a), &w(&g)), len), match_word_chars(&a, &g), "{} {}", a, g);
+                assert_eq!(match_word(&a, &g), match_word_chars(&a, &g));
+            }
+        }
+    }
+
+    #[test]
+    fn test_batch() {
+        let answers: Vec<Word> = ["slate", "crane", "brine", "shine"].iter().map(|s| w(s)).collect();
+        let guesses: Vec<Word> = ["brine", "xxxxx"].iter().map(|s| w(s)).collect();
+        let mut row = Vec::new();
+        patterns_for_guess(&guesses[0], &answers, &mut row);
+        assert_eq!(row.len(), answers.len());
+        assert_eq!(row[2] as usize, pattern_count(5) - 1);
+        for (a, p) in answers.iter().zip(&row) {
+            assert_eq!(*p, pattern(a, &guesses[0]));
+        }
+
+        assert_eq!(partition(&guesses[1], &answers), vec![(0, 4)]);
+        assert_eq!(entropy(&guesses[1], &answers), 0.0);
+        // slate gives ____E, crane _E_EE, brine EEEEE and shine __EEE
+        assert_eq!(partition(&guesses[0], &answers).len(), 4);
+        assert!((entropy(&guesses[0], &answers) - 2.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_table() {
+        let answers: Vec<Word> = ["brine", "crane", "shine", "slate", "spine"].iter().map(|s| w(s)).collect();
+        let table = PatternTable::build(answers.clone()).unwrap();
+        assert_eq!(table.guesses(), &answers[..]);
+        let columns: Vec<usize> = answers.iter().map(|a| table.answer_index(a).unwrap()).collect();
+        for (row, g) in table.guesses().iter().enumerate() {
+            for (a, &col) in answers.iter().zip(&columns) {
+                assert_eq!(table.get(row, col), pattern(a, g));
+            }
+            assert!((table.entropy(row, &columns) - entropy(g, &answers)).abs() < 1e-9);
+        }
+        assert!(table.answer_index(&w("tower")).is_none());
+        assert!(PatternTable::build(vec![w("abcdefghijk")]).is_none());
+    }
+}
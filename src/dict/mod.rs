@@ -1,81 +1,232 @@
-use std::collections::HashMap;
+use std::collections::BTreeMap;
+use std::{env, fmt, error, fs, io};
+use std::path::{Path, PathBuf};
 use rand::prelude::*;
 use indexmap::IndexSet;
-use std::env;
-use std::path::{Path, PathBuf};
+use crate::data::persist;
 
 pub const DICT_VARNAME: &str = "WORDCLASH_DICTIONARY";
 pub const DICT_FILENAME: &str = "dictionary.json";
+pub const CACHE_VARNAME: &str = "WORDCLASH_DICTIONARY_CACHE";
+pub const CACHE_EXTENSION: &str = "bin";
+pub const WILDCARD: char = '?';
 
 pub type DictSet = IndexSet<String>;
 
+// Words bucketed by length, see `bucket::Bucket`.
 pub struct Dictionary {
-    data: HashMap<usize, DictSet>,
+    data: BTreeMap<usize, Bucket>,
+    patterns: feedback::PatternTables, // filled in as analyses need them
 }
 
 impl Dictionary {
     pub fn new(src: DictSet) -> Dictionary {
-        let mut lengthmap: HashMap<usize, Vec<String>> = HashMap::new();
-        for s in src.into_iter() {
+        let mut lengthmap: BTreeMap<usize, Vec<String>> = BTreeMap::new();
+        for s in src.into_iter().filter(|s| !s.is_empty()) {
             lengthmap.entry(s.len()).or_default().push(s);
         }
 
         Dictionary {
             data: lengthmap
                 .into_iter()
-                .map(|(k, v)| (k, v.into_iter().collect()))
-                .collect()
+                .map(|(k, v)| (k, Bucket::from_words(k, v)))
+                .collect(),
+            patterns: Default::default(),
+        }
+    }
+
+    pub fn from_buckets(buckets: Vec<Bucket>) -> Dictionary {
+        Dictionary {
+            data: buckets.into_iter().map(|b| (b.width(), b)).collect(),
+            patterns: Default::default(),
         }
     }
 
+    pub fn buckets(&self) -> impl Iterator<Item = &Bucket> {
+        self.data.values()
+    }
+
+    pub fn bucket(&self, len: usize) -> Option<&Bucket> {
+        self.data.get(&len)
+    }
+
+    // Cached feedback of words of this length, see feedback::PatternTable.
+    pub fn patterns(&self, len: usize) -> Option<&feedback::PatternTable> {
+        self.patterns.get(self.bucket(len)?)
+    }
+
     pub fn contains(&self, word: &str) -> bool {
         self.data
             .get(&word.len())
-            .map_or(false, |set| set.contains(word))
-    }
-    
-    pub fn random_with_len(&self, len: usize) -> Option<&String> {
-        let set = match self.data.get(&len) {
-            Some(s) => s, None => return None
-        };
-        let index = rand::distributions::Uniform::new(0, set.len())
+            .is_some_and(|set| set.contains(word))
+    }
+
+    pub fn random_with_len(&self, len: usize) -> Option<&str> {
+        let set = self.data.get(&len)?;
+        if set.count() == 0 {
+            return None;
+        }
+        let index = rand::distributions::Uniform::new(0, set.count())
             .sample(&mut thread_rng());
-        set.get_index(index)
+        set.get(index)
+    }
+
+    // Word count for every length present.
+    pub fn counts(&self) -> Vec<(usize, usize)> {
+        self.data.iter().map(|(k, b)| (*k, b.count())).collect()
+    }
+
+    pub fn with_prefix<'a>(&'a self, prefix: &'a str, len: usize) -> impl Iterator<Item = &'a str> {
+        self.data.get(&len).into_iter().flat_map(move |b| b.with_prefix(prefix))
+    }
+
+    // Words matching a pattern like `s?a?e`, with WILDCARD standing for any letter.
+    pub fn matching<'a>(&'a self, pattern: &'a str) -> impl Iterator<Item = &'a str> {
+        self.data
+            .get(&pattern.len())
+            .into_iter()
+            .flat_map(move |b| b.matching(pattern, WILDCARD as u8))
+    }
+
+    // Words made of exactly the same letters, including the word itself if present.
+    pub fn anagrams<'a>(&'a self, word: &str) -> impl Iterator<Item = &'a str> {
+        let counts = letter_counts(word);
+        self.data
+            .get(&word.len())
+            .into_iter()
+            .flat_map(|b| b.iter())
+            .filter(move |w| letter_counts(w) == counts)
+    }
+
+    // Words of given length with at least the letters of `include` (counting repeats)
+    // and none of the letters of `exclude`.
+    pub fn filter<'a>(&'a self, len: usize, include: &str, exclude: &str) -> impl Iterator<Item = &'a str> {
+        let include = letter_counts(include);
+        let exclude = letter_counts(exclude);
+        self.data
+            .get(&len)
+            .into_iter()
+            .flat_map(|b| b.iter())
+            .filter(move |w| fits_letters(w, &include, &exclude))
+    }
+}
+
+// Does `word` fit a pattern like `s?a?e`? Same rules as `Dictionary::matching`.
+pub fn fits_pattern(word: &str, pattern: &str) -> bool {
+    word.len() == pattern.len()
+        && word.bytes().zip(pattern.bytes()).all(|(c, p)| p == WILDCARD as u8 || c == p)
+}
+
+// Does `word` have at least the letters counted in `include` and none of those in `exclude`?
+// Same rules as `Dictionary::filter`, counts come from `letter_counts`.
+pub fn fits_letters(word: &str, include: &[u8; 26], exclude: &[u8; 26]) -> bool {
+    letter_counts(word)
+        .iter()
+        .zip(include.iter().zip(exclude.iter()))
+        .all(|(&n, (&inc, &exc))| n >= inc && (exc == 0 || n == 0))
+}
+
+// Count occurrences of each letter a-z, ignoring case and anything else.
+pub fn letter_counts(word: &str) -> [u8; 26] {
+    let mut out = [0u8; 26];
+    for c in word.bytes().filter(u8::is_ascii_alphabetic) {
+        let slot = &mut out[(c.to_ascii_lowercase() - b'a') as usize];
+        *slot = slot.saturating_add(1);
+    }
+    out
+}
+
+// Dictionary loading failure.
+#[derive(Debug)]
+pub enum DictError {
+    NoPath(io::Error), // no path given and executable location unknown
+    Io(PathBuf, io::Error),
+    Parse(PathBuf, serde_json::Error),
+    BadCache(PathBuf, String),
+}
+
+impl fmt::Display for DictError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use DictError::*;
+        match self {
+            NoPath(e) => write!(f, "Dictionary path unknown, set {}: {}", DICT_VARNAME, e),
+            Io(p, e) => write!(f, "Failed to read dictionary {}: {}", p.display(), e),
+            Parse(p, e) => write!(f, "Failed to deserialize dictionary {}: {}", p.display(), e),
+            BadCache(p, e) => write!(f, "Malformed dictionary cache {}: {}", p.display(), e),
+        }
     }
 }
 
+impl error::Error for DictError {}
+
 // Get dictionary path from environment variables or executable path.
-pub fn get_dict_path() -> PathBuf {
-    env::var(DICT_VARNAME)
+pub fn get_dict_path() -> Result<PathBuf, DictError> {
+    persist::data_path(DICT_VARNAME, DICT_FILENAME).map_err(DictError::NoPath)
+}
+
+// Get binary cache path from environment variables, or put it next to the dictionary.
+pub fn get_cache_path(dict_path: &Path) -> PathBuf {
+    env::var(CACHE_VARNAME)
         .map(PathBuf::from)
-        .or_else(|_| {
-            std::env::current_exe().map(|mut p| {
-                p.pop();
-                p.push(DICT_FILENAME);
-                p
-            })
-        })
-        .expect("Dictionary not found")
+        .unwrap_or_else(|_| dict_path.with_extension(CACHE_EXTENSION))
 }
 
 // Load dictionary as raw set of words from specified path.
-pub fn load_dictset_from(path: &Path) -> DictSet {
-    let mut file = std::fs::File::open(path)
-        .expect("Failed to open dictionary");
-    let mut buf = Vec::new();
-    use std::io::Read;
-    file.read_to_end(&mut buf)
-        .expect("Failed to read dictionary");
-    serde_json::from_slice(&buf).expect("Failed to deserialize dictionary")
+pub fn load_dictset_from(path: &Path) -> Result<DictSet, DictError> {
+    let buf = fs::read(path).map_err(|e| DictError::Io(path.into(), e))?;
+    serde_json::from_slice(&buf).map_err(|e| DictError::Parse(path.into(), e))
+}
+
+// Load dictionary from binary cache.
+pub fn load_cache_from(path: &Path) -> Result<Dictionary, DictError> {
+    let buf = fs::read(path).map_err(|e| DictError::Io(path.into(), e))?;
+    cache::decode(&buf).map_err(|e| DictError::BadCache(path.into(), e))
+}
+
+pub fn save_cache_to(dict: &Dictionary, path: &Path) -> Result<(), DictError> {
+    fs::write(path, cache::encode(dict)).map_err(|e| DictError::Io(path.into(), e))
+}
+
+// Is the cache at least as new as the dictionary? Also true if only the cache exists.
+fn cache_is_fresh(dict_path: &Path, cache_path: &Path) -> bool {
+    let modified = |p: &Path| fs::metadata(p).and_then(|m| m.modified()).ok();
+    match (modified(dict_path), modified(cache_path)) {
+        (Some(d), Some(c)) => c >= d,
+        (None, Some(_)) => true,
+        _ => false,
+    }
+}
+
+// Load dictionary from given paths.
+// Prefers a fresh binary cache, otherwise parses JSON and (re)writes the cache.
+pub fn load_dictionary_from(dict_path: &Path, cache_path: &Path) -> Result<Dictionary, DictError> {
+    if cache_is_fresh(dict_path, cache_path) {
+        match load_cache_from(cache_path) {
+            Ok(dict) => return Ok(dict),
+            // Falls through to rebuilding the cache.
+            Err(e) => tracing::warn!(error = %e, "dictionary cache not loaded"),
+        }
+    }
+    let dict = Dictionary::new(load_dictset_from(dict_path)?);
+    if let Err(e) = save_cache_to(&dict, cache_path) {
+        tracing::warn!(error = %e, "dictionary cache not written");
+    }
+    Ok(dict)
 }
 
 // Load dictionary.
-pub fn load_dictionary() -> Dictionary {
-    let path = get_dict_path();
-    Dictionary::new(load_dictset_from(&path))
+pub fn load_dictionary() -> Result<Dictionary, DictError> {
+    let path = get_dict_path()?;
+    load_dictionary_from(&path, &get_cache_path(&path))
 }
 
+pub mod bucket;
+mod cache;
+pub mod definitions;
+pub mod feedback;
+pub mod overlay;
 pub mod wordmatch;
+pub use bucket::Bucket;
 
 #[cfg(test)]
 mod test {
@@ -103,12 +254,12 @@ mod test {
         let mut count_vision = 0_usize;
         let security = 256;
         for _ in 0..security {
-            assert!(dict.random_with_len(5).map_or(false, |s| s == "squat"));
-            assert!(dict.random_with_len(7).map_or(false, |s| s == "running"));
-            assert!(dict.random_with_len(4).map_or(false, |s| s == "fall"));
+            assert!(dict.random_with_len(5).is_some_and(|s| s == "squat"));
+            assert!(dict.random_with_len(7).is_some_and(|s| s == "running"));
+            assert!(dict.random_with_len(4).is_some_and(|s| s == "fall"));
             
-            assert!(dict.random_with_len(6).map_or(false,
-                |s| match s.as_str() {
+            assert!(dict.random_with_len(6).is_some_and(
+                |s| match s {
                     "churro" => {count_churro += 1; true},
                     "vision" => {count_vision += 1; true},
                     _ => false,
@@ -120,4 +271,68 @@ mod test {
         assert!(count_vision > 0);
         assert!(count_churro == security-count_vision);
     }
-}
\ No newline at end of file
+
+    fn get_dict() -> Dictionary {
+        let set = DictSet::from(["shade", "slate", "snake", "share", "stare", "tears", "rates", "sh"]
+            .map(|s| s.to_string()));
+        Dictionary::new(set)
+    }
+
+    #[test]
+    fn test_queries() {
+        let dict = get_dict();
+        assert_eq!(dict.counts(), vec![(2, 1), (5, 7)]);
+        assert_eq!(dict.with_prefix("sha", 5).collect::<Vec<_>>(), vec!["shade", "share"]);
+        assert_eq!(dict.with_prefix("sh", 2).collect::<Vec<_>>(), vec!["sh"]);
+        assert_eq!(dict.with_prefix("x", 5).count(), 0);
+        assert_eq!(dict.matching("s?a?e").collect::<Vec<_>>(), vec!["shade", "share", "slate", "snake", "stare"]);
+        assert_eq!(dict.matching("?a?e?").collect::<Vec<_>>(), vec!["rates"]);
+        assert_eq!(dict.matching("s?a?").count(), 0);
+        assert_eq!(dict.anagrams("aster").collect::<Vec<_>>(), vec!["rates", "stare", "tears"]);
+        assert_eq!(dict.filter(5, "as", "t").collect::<Vec<_>>(), vec!["shade", "share", "snake"]);
+        assert_eq!(dict.filter(5, "ee", "").count(), 0);
+        assert!(dict.random_with_len(3).is_none());
+        assert!(fits_pattern("slate", "s?a?e") && !fits_pattern("slates", "s?a?e"));
+        assert!(fits_letters("shade", &letter_counts("as"), &letter_counts("t")));
+    }
+
+    #[test]
+    fn test_cache_roundtrip() {
+        let dict = get_dict();
+        let bytes = cache::encode(&dict);
+        let decoded = cache::decode(&bytes).unwrap();
+        assert!(dict.buckets().eq(decoded.buckets()));
+        assert!(decoded.contains("tears"));
+
+        assert!(cache::decode(&bytes[..bytes.len() - 1]).is_err());
+        assert!(cache::decode(b"not a dictionary").is_err());
+        // Swap the last two words
+        let mut unsorted = bytes.clone();
+        let tail = unsorted.len() - 10;
+        unsorted[tail..].rotate_left(5);
+        assert!(cache::decode(&unsorted).is_err());
+        // Huge bucket count in the header
+        let mut corrupt = bytes.clone();
+        corrupt[8..12].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(cache::decode(&corrupt).is_err());
+    }
+
+    #[test]
+    fn test_load_errors() {
+        let dir = env::temp_dir().join(format!("wordclash-dict-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let (json, bin) = (dir.join("dict.json"), dir.join("dict.bin"));
+
+        assert!(matches!(load_dictionary_from(&json, &bin), Err(DictError::Io(..))));
+        fs::write(&json, "[\"slate\", 4]").unwrap();
+        assert!(matches!(load_dictionary_from(&json, &bin), Err(DictError::Parse(..))));
+
+        fs::write(&json, "[\"slate\", \"tears\"]").unwrap();
+        assert!(load_dictionary_from(&json, &bin).unwrap().contains("slate"));
+        // Now served from the cache
+        fs::remove_file(&json).unwrap();
+        assert!(load_dictionary_from(&json, &bin).unwrap().contains("tears"));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,110 @@
+use std::collections::{BTreeMap, BTreeSet};
+use rand::prelude::*;
+use serde::{Deserialize, Serialize};
+use super::Dictionary;
+
+// How many times to redraw a random word that turned out to be denied.
+const RANDOM_RETRIES: usize = 32;
+
+// Named word list that can be switched on and off as a whole.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WordPack {
+    pub words: BTreeSet<String>,
+    pub enabled: bool,
+}
+
+// Guild-scoped changes on top of the base dictionary.
+// Denied words always lose, even against allow-lists and packs.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WordOverlay {
+    pub allow: BTreeSet<String>,
+    pub deny: BTreeSet<String>,
+    pub packs: BTreeMap<String, WordPack>,
+}
+
+impl WordOverlay {
+    fn is_extra(&self, word: &str) -> bool {
+        self.allow.contains(word)
+            || self.packs.values().any(|p| p.enabled && p.words.contains(word))
+    }
+
+    pub fn contains(&self, dict: &Dictionary, word: &str) -> bool {
+        !self.deny.contains(word) && (dict.contains(word) || self.is_extra(word))
+    }
+
+    // Extra (non-dictionary, non-denied) words of given length.
+    pub fn extras_with_len(&self, dict: &Dictionary, len: usize) -> Vec<&str> {
+        let packs = self.packs.values().filter(|p| p.enabled).flat_map(|p| p.words.iter());
+        self.allow
+            .iter()
+            .chain(packs)
+            .filter(|w| w.len() == len && !self.deny.contains(*w) && !dict.contains(w))
+            .map(|w| w.as_str())
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect()
+    }
+
+    // Uniformly pick from the dictionary bucket plus extra words, skipping denied ones.
+    pub fn random_with_len(&self, dict: &Dictionary, len: usize) -> Option<String> {
+        let base = dict.bucket(len).map_or(0, |b| b.count());
+        let extras = self.extras_with_len(dict, len);
+        let total = base + extras.len();
+        if total == 0 {
+            return None;
+        }
+        let mut rng = thread_rng();
+        for _ in 0..RANDOM_RETRIES {
+            let index = rng.gen_range(0..total);
+            let word = match index.checked_sub(base) {
+                Some(i) => extras[i],
+                None => dict.bucket(len)?.get(index)?,
+            };
+            if !self.deny.contains(word) {
+                return Some(word.to_string());
+            }
+        }
+        // Deny-list must be eating most of the bucket, fall back to a full scan.
+        dict.bucket(len)?
+            .iter()
+            .chain(extras)
+            .filter(|w| !self.deny.contains(*w))
+            .choose(&mut rng)
+            .map(|w| w.to_string())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::dict::DictSet;
+
+    #[test]
+    fn test_overlay() {
+        let dict = Dictionary::new(DictSet::from(["slate", "tears", "crude"].map(|s| s.to_string())));
+        let mut overlay = WordOverlay::default();
+        overlay.deny.insert("crude".to_string());
+        overlay.allow.insert("wordy".to_string());
+        overlay.packs.insert("fruit".to_string(), WordPack {
+            words: ["mango", "grape"].iter().map(|s| s.to_string()).collect(),
+            enabled: false,
+        });
+
+        assert!(overlay.contains(&dict, "slate"));
+        assert!(!overlay.contains(&dict, "crude"));
+        assert!(overlay.contains(&dict, "wordy"));
+        assert!(!overlay.contains(&dict, "mango"));
+        overlay.packs.get_mut("fruit").unwrap().enabled = true;
+        assert!(overlay.contains(&dict, "mango"));
+
+        let mut seen = BTreeSet::new();
+        for _ in 0..256 {
+            seen.insert(overlay.random_with_len(&dict, 5).unwrap());
+        }
+        assert!(!seen.contains("crude"));
+        assert_eq!(seen.len(), 5);
+
+        overlay.deny.extend(["slate", "tears", "wordy", "mango", "grape"].map(|s| s.to_string()));
+        assert!(overlay.random_with_len(&dict, 5).is_none());
+    }
+}
@@ -1,4 +1,5 @@
 use std::collections::HashMap;
+use super::feedback::{self, Word};
 
 #[repr(u8)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
@@ -8,7 +9,19 @@ pub enum MatchLetter {
     Exact = 2, // present here
 }
 
+// Feedback for `provided` guessed against `base`.
+// Goes through the fast engine whenever both words are plain lowercase ASCII.
 pub fn match_word(base: &str, provided: &str) -> Vec<MatchLetter> {
+    if base.len() == provided.len() {
+        if let (Some(b), Some(p)) = (Word::new(base), Word::new(provided)) {
+            return feedback::decode(feedback::pattern(&b, &p), base.len());
+        }
+    }
+    match_word_chars(base, provided)
+}
+
+// Reference implementation working on arbitrary characters.
+pub fn match_word_chars(base: &str, provided: &str) -> Vec<MatchLetter> {
     let mut output = Vec::with_capacity(base.len());
     output.resize(base.len(), MatchLetter::Null);
 
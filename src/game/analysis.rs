@@ -0,0 +1,175 @@
+// Post-game analysis: how much each guess narrowed down the answer,
+// and what a solver maximising expected information would have played.
+use crate::dict::Dictionary;
+use crate::dict::feedback::{self, stride_sample, PatternTable, Word};
+
+// How many words are tried when searching for the best guess.
+const SEARCH_POOL: usize = 2000;
+// How many remaining candidates are used to score those words.
+const SEARCH_SAMPLE: usize = 3000;
+
+#[derive(Debug, Clone)]
+pub struct GuessAnalysis {
+    pub guess: String,
+    pub before: usize, // candidates consistent with all previous feedback
+    pub after: usize,  // candidates also consistent with this guess's feedback
+    pub expected_bits: f64,
+    pub best_guess: Option<String>,
+    pub best_bits: f64,
+}
+
+impl GuessAnalysis {
+    // Information this guess actually gave, in bits.
+    pub fn actual_bits(&self) -> f64 {
+        if self.after == 0 {
+            return 0.0;
+        }
+        (self.before as f64 / self.after as f64).log2()
+    }
+}
+
+// Most informative guess from the table's guesses and `pool`.
+// Candidates (sorted) win ties, as they might also be the answer.
+// Guesses in the table are scored from it, as long as it knows every candidate sampled.
+pub fn best_guess(table: Option<&PatternTable>, pool: &[Word], candidates: &[Word]) -> Option<(Word, f64)> {
+    if candidates.len() == 1 {
+        let known = pool.contains(&candidates[0]) || table.is_some_and(|t| t.guesses().contains(&candidates[0]));
+        return known.then_some((candidates[0], 0.0));
+    }
+    let sample = stride_sample(candidates, SEARCH_SAMPLE);
+    let columns = table.and_then(|t| sample.iter().map(|w| t.answer_index(w)).collect::<Option<Vec<_>>>());
+    let tabled = match (table, &columns) {
+        (Some(t), Some(_)) => t.guesses(),
+        _ => &[],
+    };
+
+    let mut best: Option<(Word, f64, bool)> = None;
+    let mut consider = |guess: Word, bits: f64| {
+        let is_candidate = candidates.binary_search(&guess).is_ok();
+        let better = match best {
+            None => true,
+            Some((_, b, c)) => bits > b + 1e-9 || ((bits - b).abs() <= 1e-9 && is_candidate && !c),
+        };
+        if better {
+            best = Some((guess, bits, is_candidate));
+        }
+    };
+    if let (Some(t), Some(columns)) = (table, &columns) {
+        for (row, guess) in tabled.iter().enumerate() {
+            consider(*guess, t.entropy(row, columns));
+        }
+    }
+    for guess in pool.iter().filter(|g| tabled.binary_search(g).is_err()) {
+        consider(*guess, feedback::entropy(guess, &sample));
+    }
+    best.map(|(w, b, _)| (w, b))
+}
+
+// Analyse one side's guesses against the word it had to find.
+// Only words the feedback engine can encode take part, which is every lowercase ASCII word.
+pub fn analyze_side(dict: &Dictionary, answer: &str, guesses: &[String]) -> Vec<GuessAnalysis> {
+    let answer = match Word::new(answer) {
+        Some(w) => w,
+        None => return Vec::new(),
+    };
+    let mut words = dict.bucket(answer.len()).map(feedback::encode_bucket).unwrap_or_default();
+    // Guild word lists might have allowed a word the dictionary doesn't know.
+    if let Err(i) = words.binary_search(&answer) {
+        words.insert(i, answer);
+    }
+    let table = dict.patterns(answer.len());
+    let mut candidates = words.clone();
+    let mut patterns = Vec::new();
+
+    let mut out = Vec::with_capacity(guesses.len());
+    for guess in guesses {
+        let encoded = Word::new(guess).filter(|g| g.len() == answer.len());
+        let g = match encoded {
+            Some(g) => g,
+            None => continue,
+        };
+        let before = candidates.len();
+        let expected_bits = feedback::entropy(&g, &candidates);
+
+        // Half the pool from what's still possible, half from everything,
+        // which the dictionary's pattern table stands in for when there is one.
+        let mut pool = stride_sample(&candidates, SEARCH_POOL / 2);
+        if table.is_none() {
+            pool.extend(stride_sample(&words, SEARCH_POOL / 2));
+            pool.sort_unstable();
+            pool.dedup();
+        }
+        let best = best_guess(table, &pool, &candidates);
+
+        let target = feedback::pattern(&answer, &g);
+        feedback::patterns_for_guess(&g, &candidates, &mut patterns);
+        let mut keep = patterns.iter().map(|&p| p == target);
+        candidates.retain(|_| keep.next().unwrap_or(false));
+
+        out.push(GuessAnalysis {
+            guess: guess.clone(),
+            before,
+            after: candidates.len(),
+            expected_bits,
+            best_guess: best.map(|(w, _)| w.to_string()),
+            best_bits: best.map_or(0.0, |(_, b)| b),
+        });
+    }
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::dict::DictSet;
+
+    fn get_dict() -> Dictionary {
+        let set = ["slate", "stale", "steal", "tales", "crane", "brine", "shine", "spine"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect::<DictSet>();
+        Dictionary::new(set)
+    }
+
+    #[test]
+    fn test_best_guess() {
+        let w = |s: &str| Word::new(s).unwrap();
+        let candidates = ["brine", "crane", "slate"].map(w);
+        let pool = ["brine", "xxxxx"].map(w);
+        let (best, bits) = best_guess(None, &pool, &candidates).unwrap();
+        // Three different feedbacks, so log2(3) bits
+        assert_eq!(best, w("brine"));
+        assert!((bits - 3f64.log2()).abs() < 1e-9);
+        // Nothing to learn from a word that shares no letters
+        assert_eq!(best_guess(None, &pool[1..], &candidates).unwrap().1, 0.0);
+    }
+
+    #[test]
+    fn test_analyze_side() {
+        let dict = get_dict();
+        let guesses = ["crane", "spine", "shine"].map(|s| s.to_string());
+        let analysis = analyze_side(&dict, "shine", &guesses);
+
+        assert_eq!(analysis.len(), 3);
+        assert_eq!(analysis[0].before, 8);
+        assert!(analysis[0].after < 8);
+        assert_eq!(analysis[1].before, analysis[0].after);
+        assert_eq!(analysis[2].after, 1);
+        // The dictionary is small enough for the search to see every word
+        for a in &analysis {
+            assert!(a.after <= a.before);
+            assert!(a.best_bits + 1e-9 >= a.expected_bits);
+            assert!(a.actual_bits() >= 0.0);
+        }
+        // Only one word left, the solver would just play it.
+        assert_eq!(analysis[2].best_guess.as_deref(), Some("shine"));
+
+        // The table gives the same answers as working patterns out directly
+        let table = dict.patterns(5).unwrap();
+        let candidates = feedback::encode_bucket(dict.bucket(5).unwrap());
+        let direct = best_guess(None, table.guesses(), &candidates).unwrap();
+        let cached = best_guess(Some(table), &[], &candidates).unwrap();
+        assert_eq!(direct.0, cached.0);
+        assert!((direct.1 - cached.1).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,21 @@
+use poise::serenity_prelude as serenity;
+use serde::{Deserialize, Serialize};
+use serenity::{GuildId, UserId};
+use super::{GameId, GameVariant};
+use super::multiplayer::Handicap;
+
+// Record of a finished game, indexed by side like GameMP.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArchivedGame {
+    pub id: GameId,
+    pub players: [UserId; 2],
+    pub words: [String; 2], // words[i] is the word side i had to guess
+    pub guesses: [Vec<String>; 2],
+    pub variant: GameVariant,
+    pub guild: Option<GuildId>,
+    pub handicaps: [Handicap; 2],
+    pub scores: [u64; 2],
+    pub finished: bool, // played to the end rather than interrupted
+    pub winner: Option<usize>, // side, None for draws and interrupted games
+    pub ended: u64, // Unix time in seconds
+}
@@ -1,10 +1,15 @@
 // Wordle implementation proper.
 
 pub mod side;
-pub use side::GameSide;
 pub mod multiplayer;
 pub use multiplayer::GameMP;
-use poise::serenity_prelude::UserId;
+pub mod archive;
+pub use archive::ArchivedGame;
+pub mod analysis;
+pub mod series;
+pub use series::Series;
+use poise::serenity_prelude::{ChannelId, GuildId, RoleId, UserId};
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::sync::atomic;
 use std::time;
@@ -16,9 +21,45 @@ pub type AtomicGameId = atomic::AtomicU64;
 pub struct Invite {
     pub expiry: time::SystemTime,
     pub game: GameId,
+    pub channel: Option<ChannelId>, // where the challenge was made, for notices
 }
 
-#[derive(Debug, Clone, Copy)]
+// Where a challenge comes from, and optional parts of it.
+#[derive(Debug, Clone, Default)]
+pub struct ChallengeOptions {
+    pub guild: Option<GuildId>, // whose word lists apply to the game
+    pub handicaps: [multiplayer::Handicap; 2], // by side, the challenger's first
+    pub channel: Option<ChannelId>, // to tell the challenger in if the invite expires
+    pub roles: Vec<RoleId>, // the challenger's, in the guild
+    pub matched: bool, // paired by matchmaking rather than picked
+}
+
+// Challenge posted to a channel, for anyone eligible to take up.
+// The game exists from the start, its second seat is `multiplayer::OPEN_SEAT` until claimed.
+#[derive(Debug, Clone)]
+pub struct OpenChallenge {
+    pub invite: Invite,
+    pub variant: GameVariant,
+    pub channel: ChannelId,
+    pub eligible: Eligibility,
+}
+
+// Who may take up an open challenge.
+#[derive(Debug, Clone, Default)]
+pub struct Eligibility {
+    pub role: Option<RoleId>,
+    pub rating: (Option<u64>, Option<u64>), // inclusive score bounds
+}
+
+impl Eligibility {
+    pub fn allows(&self, roles: &[RoleId], rating: u64) -> bool {
+        self.role.is_none_or(|r| roles.contains(&r))
+            && self.rating.0.is_none_or(|min| rating >= min)
+            && self.rating.1.is_none_or(|max| rating <= max)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum GameVariant {
     Timed,
     TurnBased,
@@ -92,20 +133,31 @@ impl PlayerData {
         })
     }
     
+    // Drop references to a game this player challenged `enemy_id` to, once it's gone.
+    pub fn forget_game(&mut self, enemy_id: UserId, game: GameId) {
+        if self.timed_game == Some(game) {
+            self.timed_game = None;
+        }
+        if self.turn_games.get(&enemy_id) == Some(&game) {
+            self.turn_games.remove(&enemy_id);
+        }
+    }
+
     pub fn clean_invites(&mut self, before: time::SystemTime) {
         self.timed_challenges.retain(|_, v| v.expiry > before);
         self.turn_challenges.retain(|_, v| v.expiry > before);
     }
 
     /**
-     * Removes expired invites and runs `each` on every expired invite.
+     * Removes expired invites and runs `each` on every expired invite,
+     * along with its variant and the challenger.
      */
-    pub fn clean_invites_then<F: FnMut(&mut Invite)>(&mut self, before: time::SystemTime, mut each: F) {
-        self.timed_challenges.retain(|_, v| v.expiry > before || {
-            each(v); true
+    pub fn clean_invites_then<F: FnMut(GameVariant, UserId, &Invite)>(&mut self, before: time::SystemTime, mut each: F) {
+        self.timed_challenges.retain(|id, v| v.expiry > before || {
+            each(Timed, *id, v); false
         });
-        self.turn_challenges.retain(|_, v| v.expiry > before || {
-            each(v); true
+        self.turn_challenges.retain(|id, v| v.expiry > before || {
+            each(TurnBased, *id, v); false
         });
     }
 }
\ No newline at end of file
@@ -1,8 +1,10 @@
 use crate::dict::wordmatch::*;
 use crate::commands::util::{CmdError, CmdResult};
 use poise::serenity_prelude as serenity;
-use serenity::UserId;
-use std::time::Instant;
+use serde::{Deserialize, Serialize};
+use serenity::{ChannelId, GuildId, MessageId, UserId};
+use std::time::{Duration, Instant, SystemTime};
+use crate::i18n::{self, tr, Lang};
 
 use super::side::GameSide;
 use super::GameVariant;
@@ -17,6 +19,32 @@ pub enum GameProgress {
     Over(Option<usize>), // .0 indicates winning player if Some, or draw if None
 }
 
+// Someone following a game without playing in it.
+#[derive(Debug, Clone)]
+pub struct Watcher {
+    pub id: UserId,
+    pub view: Option<(ChannelId, MessageId)>, // live view kept up to date
+    pub prediction: Option<usize>, // side expected to win
+}
+
+// Help for one side of an uneven matchup.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Handicap {
+    pub extra_guesses: usize, // on top of the shared limit, they don't add to the score
+    pub time_bonus: u64, // seconds taken off their time in timed games
+    pub first_letter: bool, // first letter of the word to guess is shown
+}
+
+// What a guess is worth, in timed games at least. A revealed first letter
+// and each extra guess cost this much of the helped side's score.
+const GUESS_COST: u64 = 3;
+
+impl Handicap {
+    pub fn is_none(&self) -> bool {
+        *self == Handicap::default()
+    }
+}
+
 // Per-game data
 pub struct GameMP {
     side: [GameSide; 2],
@@ -24,17 +52,25 @@ pub struct GameMP {
     end: [Option<Instant>; 2],
     progress: GameProgress,
     score: [u64; 2],
-    max_guesses: usize,
+    max_guesses: usize, // shared limit, handicaps may add to it
+    handicap: [Handicap; 2],
     variant: GameVariant,
+    guild: Option<GuildId>, // where the challenge was made, its word lists apply
+    watchers: Vec<Watcher>,
 }
 
+// Placeholder opponent of an open challenge, no real user has this ID.
+pub const OPEN_SEAT: UserId = UserId(0);
+
 const PLAYER_CAP: usize = 2;
 const EMOJI_WIDTH: usize = 25;
 const ALPHA_LENGTH: usize = 26;
 
 impl GameMP {
     // Start of a game.
-    pub fn create(id_self: UserId, id_challenged: UserId, word: String, variant: GameVariant) -> GameMP {
+    pub fn create(
+        id_self: UserId, id_challenged: UserId, word: String, variant: GameVariant, guild: Option<GuildId>
+    ) -> GameMP {
         let mut out = GameMP {
             side: [GameSide::with_id(id_self), GameSide::with_id(id_challenged)],
             start: Instant::now(),
@@ -42,13 +78,40 @@ impl GameMP {
             progress: GameProgress::Waiting,
             score: [0, 0],
             max_guesses: word.len() + 1,
-            variant
+            handicap: [Handicap::default(); 2],
+            variant,
+            guild,
+            watchers: Vec::new(),
         };
         out.side[1].baseword = word;
 
         out
     }
 
+    // Only before anyone accepted, the opponent has to know what they agree to.
+    pub fn set_handicap(&mut self, index: usize, handicap: Handicap) -> CmdResult<()> {
+        if !matches!(self.progress, GameProgress::Waiting) {
+            return Err(CmdError::GameStarted(false));
+        }
+        self.handicap[index] = handicap;
+        Ok(())
+    }
+
+    // Guesses both sides get before handicaps, instead of one more than the word length.
+    // Only before anyone accepted, same as handicaps.
+    pub fn set_max_guesses(&mut self, guesses: usize) -> CmdResult<()> {
+        if !matches!(self.progress, GameProgress::Waiting) {
+            return Err(CmdError::GameStarted(false));
+        }
+        self.max_guesses = guesses;
+        Ok(())
+    }
+
+    // How many guesses side `index` gets.
+    pub fn get_guess_limit(&self, index: usize) -> usize {
+        self.max_guesses + self.handicap[index].extra_guesses
+    }
+
     #[inline]
     pub fn get_word_length(&self) -> usize {
         self.side[1].baseword.len()
@@ -56,12 +119,16 @@ impl GameMP {
 
     // Match an user ID to a side index.
     pub fn match_user(&self, id: UserId) -> Option<usize> {
-        for i in 0..2 {
-            if self.side[i].id == id {
-                return Some(i);
-            }
+        (0..PLAYER_CAP).find(|&i| self.side[i].id == id)
+    }
+
+    // Take the free seat of an open challenge. False if it's taken or the game moved on.
+    pub fn claim(&mut self, id: UserId) -> bool {
+        if !matches!(self.progress, GameProgress::Waiting) || self.side[1].id != OPEN_SEAT || self.side[0].id == id {
+            return false;
         }
-        None
+        self.side[1].id = id;
+        true
     }
 
     // Respond to started game with a word for the challenger, and start the game if valid.
@@ -71,7 +138,8 @@ impl GameMP {
             return Err(CmdError::GameStarted(true));
         }
         if word.len() != self.get_word_length() {
-            return Err(CmdError::BadWordLength(word.len()));
+            let length = self.get_word_length();
+            return Err(CmdError::BadWordLength { length: word.len(), min: length, max: length });
         }
         if id != self.side[1].id {
             return Err(CmdError::BadAccept);
@@ -82,20 +150,25 @@ impl GameMP {
         Ok(())
     }
 
-    fn calculate_scores(&mut self) {
-        for end in self.end {
-            if end.is_none() {
-                return;
-            }
+    // Player `index` gives up, the other one wins if the game got going.
+    // Scores stay as they are.
+    pub fn forfeit(&mut self, index: usize) {
+        if !matches!(self.progress, GameProgress::Waiting) {
+            self.progress = GameProgress::Over(Some(1 - index));
+        }
+    }
+
+    // Sets the scores kept, and returns the ones the winner is decided by.
+    // Handicaps help win: time bonuses come off the helped side's time, extra guesses let them go on.
+    // What they were worth is then taken off the score kept, so a handicapped win doesn't inflate it.
+    fn calculate_scores(&mut self) -> [u64; 2] {
+        if self.end.iter().any(|e| e.is_none()) {
+            return self.score;
         }
         let spans = self.end.map(|e| e.unwrap().duration_since(self.start));
-        // The last of the two ends
-        let max_end = spans.iter().max().unwrap();
-        // Duration to add before as_secs to achieve "rounding up" behavior
-        // Equal to one second minus one smallest unit of duration (ns)
-        let near_second = std::time::Duration::from_nanos(999_999_999);
-        // Second count based on spans, used as input for score calculation
-        let mut secscores = spans.map(|s| (*max_end - s + near_second).as_secs());
+        let on_clock = [0, 1].map(|i| spans[i].saturating_sub(Duration::from_secs(self.handicap[i].time_bonus)));
+        let mut secscores = time_advantage(on_clock);
+        let mut unhelped = time_advantage(spans);
 
         // Maximum of used guesses
         let top_guesses = self.side.iter().map(|s| s.guesses.len()).max().unwrap();
@@ -104,23 +177,32 @@ impl GameMP {
         let victory: Vec<_> = self.side.iter().map(|s| s.victorious()).collect();
         if !victory.iter().all(|x| *x) {
             secscores = [0, 0];
+            unhelped = [0, 0];
         }
 
+        let mut raw = [0; 2];
         for i in 0..2 {
             if !victory[i] {
                 self.score[i] = 0;
-            } else {
-                self.score[i] = match self.variant {
-                    GameVariant::Timed => self.side[i].calculate_timed_score(secscores[i], self.max_guesses),
-                    GameVariant::TurnBased => self.side[i].calculate_turn_score(
-                        self.max_guesses,
-                        top_guesses - self.side[i].guesses.len(),
-                        self.get_word_length(),
-                    ),
-                };
+                continue;
             }
+            // Scored against the shared limit, so extra guesses can't earn more
+            raw[i] = match self.variant {
+                GameVariant::Timed => self.side[i].calculate_timed_score(secscores[i], self.max_guesses),
+                GameVariant::TurnBased => self.side[i].calculate_turn_score(
+                    self.max_guesses,
+                    top_guesses - self.side[i].guesses.len(),
+                    self.get_word_length(),
+                ),
+            };
+            let handicap = self.handicap[i];
+            let cost = (handicap.extra_guesses as u64 + handicap.first_letter as u64) * GUESS_COST
+                + secscores[i].saturating_sub(unhelped[i]);
+            self.score[i] = raw[i].saturating_sub(cost);
         }
+        raw
     }
+
     // Send a guess as player number `index`.
     // Returns true if accepted (which is not an error). Adjusts progress.
     pub fn send_guess(&mut self, index: usize, guess: String) -> bool {
@@ -131,7 +213,7 @@ impl GameMP {
             GameProgress::Started => {
                 let finished = self.side[index].push_guess(guess);
                 // Move on to Ending if finished or if out of guesses
-                if finished || self.side[index].guesses.len() == self.max_guesses {
+                if finished || self.side[index].guesses.len() == self.get_guess_limit(index) {
                     self.progress = GameProgress::Ending(index);
                     self.end[index] = Some(Instant::now());
                 }
@@ -143,13 +225,13 @@ impl GameMP {
                 }
                 let finished = self.side[index].push_guess(guess);
 
-                if finished || self.side[index].guesses.len() == self.max_guesses {
+                if finished || self.side[index].guesses.len() == self.get_guess_limit(index) {
                     self.end[index] = Some(Instant::now());
-                    self.calculate_scores();
+                    let raw = self.calculate_scores();
 
                     use std::cmp::Ordering::*;
                     use GameProgress::Over;
-                    match self.score[0].cmp(&self.score[1]) {
+                    match raw[0].cmp(&raw[1]) {
                         Less => {
                             self.progress = Over(Some(1));
                         }
@@ -168,11 +250,12 @@ impl GameMP {
     }
 
     pub fn render_view(&self, index: usize) -> String {
-        let mut out = String::with_capacity(self.max_guesses * 20);
+        let rows = self.get_guess_limit(index);
+        let mut out = String::with_capacity(rows * 20);
         let empty_line: String = (0..self.get_word_length() * 3).map(|_| ' ').collect();
         let side = &self.side[index];
 
-        for i in 0..self.max_guesses {
+        for i in 0..rows {
             if let Some(row) = side.guesses.get(i) {
                 row.0.chars().zip(row.1.iter()).for_each(|(c, m)| {
                     use MatchLetter::*;
@@ -186,7 +269,7 @@ impl GameMP {
             } else {
                 out.push_str(&empty_line);
             }
-            if i + 1 < self.max_guesses {
+            if i + 1 < rows {
                 out.push('\n');
             }
         }
@@ -194,12 +277,13 @@ impl GameMP {
     }
 
     pub fn render_view_color(&self, index: usize) -> String {
-        let mut out = String::with_capacity(self.max_guesses * EMOJI_WIDTH * self.get_word_length() * 2);
+        let rows = self.get_guess_limit(index);
+        let mut out = String::with_capacity(rows * EMOJI_WIDTH * self.get_word_length() * 2);
         let white_line: String = (0..self.get_word_length()).map(|_| ":white_large_square:").collect();
         let black_line: String = (0..self.get_word_length()).map(|_| ":black_large_square:").collect();
         let side = &self.side[index];
 
-        for i in 0..self.max_guesses {
+        for i in 0..rows {
             if let Some(row) = side.guesses.get(i) {
                 let mut l_out = String::with_capacity(EMOJI_WIDTH * self.get_word_length());
                 let mut a_out = String::with_capacity(EMOJI_WIDTH * self.get_word_length());
@@ -221,43 +305,36 @@ impl GameMP {
                 out.push('\n');
                 out.push_str(&black_line);
             }
-            if i + 1 < self.max_guesses {
+            if i + 1 < rows {
                 out.push('\n');
             }
         }
         out
     }
     
-    pub fn render_stateline(&self, want_scores: bool) -> String {
-        let mut state = serenity::MessageBuilder::new();
+    pub fn render_stateline(&self, lang: Lang, want_scores: bool) -> String {
         use GameProgress::*;
-        use std::fmt::Write;
-        match self.progress {
-            Waiting => state.push("Waiting"),
-            Started => state.push("Both players active, game in progress"),
-            Ending(i) => state
-                .push("Player ")
-                .push(i.to_string())
-                .push(" finished in ")
-                .push(
-                    self
-                        .get_end(i)
-                        .map(|e| format!("{} seconds", (e - self.get_start()).as_secs()))
-                        .unwrap_or_else(|| "some time".to_string()),
-                )
-                .push(", game in progress"),
-            Over(None) => state.push("Game over (draw)"),
-            Over(Some(i)) => {
-                let id = self.get_user_id(i);
+        let mut state = match self.progress {
+            Waiting => tr!(lang, "state.waiting"),
+            Started => tr!(lang, "state.started"),
+            Ending(i) => match self.get_end(i) {
+                Some(e) => tr!(lang, "state.ending", player = i, seconds = (e - self.get_start()).as_secs()),
+                None => tr!(lang, "state.ending_unknown", player = i),
+            },
+            Over(None) => tr!(lang, "state.draw"),
+            Over(Some(i)) if want_scores => {
                 let scores = self.get_score();
-                state.push("Game over");
-                if want_scores {
-                    state.push("(winner: ").user(id);
-                    write!(state.0, ", score: {}:{})", scores[i], scores[1 - i]).unwrap();
-                }
-                &mut state
+                tr!(lang, "state.over_scores",
+                    winner = i18n::user(self.get_user_id(i)), score = scores[i], enemy_score = scores[1 - i])
             }
-        }.build()
+            Over(Some(_)) => tr!(lang, "state.over"),
+        };
+        // Handicaps matter for as long as the game goes on
+        if let Some(h) = self.render_handicaps(lang) {
+            state.push('\n');
+            state.push_str(&h);
+        }
+        state
     }
 
     pub fn render_keyboard(&self, index: usize) -> String {
@@ -279,33 +356,116 @@ impl GameMP {
                     Some(MatchLetter::Close) => ":yellow_square: ",
                     Some(MatchLetter::Exact) => ":green_square: ",
                 };
-                out.push_str(&emoji_str);
+                out.push_str(emoji_str);
             }
             out.push('\n');
         }
         out
     }
 
-    // Render views side by side, separated with `separator`.
-    pub fn render_views(&self, separator: &str) -> String {
-        self.render_view_color(0)
-            .split('\n')
-            .zip(self.render_view_color(1).split('\n'))
-            .map(|(a, b)| [a, b].join(separator))
+    // Like render_view_color, but only the match squares, so spectators can't
+    // pass the letters on to the players.
+    pub fn render_view_letterless(&self, index: usize) -> String {
+        let empty_line: String = (0..self.get_word_length()).map(|_| ":white_large_square:").collect();
+        let side = &self.side[index];
+
+        (0..self.get_guess_limit(index))
+            .map(|i| match side.guesses.get(i) {
+                Some(row) => row.1.iter().map(|m| match m {
+                    MatchLetter::Null => ":black_large_square:",
+                    MatchLetter::Close => ":yellow_square:",
+                    MatchLetter::Exact => ":green_square:",
+                }).collect(),
+                None => empty_line.clone(),
+            })
             .collect::<Vec<_>>()
             .join("\n")
     }
 
+    // Render views side by side, separated with `separator`.
+    pub fn render_views(&self, separator: &str) -> String {
+        side_by_side(&self.render_view_color(0), &self.render_view_color(1), separator)
+    }
+
+    // What spectators see: letters are only revealed once the game is over.
+    pub fn render_views_spectator(&self, separator: &str) -> String {
+        if matches!(self.progress, GameProgress::Over(_)) {
+            return self.render_views(separator);
+        }
+        side_by_side(&self.render_view_letterless(0), &self.render_view_letterless(1), separator)
+    }
+
+    // Start watching, or update the prediction of someone already watching.
+    // Predictions are only taken until the first player finishes.
+    pub fn watch(&mut self, id: UserId, prediction: Option<UserId>) -> CmdResult<()> {
+        if self.match_user(id).is_some() {
+            return Err(CmdError::WatchedOwnGame);
+        }
+        let prediction = match prediction {
+            Some(p) => {
+                if !matches!(self.progress, GameProgress::Waiting | GameProgress::Started) {
+                    return Err(CmdError::PredictionClosed);
+                }
+                Some(self.match_user(p).ok_or(CmdError::BadPrediction)?)
+            }
+            None => None,
+        };
+        match self.watchers.iter_mut().find(|w| w.id == id) {
+            Some(w) => {
+                if prediction.is_some() {
+                    w.prediction = prediction;
+                }
+            }
+            None => self.watchers.push(Watcher { id, view: None, prediction }),
+        }
+        Ok(())
+    }
+
+    // Remember where a spectator's live view was posted.
+    pub fn set_watch_view(&mut self, id: UserId, view: (ChannelId, MessageId)) {
+        if let Some(w) = self.watchers.iter_mut().find(|w| w.id == id) {
+            w.view = Some(view);
+        }
+    }
+
+    pub fn get_watchers(&self) -> &[Watcher] {
+        &self.watchers
+    }
+
+    // Handicaps in effect, if any. A first letter is only shown once the word is known.
+    pub fn render_handicaps(&self, lang: Lang) -> Option<String> {
+        let mut out = serenity::MessageBuilder::new();
+        for (i, h) in self.handicap.iter().enumerate().filter(|(_, h)| !h.is_none()) {
+            let mut parts = Vec::new();
+            if h.extra_guesses > 0 {
+                parts.push(tr!(lang, "handicap.guesses", n = h.extra_guesses));
+            }
+            if h.time_bonus > 0 && matches!(self.variant, GameVariant::Timed) {
+                parts.push(tr!(lang, "handicap.seconds", n = h.time_bonus));
+            }
+            if h.first_letter {
+                parts.push(match self.side[i].baseword.chars().next() {
+                    Some(c) => tr!(lang, "handicap.first_letter", letter = c.to_uppercase()),
+                    None => tr!(lang, "handicap.first_letter_hidden"),
+                });
+            }
+            out.user(self.get_user_id(i)).push(": ").push(parts.join(", ")).push("\n");
+        }
+        let out = out.build();
+        if out.is_empty() { None } else { Some(out) }
+    }
+
     // Getter methods.
     pub fn get_start(&self) -> Instant {
         self.start
     }
     
-    pub fn is_expiring(&self) -> bool {
+    // Timed games are cut short once they run for longer than `expiry`.
+    pub fn is_expiring(&self, expiry: Duration) -> bool {
         if matches!(self.variant, GameVariant::TurnBased) {
             return false;
         }
-        self.get_start() + crate::constants::TIMED_GAME_EXPIRY <= Instant::now()
+        self.get_start() + expiry <= Instant::now()
     }
 
     pub fn get_end(&self, index: usize) -> Option<Instant> {
@@ -324,6 +484,10 @@ impl GameMP {
         self.max_guesses
     }
 
+    pub fn get_handicap(&self, index: usize) -> Handicap {
+        self.handicap[index]
+    }
+
     pub fn get_progress(&self) -> &GameProgress {
         &self.progress
     }
@@ -331,6 +495,62 @@ impl GameMP {
     pub fn get_user_id(&self, index: usize) -> UserId {
         self.side[index].id
     }
+
+    pub fn get_variant(&self) -> GameVariant {
+        self.variant
+    }
+
+    pub fn get_guild(&self) -> Option<GuildId> {
+        self.guild
+    }
+
+    pub fn get_guesses(&self, index: usize) -> impl Iterator<Item = &str> {
+        self.side[index].guesses.iter().map(|(w, _)| w.as_str())
+    }
+
+    // Feedback on each guess, without the letters.
+    pub fn get_matches(&self, index: usize) -> impl Iterator<Item = &[MatchLetter]> {
+        self.side[index].guesses.iter().map(|(_, m)| m.as_slice())
+    }
+
+    // Snapshot of a started game for the archive, also taken when it ends early.
+    pub fn archive(&self, id: super::GameId) -> super::ArchivedGame {
+        super::ArchivedGame {
+            id,
+            players: [0, 1].map(|i| self.get_user_id(i)),
+            words: [0, 1].map(|i| self.get_baseword(i).to_string()),
+            guesses: [0, 1].map(|i| self.get_guesses(i).map(|g| g.to_string()).collect()),
+            variant: self.variant,
+            guild: self.guild,
+            handicaps: self.handicap,
+            scores: self.score,
+            finished: matches!(self.progress, GameProgress::Over(_)),
+            winner: match self.progress {
+                GameProgress::Over(winner) => winner,
+                _ => None,
+            },
+            ended: crate::data::audit::unix_secs(SystemTime::now()),
+        }
+    }
+}
+
+// Seconds each side finished ahead of the other, rounded up, from how long they took.
+fn time_advantage(spans: [Duration; 2]) -> [u64; 2] {
+    // The last of the two ends
+    let max_end = *spans.iter().max().unwrap();
+    // Duration to add before as_secs to achieve "rounding up" behavior
+    // Equal to one second minus one smallest unit of duration (ns)
+    let near_second = Duration::from_nanos(999_999_999);
+    spans.map(|s| (max_end - s + near_second).as_secs())
+}
+
+// Join two views line by line. Views may differ in length when handicaps add guesses.
+fn side_by_side(a: &str, b: &str, separator: &str) -> String {
+    let (a, b): (Vec<_>, Vec<_>) = (a.split('\n').collect(), b.split('\n').collect());
+    (0..a.len().max(b.len()))
+        .map(|i| [a.get(i).copied().unwrap_or(""), b.get(i).copied().unwrap_or("")].join(separator))
+        .collect::<Vec<_>>()
+        .join("\n")
 }
 
 #[cfg(test)]
@@ -344,17 +564,17 @@ mod test {
     fn basic_game() {
         let u1 = UserId(1011);
         let u2 = UserId(1013);
-        let mut game = GameMP::create(u1, u2, "north".to_string(), GameVariant::Timed);
+        let mut game = GameMP::create(u1, u2, "north".to_string(), GameVariant::Timed, None);
         assert!(matches!(game.match_user(u2), Some(1)));
         assert!(matches!(game.match_user(u1), Some(0)));
-        assert!(matches!(game.match_user(UserId(1012)), None));
+        assert!(game.match_user(UserId(1012)).is_none());
 
         assert!(matches!(game.get_progress(), GameProgress::Waiting));
         game.respond("slide".to_string(), u2).unwrap();
         assert!(matches!(game.get_progress(), GameProgress::Started));
 
-        assert_eq!(game.send_guess(0, "tower".to_string()), true);
-        assert_eq!(game.send_guess(1, "trial".to_string()), true);
+        assert!(game.send_guess(0, "tower".to_string()));
+        assert!(game.send_guess(1, "trial".to_string()));
         println!(
             "Game state:\n{}",
             game.render_views(constants::WORDUEL_VIEWSEP)
@@ -390,21 +610,99 @@ mod test {
     }
 
     #[test]
+    #[allow(clippy::inconsistent_digit_grouping)]
     fn rejections() {
         let u1 = UserId(118_999_881_999_119_7253);
         let u2 = UserId(1_800_434_2637);
-        let mut game = GameMP::create(u1, u2, "ounce".to_string(), GameVariant::Timed);
+        let mut game = GameMP::create(u1, u2, "ounce".to_string(), GameVariant::Timed, None);
         assert!(matches!(game.get_progress(), GameProgress::Waiting));
         game.respond("scout".to_string(), u2).unwrap();
         assert!(matches!(game.get_progress(), GameProgress::Started));
         let view1 = game.render_views(constants::WORDUEL_VIEWSEP);
 
-        assert_eq!(game.send_guess(0, "quince".to_string()), false);
-        assert_eq!(game.send_guess(1, "rows".to_string()), false);
+        assert!(!game.send_guess(0, "quince".to_string()));
+        assert!(!game.send_guess(1, "rows".to_string()));
 
-        assert_eq!(game.send_guess(2, "steed".to_string()), false);
+        assert!(!game.send_guess(2, "steed".to_string()));
 
         assert_eq!(game.render_views(constants::WORDUEL_VIEWSEP), view1);
         assert!(matches!(game.get_progress(), GameProgress::Started));
     }
+
+    #[test]
+    fn spectators() {
+        let (u1, u2, u3) = (UserId(71), UserId(72), UserId(73));
+        let mut game = GameMP::create(u1, u2, "north".to_string(), GameVariant::Timed, None);
+        assert!(matches!(game.watch(u1, None), Err(CmdError::WatchedOwnGame)));
+        assert!(matches!(game.watch(u3, Some(u3)), Err(CmdError::BadPrediction)));
+        game.watch(u3, Some(u2)).unwrap();
+        game.watch(u3, None).unwrap();
+        assert_eq!(game.get_watchers().len(), 1);
+        assert_eq!(game.get_watchers()[0].prediction, Some(1));
+
+        game.respond("slide".to_string(), u2).unwrap();
+        assert!(game.send_guess(0, "tower".to_string()));
+        // No letters until the game is over
+        let view = game.render_views_spectator(constants::WORDUEL_VIEWSEP);
+        assert!(!view.contains("regional_indicator"));
+        assert_eq!(view.lines().count(), game.get_max_guesses());
+
+        assert!(game.send_guess(0, "slide".to_string()));
+        assert!(matches!(game.watch(u3, Some(u1)), Err(CmdError::PredictionClosed)));
+        for w in ["tower", "north"] {
+            assert!(game.send_guess(1, w.to_string()));
+        }
+        assert!(matches!(game.get_progress(), GameProgress::Over(_)));
+        assert!(game.render_views_spectator(constants::WORDUEL_VIEWSEP).contains("regional_indicator"));
+    }
+
+    #[test]
+    fn handicaps() {
+        let (u1, u2) = (UserId(81), UserId(82));
+        let mut game = GameMP::create(u1, u2, "north".to_string(), GameVariant::Timed, None);
+        let help = Handicap { extra_guesses: 2, time_bonus: 0, first_letter: true };
+        game.set_handicap(1, help).unwrap();
+        assert!(game.render_handicaps(Lang::En).unwrap().contains("**N**"));
+        game.respond("slide".to_string(), u2).unwrap();
+        assert!(game.set_handicap(0, help).is_err());
+        assert_eq!(game.render_view_color(1).lines().count(), 2 * (game.get_max_guesses() + 2));
+
+        assert!(game.send_guess(0, "slide".to_string()));
+        // Past the shared limit, still going
+        for _ in 0..game.get_max_guesses() {
+            assert!(game.send_guess(1, "tower".to_string()));
+        }
+        assert!(matches!(game.get_progress(), GameProgress::Ending(0)));
+        assert!(game.send_guess(1, "north".to_string()));
+        assert!(matches!(game.get_progress(), GameProgress::Over(Some(0))));
+        // Extra guesses earn nothing, and both handicaps cost what they're worth
+        assert_eq!(game.get_score()[1], 0);
+    }
+
+    #[test]
+    fn handicap_scores() {
+        let (u1, u2) = (UserId(83), UserId(84));
+        // Both find the word with their second guess, side 0 ten seconds before side 1
+        let play = |help: Handicap| {
+            let mut game = GameMP::create(u1, u2, "north".to_string(), GameVariant::Timed, None);
+            game.set_handicap(1, help).unwrap();
+            game.respond("slide".to_string(), u2).unwrap();
+            for w in ["tower", "slide"] {
+                assert!(game.send_guess(0, w.to_string()));
+            }
+            for w in ["tower", "north"] {
+                assert!(game.send_guess(1, w.to_string()));
+            }
+            game.end = [Some(game.start + Duration::from_secs(20)), Some(game.start + Duration::from_secs(30))];
+            let raw = game.calculate_scores();
+            (raw, *game.get_score())
+        };
+        let none = Handicap::default();
+        // 10 seconds ahead, and 5 guesses to spare of 6 at 3 points each
+        assert_eq!(play(none), ([25, 15], [25, 15]));
+        assert_eq!(play(Handicap { first_letter: true, ..none }), ([25, 15], [25, 12]));
+        assert_eq!(play(Handicap { extra_guesses: 2, ..none }), ([25, 15], [25, 9]));
+        // On the clock side 1 finished 20 seconds first and wins, but keeps no more than without help
+        assert_eq!(play(Handicap { time_bonus: 30, ..none }), ([15, 35], [15, 15]));
+    }
 }
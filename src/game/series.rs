@@ -0,0 +1,118 @@
+use poise::serenity_prelude::UserId;
+use crate::i18n::{self, tr, Lang};
+use super::{GameId, GameVariant};
+
+// Best-of-N games between the same pair.
+// Players take turns challenging, the series starter goes first.
+#[derive(Debug, Clone)]
+pub struct Series {
+    players: [UserId; 2],
+    best_of: usize,
+    variant: GameVariant,
+    wins: [usize; 2],
+    played: Vec<GameId>,
+    current: Option<GameId>,
+}
+
+impl Series {
+    pub fn new(players: [UserId; 2], best_of: usize, variant: GameVariant) -> Series {
+        Series {
+            players,
+            best_of,
+            variant,
+            wins: [0, 0],
+            played: Vec::new(),
+            current: None,
+        }
+    }
+
+    pub fn get_players(&self) -> [UserId; 2] {
+        self.players
+    }
+
+    pub fn get_variant(&self) -> GameVariant {
+        self.variant
+    }
+
+    pub fn get_current(&self) -> Option<GameId> {
+        self.current
+    }
+
+    pub fn has_game(&self, id: GameId) -> bool {
+        self.current == Some(id) || self.played.contains(&id)
+    }
+
+    // Who challenges next. Games that never started don't count.
+    pub fn next_picker(&self) -> UserId {
+        self.players[self.played.len() % 2]
+    }
+
+    pub fn set_current(&mut self, id: GameId) {
+        self.current = Some(id);
+    }
+
+    // The current game ended before it started, the same player picks again.
+    pub fn void(&mut self, id: GameId) {
+        if self.current == Some(id) {
+            self.current = None;
+        }
+    }
+
+    // The current game is over, `winner` being None for a draw.
+    pub fn record(&mut self, id: GameId, winner: Option<UserId>) {
+        if self.current != Some(id) {
+            return;
+        }
+        self.current = None;
+        self.played.push(id);
+        if let Some(i) = winner.and_then(|w| self.players.iter().position(|&p| p == w)) {
+            self.wins[i] += 1;
+        }
+    }
+
+    pub fn winner(&self) -> Option<UserId> {
+        (0..2).find(|&i| self.wins[i] > self.best_of / 2).map(|i| self.players[i])
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.winner().is_some() || self.played.len() >= self.best_of
+    }
+
+    pub fn render(&self, lang: Lang) -> String {
+        tr!(lang, "series.standing",
+            best_of = self.best_of,
+            a = i18n::user(self.players[0]),
+            wins_a = self.wins[0],
+            wins_b = self.wins[1],
+            b = i18n::user(self.players[1]),
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn best_of_three() {
+        let (a, b) = (UserId(1), UserId(2));
+        let mut series = Series::new([a, b], 3, GameVariant::Timed);
+        assert_eq!(series.next_picker(), a);
+        series.set_current(10);
+        series.void(10);
+        assert_eq!(series.next_picker(), a);
+
+        series.set_current(11);
+        series.record(11, Some(b));
+        assert_eq!(series.next_picker(), b);
+        series.record(11, Some(b)); // not current any more
+        series.set_current(12);
+        series.record(12, None);
+        assert!(!series.is_over());
+        series.set_current(13);
+        series.record(13, Some(b));
+        assert_eq!(series.winner(), Some(b));
+        assert!(series.is_over());
+        assert!(series.has_game(12) && !series.has_game(10));
+    }
+}
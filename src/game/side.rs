@@ -49,7 +49,7 @@ impl GameSide {
     // See if the last guess is an exact match for every letter.
     // That's the win condition.
     pub fn victorious(&self) -> bool {
-        self.guesses.last().map_or(false, |g| {
+        self.guesses.last().is_some_and(|g| {
             g.1 // within match vector
                 .iter()
                 .all(|&e| e == MatchLetter::Exact) // test if all matches exact
@@ -0,0 +1,179 @@
+// Translations of user-facing messages.
+//
+// Each language has a catalog (en.txt, pl.txt) of `key = text` lines, `#` starting a comment.
+// Texts take named arguments written as `{name}`, and `\n` for line breaks.
+// Messages about a count have one line per plural category instead
+// (`key.one`, `key.few`, `key.many`, `key.other`), chosen by the `n` argument.
+// Keys missing from a catalog fall back to English.
+
+use poise::serenity_prelude as serenity;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::OnceLock;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize, poise::ChoiceParameter)]
+pub enum Lang {
+    #[default]
+    #[name = "English"]
+    En,
+    #[name = "Polski"]
+    Pl,
+}
+
+type Catalog = HashMap<&'static str, String>;
+
+impl Lang {
+    pub const ALL: [Lang; 2] = [Lang::En, Lang::Pl];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Lang::En => "English",
+            Lang::Pl => "Polski",
+        }
+    }
+
+    fn catalog(&self) -> &'static Catalog {
+        static EN: OnceLock<Catalog> = OnceLock::new();
+        static PL: OnceLock<Catalog> = OnceLock::new();
+        match self {
+            Lang::En => EN.get_or_init(|| parse(include_str!("en.txt"))),
+            Lang::Pl => PL.get_or_init(|| parse(include_str!("pl.txt"))),
+        }
+    }
+
+    // CLDR plural category of a count.
+    fn plural(&self, n: u64) -> &'static str {
+        match self {
+            Lang::En => if n == 1 {"one"} else {"other"},
+            Lang::Pl => match (n % 10, n % 100) {
+                _ if n == 1 => "one",
+                (2..=4, m) if !(12..=14).contains(&m) => "few",
+                _ => "many",
+            },
+        }
+    }
+
+    // The text for `key`, without arguments filled in.
+    fn lookup(&self, key: &str, n: Option<u64>) -> Option<&'static str> {
+        let catalog = self.catalog();
+        let text = match (catalog.get(key), n) {
+            (Some(text), _) => text,
+            (None, Some(n)) => catalog.get(format!("{}.{}", key, self.plural(n)).as_str())
+                .or_else(|| catalog.get(format!("{}.other", key).as_str()))?,
+            (None, None) => return None,
+        };
+        Some(text.as_str())
+    }
+}
+
+fn parse(source: &'static str) -> Catalog {
+    source.lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .filter_map(|l| l.split_once('='))
+        .map(|(key, text)| (key.trim(), text.trim().replace("\\n", "\n")))
+        .collect()
+}
+
+// Message `key` in `lang`, with `{name}` replaced by the matching argument.
+// Use the tr! macro rather than calling this.
+pub fn message(lang: Lang, key: &str, args: &[(&str, &dyn fmt::Display)]) -> String {
+    let n = args.iter()
+        .find(|(name, _)| *name == "n")
+        .and_then(|(_, v)| v.to_string().parse().ok());
+    let text = match lang.lookup(key, n).or_else(|| Lang::En.lookup(key, n)) {
+        Some(text) => text,
+        None => {
+            tracing::warn!(key, "missing message");
+            return key.to_string();
+        }
+    };
+    let mut out = text.to_string();
+    for (name, value) in args {
+        out = out.replace(&format!("{{{}}}", name), &value.to_string());
+    }
+    out
+}
+
+// tr!(lang, "key", name = value, ...) formats a message, see `message`.
+macro_rules! tr {
+    ($lang:expr, $key:expr) => {
+        $crate::i18n::message($lang, $key, &[])
+    };
+    // The arguments are borrowed in a statement of their own, so they don't outlive it
+    // and messages can be formatted right before an await
+    ($lang:expr, $key:expr, $($name:ident = $value:expr),+ $(,)?) => {{
+        let text = $crate::i18n::message($lang, $key, &[$((stringify!($name), &$value as &dyn std::fmt::Display)),+]);
+        text
+    }};
+}
+pub(crate) use tr;
+
+// Mention of a user, for message arguments.
+pub fn user(id: serenity::UserId) -> serenity::Mention {
+    serenity::Mention::from(id)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_catalogs_match() {
+        let en = Lang::En.catalog();
+        for lang in Lang::ALL {
+            let catalog = lang.catalog();
+            for (key, text) in catalog {
+                let base = key.rsplit_once('.')
+                    .filter(|(_, c)| ["one", "few", "many", "other"].contains(c))
+                    .map(|(b, _)| b);
+                // Plural forms differ between languages, only the base needs to exist
+                let english = match base {
+                    Some(b) => en.get(key).or_else(|| en.get(format!("{}.other", b).as_str())),
+                    None => en.get(key),
+                };
+                let english = english.unwrap_or_else(|| panic!("{:?} has {} missing in English", lang, key));
+                let args = |t: &str| {
+                    let mut args: Vec<String> = t.split('{').skip(1)
+                        .filter_map(|s| s.split_once('}').map(|(a, _)| a.to_string()))
+                        .collect();
+                    args.sort();
+                    args.dedup();
+                    args
+                };
+                let mut wanted = args(english);
+                let mut got = args(text);
+                // Plural forms may leave the count out
+                wanted.retain(|a| a != "n");
+                got.retain(|a| a != "n");
+                assert_eq!(got, wanted, "{:?} {} has other arguments than in English", lang, key);
+            }
+            // And everything English has is translated
+            for key in en.keys() {
+                assert!(lang.lookup(key, None).is_some() || key.rsplit_once('.').is_some_and(|(b, _)| {
+                    [0, 1, 2, 5, 12, 22].iter().all(|&n| lang.lookup(b, Some(n)).is_some())
+                }), "{:?} lacks {}", lang, key);
+            }
+        }
+    }
+
+    #[test]
+    fn test_plurals() {
+        assert_eq!(tr!(Lang::En, "words.allowed", n = 1), "Allowed 1 word");
+        assert_eq!(tr!(Lang::En, "words.allowed", n = 3), "Allowed 3 words");
+        assert_eq!(tr!(Lang::Pl, "words.allowed", n = 1), "Dozwolono 1 słowo");
+        assert_eq!(tr!(Lang::Pl, "words.allowed", n = 3), "Dozwolono 3 słowa");
+        assert_eq!(tr!(Lang::Pl, "words.allowed", n = 5), "Dozwolono 5 słów");
+        assert_eq!(tr!(Lang::Pl, "words.allowed", n = 13), "Dozwolono 13 słów");
+        assert_eq!(tr!(Lang::Pl, "words.allowed", n = 22), "Dozwolono 22 słowa");
+    }
+
+    #[test]
+    fn test_arguments() {
+        let id = serenity::UserId(7);
+        assert_eq!(tr!(Lang::En, "invite.withdrawn_public", user = user(id), challenger = user(id)),
+            "<@7>, the challenge from <@7> has been withdrawn.");
+        assert_eq!(tr!(Lang::Pl, "no.such.key"), "no.such.key");
+    }
+}
@@ -4,17 +4,24 @@ pub use data::*;
 use std::env;
 use std::sync::Arc;
 
+mod cli;
 mod commands;
+mod config;
 mod constants;
 mod dict;
 mod game;
+mod i18n;
 mod data;
+mod dashboard;
+mod metrics;
 // use serde::{Deserialize, Serialize};
 
 pub type Error = Box<dyn std::error::Error + Send + Sync>;
 pub type Context<'a> = poise::Context<'a, Arc<CtxData>, Error>;
 
 const TOKEN_VARNAME: &str = "DISCORD_TOKEN";
+// Address to serve metrics on, e.g. 127.0.0.1:9100. No endpoint if unset.
+const METRICS_VARNAME: &str = "WORDCLASH_METRICS_ADDR";
 
 #[poise::command(prefix_command, track_edits, slash_command)]
 async fn help(
@@ -37,7 +44,7 @@ This is an example bot made to showcase features of my custom Discord bot framew
     Ok(())
 }
 
-#[poise::command(prefix_command, slash_command, hide_in_help)]
+#[poise::command(prefix_command, slash_command, hide_in_help, check = "commands::util::perms::owner")]
 async fn register(ctx: Context<'_>) -> Result<(), Error> {
     poise::builtins::register_application_commands_buttons(ctx).await?;
     Ok(())
@@ -45,7 +52,29 @@ async fn register(ctx: Context<'_>) -> Result<(), Error> {
 
 #[tokio::main]
 async fn main() {
+    // RUST_LOG overrides this, e.g. RUST_LOG=project_wordclash=debug
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            tracing_subscriber::EnvFilter::try_from_default_env()
+                .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new("warn,project_wordclash=info")),
+        )
+        .init();
+    let args: Vec<String> = env::args().skip(1).collect();
+    if let Some(res) = cli::run(&args) {
+        if let Err(e) = res {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
     let token_errstr: String = format!("Missing token variable ({})", TOKEN_VARNAME);
+    let config = match config::Config::load() {
+        Ok(c) => c,
+        Err(e) => {
+            tracing::error!("{}", e);
+            std::process::exit(1);
+        }
+    };
 
     let options = poise::FrameworkOptions {
         commands: vec![
@@ -53,33 +82,104 @@ async fn main() {
             register(),
             commands::dict::lookup(),
             commands::dict::testmatch(),
+            commands::dict::explore(),
             commands::timedgame::challenge(),
+            commands::timedgame::open(),
+            commands::timedgame::open_cancel(),
             commands::timedgame::accept(),
+            commands::timedgame::reject(),
+            commands::timedgame::cancel(),
             commands::timedgame::send(),
             commands::timedgame::forfeit(),
             commands::timedgame::keyboard(),
             commands::turngame::challenge(),
+            commands::turngame::open(),
             commands::turngame::accept(),
+            commands::turngame::reject(),
+            commands::turngame::cancel(),
             commands::turngame::send(),
             commands::turngame::remind(),
             commands::turngame::forfeit(),
             commands::turngame::keyboard(),
+            commands::invites::invites(),
+            commands::notify::notify(),
+            commands::privacy::privacy(),
+            commands::privacy::block(),
+            commands::privacy::friend(),
+            commands::lang::lang(),
+            commands::lang::lang_server(),
+            commands::settings::config(),
+            commands::settings::admin_role(),
+            commands::moderation::moderation(),
+            commands::export::export(),
+            commands::export::import(),
+            commands::series::series(),
+            commands::queue::queue(),
+            commands::queue::queue_leave(),
+            commands::watch::watch(),
             commands::scores::leaderboard(),
+            commands::analyze::analyze(),
+            commands::words::words(),
             commands::misc::roll_dice(),
         ],
+        listener: commands::series::listener,
+        on_error: commands::util::errors::on_error,
+        command_check: Some(commands::util::perms::rate_limit),
+        pre_command: metrics::command_started,
+        post_command: metrics::command_succeeded,
+        owners: config.owners.iter().map(|&id| serenity::UserId(id)).collect(),
         prefix_options: poise::PrefixFrameworkOptions {
-            prefix: Some("!".into()),
+            prefix: Some(config.prefix.clone()),
             ..Default::default()
         },
         ..Default::default()
     };
 
-    let ctxdata = Arc::new(CtxData::new(dict::load_dictionary()));
-    let fwdata = ctxdata.clone();
+    let dictionary = match dict::load_dictionary() {
+        Ok(d) => d,
+        Err(e) => {
+            tracing::error!("{}", e);
+            std::process::exit(1);
+        }
+    };
+    let defs = match dict::definitions::load_definitions() {
+        Ok(d) => d,
+        Err(e) => {
+            tracing::error!("{}", e);
+            std::process::exit(1);
+        }
+    };
+    if defs.is_empty() {
+        tracing::warn!("No word definitions loaded, set {} to enable them (see scripts/definitions.py)", dict::definitions::DEFS_VARNAME);
+    }
+    let guilds = match data::guilds::GuildManager::load() {
+        Ok(g) => g,
+        Err(e) => {
+            tracing::error!("{}", e);
+            std::process::exit(1);
+        }
+    };
+    let fwdata = Arc::new(CtxData::new(config, dictionary, defs, guilds));
+    match env::var(METRICS_VARNAME).map(|a| a.parse()) {
+        Ok(Ok(addr)) => {
+            tokio::task::spawn(metrics::serve(addr, fwdata.clone()));
+        }
+        Ok(Err(e)) => {
+            tracing::error!("Invalid {}: {}", METRICS_VARNAME, e);
+            std::process::exit(1);
+        }
+        Err(_) => {}
+    }
     let framework = poise::Framework::build()
         .token(env::var(TOKEN_VARNAME).expect(&token_errstr))
-        .user_data_setup(move |_ctx, _ready, _fw| {
+        .user_data_setup(move |ctx, _ready, _fw| {
             Box::pin(async move {
+                // These talk to Discord on their own, so they start once connected
+                tokio::task::spawn(commands::queue::matchmaking(fwdata.clone(), ctx.http.clone()));
+                tokio::task::spawn(commands::invites::cleanup(fwdata.clone(), ctx.http.clone()));
+                if let Some(addr) = fwdata.config.dashboard.address {
+                    tokio::task::spawn(dashboard::serve(addr, fwdata.clone(), ctx.cache.clone(), ctx.http.clone()));
+                }
                 Ok(fwdata)
             })
         })
@@ -87,23 +187,5 @@ async fn main() {
         .intents(
             serenity::GatewayIntents::non_privileged() | serenity::GatewayIntents::MESSAGE_CONTENT,
         );
-
-    tokio::task::spawn(async move {
-        let mut cleanup_timer = tokio::time::interval(constants::CLEANUP_INTERVAL);
-        loop {
-            use std::time::SystemTime;
-            cleanup_timer.tick().await;
-            let mut mplock = ctxdata.mpgames.write().await;
-            { // Separate scope to hold udlock for less time
-                let mut udlock = ctxdata.userdata.write().await;
-                for v in udlock.values_mut() {
-                    v.player.clean_invites_then(SystemTime::now(), |invite| {
-                        mplock.remove(&invite.game);
-                    });
-                };
-            }
-            mplock.retain(|_, g| !g.is_expiring());
-        }
-    });
     framework.run().await.unwrap();
 }
@@ -0,0 +1,217 @@
+// Counters behind the optional metrics endpoint, in the Prometheus text format.
+//
+// Counters are kept here as commands run, gauges (games, invites, the queue)
+// are read from CtxData when scraped.
+
+use crate::data::CtxData;
+use crate::Context;
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Method, Request, Response, Server, StatusCode};
+use std::collections::{BTreeMap, VecDeque};
+use std::convert::Infallible;
+use std::fmt::Write;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+// Upper bounds of the command latency histogram, in seconds.
+const LATENCY_BUCKETS: [f64; 8] = [0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+// Window of the guesses per minute gauge.
+const GUESS_WINDOW: Duration = Duration::from_secs(60);
+
+#[derive(Default)]
+struct Latency {
+    buckets: [u64; LATENCY_BUCKETS.len()], // cumulative, like Prometheus wants them
+    count: u64,
+    sum: f64,
+    errors: u64,
+}
+
+#[derive(Default)]
+struct Counters {
+    commands: BTreeMap<String, Latency>,
+    guesses: u64,
+    recent_guesses: VecDeque<Instant>,
+}
+
+// Shared by all commands. The mutex is never held across an await.
+#[derive(Default)]
+pub struct Metrics {
+    counters: Mutex<Counters>,
+}
+
+// What's going on right now, see CtxData::gauges.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Gauges {
+    pub waiting_games: usize,
+    pub active_games: usize,
+    pub pending_invites: usize,
+    pub open_challenges: usize,
+    pub queued_players: usize,
+}
+
+impl Metrics {
+    pub fn command(&self, name: &str, took: Duration, ok: bool) {
+        let mut counters = self.counters.lock().unwrap();
+        let latency = counters.commands.entry(name.to_string()).or_default();
+        let secs = took.as_secs_f64();
+        for (bucket, bound) in latency.buckets.iter_mut().zip(LATENCY_BUCKETS) {
+            if secs <= bound {
+                *bucket += 1;
+            }
+        }
+        latency.count += 1;
+        latency.sum += secs;
+        if !ok {
+            latency.errors += 1;
+        }
+    }
+
+    pub fn guess(&self, now: Instant) {
+        let mut counters = self.counters.lock().unwrap();
+        counters.guesses += 1;
+        counters.recent_guesses.push_back(now);
+        prune(&mut counters.recent_guesses, now);
+    }
+
+    pub fn render(&self, gauges: Gauges, now: Instant) -> String {
+        let mut counters = self.counters.lock().unwrap();
+        prune(&mut counters.recent_guesses, now);
+
+        let mut out = String::new();
+        let mut gauge = |name: &str, help: &str, value: usize| {
+            let _ = writeln!(out, "# HELP {} {}\n# TYPE {} gauge\n{} {}", name, help, name, name, value);
+        };
+        gauge("wordclash_games_waiting", "Games waiting for the challenged player.", gauges.waiting_games);
+        gauge("wordclash_games_active", "Games being played.", gauges.active_games);
+        gauge("wordclash_invites_pending", "Invites not yet accepted, rejected or expired.", gauges.pending_invites);
+        gauge("wordclash_open_challenges", "Open challenges nobody took up yet.", gauges.open_challenges);
+        gauge("wordclash_queue_players", "Players waiting for a match.", gauges.queued_players);
+        gauge("wordclash_guesses_per_minute", "Guesses sent in the last minute.", counters.recent_guesses.len());
+
+        let _ = writeln!(out, "# HELP wordclash_guesses_total Guesses sent.\n# TYPE wordclash_guesses_total counter");
+        let _ = writeln!(out, "wordclash_guesses_total {}", counters.guesses);
+
+        let _ = writeln!(out, "# HELP wordclash_command_seconds Time from invocation to the command finishing.");
+        let _ = writeln!(out, "# TYPE wordclash_command_seconds histogram");
+        for (name, latency) in &counters.commands {
+            for (count, bound) in latency.buckets.iter().zip(LATENCY_BUCKETS) {
+                let _ = writeln!(out, "wordclash_command_seconds_bucket{{command=\"{}\",le=\"{}\"}} {}", name, bound, count);
+            }
+            let _ = writeln!(out, "wordclash_command_seconds_bucket{{command=\"{}\",le=\"+Inf\"}} {}", name, latency.count);
+            let _ = writeln!(out, "wordclash_command_seconds_sum{{command=\"{}\"}} {}", name, latency.sum);
+            let _ = writeln!(out, "wordclash_command_seconds_count{{command=\"{}\"}} {}", name, latency.count);
+        }
+        let _ = writeln!(out, "# HELP wordclash_command_errors_total Commands that ended in an error.");
+        let _ = writeln!(out, "# TYPE wordclash_command_errors_total counter");
+        for (name, latency) in &counters.commands {
+            let _ = writeln!(out, "wordclash_command_errors_total{{command=\"{}\"}} {}", name, latency.errors);
+        }
+        out
+    }
+}
+
+// Invocation data of commands, set before they run.
+struct Started(Instant);
+
+// pre_command hook: logs the invocation and starts its clock.
+pub fn command_started(ctx: Context<'_>) -> poise::BoxFuture<'_, ()> {
+    Box::pin(async move {
+        tracing::info!(
+            command = %ctx.command().qualified_name,
+            user = %ctx.author().id,
+            guild = ?ctx.guild_id().map(|g| g.0),
+            channel = %ctx.channel_id(),
+            "command",
+        );
+        ctx.set_invocation_data(Started(Instant::now())).await;
+    })
+}
+
+// post_command hook, only called when the command succeeded.
+pub fn command_succeeded(ctx: Context<'_>) -> poise::BoxFuture<'_, ()> {
+    Box::pin(command_finished(ctx, None))
+}
+
+// Records how long the command took, `error` being the code it failed with.
+pub async fn command_finished(ctx: Context<'_>, error: Option<&str>) {
+    let took = match ctx.invocation_data::<Started>().await {
+        Some(started) => started.0.elapsed(),
+        None => return, // failed before the pre_command hook
+    };
+    let name = &ctx.command().qualified_name;
+    ctx.data().metrics.command(name, took, error.is_none());
+    match error {
+        None => tracing::debug!(command = %name, took_ms = took.as_millis() as u64, "command done"),
+        Some(code) => tracing::info!(command = %name, took_ms = took.as_millis() as u64, code, "command failed"),
+    }
+}
+
+fn prune(recent: &mut VecDeque<Instant>, now: Instant) {
+    while recent.front().is_some_and(|&t| now.duration_since(t) > GUESS_WINDOW) {
+        recent.pop_front();
+    }
+}
+
+// Serve GET /metrics on `addr` for as long as the bot runs.
+// Meant for a local scraper, there's no authentication.
+pub async fn serve(addr: SocketAddr, data: Arc<CtxData>) {
+    let make_service = make_service_fn(move |_| {
+        let data = data.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |req| respond(req, data.clone())))
+        }
+    });
+    let server = match Server::try_bind(&addr) {
+        Ok(builder) => builder.serve(make_service),
+        Err(e) => {
+            tracing::error!(%addr, error = %e, "metrics endpoint not started");
+            return;
+        }
+    };
+    tracing::info!(%addr, "serving metrics");
+    if let Err(e) = server.await {
+        tracing::error!(error = %e, "metrics endpoint stopped");
+    }
+}
+
+async fn respond(req: Request<Body>, data: Arc<CtxData>) -> Result<Response<Body>, Infallible> {
+    let response = match (req.method(), req.uri().path()) {
+        (&Method::GET, "/metrics") => {
+            let gauges = data.gauges().await;
+            Response::builder()
+                .header("Content-Type", "text/plain; version=0.0.4")
+                .body(Body::from(data.metrics.render(gauges, Instant::now())))
+        }
+        _ => Response::builder().status(StatusCode::NOT_FOUND).body(Body::empty()),
+    };
+    Ok(response.unwrap())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let metrics = Metrics::default();
+        let start = Instant::now();
+        metrics.command("wd_send", Duration::from_millis(200), true);
+        metrics.command("wd_send", Duration::from_secs(3), false);
+        metrics.guess(start);
+        metrics.guess(start + Duration::from_secs(30));
+
+        let gauges = Gauges { active_games: 2, pending_invites: 1, ..Default::default() };
+        let text = metrics.render(gauges, start + Duration::from_secs(70));
+        let lines: Vec<&str> = text.lines().collect();
+        assert!(lines.contains(&"wordclash_games_active 2"));
+        assert!(lines.contains(&"wordclash_invites_pending 1"));
+        // The first guess fell out of the window
+        assert!(lines.contains(&"wordclash_guesses_per_minute 1"));
+        assert!(lines.contains(&"wordclash_guesses_total 2"));
+        assert!(lines.contains(&"wordclash_command_seconds_bucket{command=\"wd_send\",le=\"0.1\"} 0"));
+        assert!(lines.contains(&"wordclash_command_seconds_bucket{command=\"wd_send\",le=\"0.25\"} 1"));
+        assert!(lines.contains(&"wordclash_command_seconds_bucket{command=\"wd_send\",le=\"+Inf\"} 2"));
+        assert!(lines.contains(&"wordclash_command_errors_total{command=\"wd_send\"} 1"));
+    }
+}